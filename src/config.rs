@@ -0,0 +1,995 @@
+//! # 配置模块
+//!
+//! 从 TOML、YAML 或 JSON 文件和环境变量加载声明式的服务器配置 (需要启用 `config` feature)
+//!
+//! # 主要组件
+//! - `ServerConfig`: 服务器配置，覆盖监听地址、TLS 证书路径、超时、日志过滤规则和 JWT 密钥
+//! - `ConfigFormat`: 配置文件格式，默认按扩展名判断
+//! - `ConfigError`: 读取、解析或校验配置的错误，校验错误一次性列出所有问题
+//!
+//! # 重新加载
+//! 从文件加载的配置创建服务器时，[`Server::from_config`] 会添加一个重新加载钩子，
+//! 配合 [`Server::with_sighup_reload`] 在收到 `SIGHUP` 时重新读取配置文件 (包括环境变量覆盖)。
+//! 新配置校验失败时保留原来的配置，校验通过后更新空闲超时、日志过滤规则和 IP 列表，
+//! 其余字段的修改记录警告日志，重启后生效。证书文件的内容由服务器在同一次重新加载中重新读取
+//!
+//! # 环境变量
+//! 以 `AXUM_BOOTSTRAP__` 开头的环境变量覆盖配置文件中的值，其余部分按 `__` 分隔为配置路径 (不区分大小写)，
+//! 例如 `AXUM_BOOTSTRAP__TLS__CERT=/etc/app/cert.pem` 覆盖 `tls.cert`。
+//! 值按字符串处理，以 `[` 或 `{` 开头时按 JSON 解析，例如 `AXUM_BOOTSTRAP__LISTEN='["[::]:80", "[::]:8080"]'`
+//!
+//! # 时长
+//! 超时可以写成整数秒，或带单位的字符串，例如 `500ms`、`30s`、`2m`、`1h30m`、`1d`
+//!
+//! # 示例
+//!
+//! ```toml
+//! listen = ["[::]:443"]
+//!
+//! [tls]
+//! cert = "/etc/app/cert.pem"
+//! key = "/etc/app/privkey.pem"
+//!
+//! [http]
+//! listen = ["[::]:80"]
+//! redirect = true
+//!
+//! [timeouts]
+//! idle = "2m"
+//! header_read = "10s"
+//!
+//! [log]
+//! filter = "info,my_app=debug"
+//!
+//! [ip_filter]
+//! allow = ["10.0.0.0/8", "2001:db8::/32"]
+//! deny = ["10.1.2.3"]
+//! ```
+
+use std::{
+    fmt, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use axum::Router;
+use log::{info, warn};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use tokio::sync::broadcast;
+
+use crate::{
+    HttpParam, HttpRedirectParam, Server, TimeoutParam, TlsParam,
+    ip_filter::{IpFilter, IpFilterRules},
+    new_server,
+    reload::{ReloadHook, RuntimeSettings},
+};
+
+/// 覆盖配置的环境变量前缀
+pub const ENV_PREFIX: &str = "AXUM_BOOTSTRAP__";
+
+/// 服务器配置
+///
+/// 所有字段都可以省略，通过 [`ServerConfig::validate`] 或 [`Server::from_config`] 校验
+///
+/// # 字段
+/// - `listen`: TCP 监听地址列表，启用 TLS 时监听 HTTPS。也可以写成逗号分隔的字符串
+/// - `unix_sockets`: Unix domain socket 监听列表
+/// - `tls`: TLS 证书和私钥路径，为 None 时不启用 TLS
+/// - `http`: 启用 TLS 时额外监听的明文 HTTP 地址
+/// - `timeouts`: 超时配置
+/// - `log`: 日志配置
+/// - `jwt`: JWT 配置
+/// - `ip_filter`: 按客户端 IP 过滤连接的允许列表和拒绝列表，为 None 时不过滤
+/// - `source`: 配置文件路径，由 [`ServerConfig::from_file`] 设置，用于重新加载
+///
+/// # 示例
+///
+/// ```no_run
+/// use axum::Router;
+/// use axum_bootstrap::{Server, config::ServerConfig, generate_shutdown_receiver};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let config = ServerConfig::from_file("config.toml")?;
+///     let server = Server::from_config(config, Router::new(), generate_shutdown_receiver())?;
+///     server.run().await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    #[serde(deserialize_with = "one_or_many")]
+    pub listen: Vec<String>,
+    pub unix_sockets: Vec<UnixSocketConfig>,
+    pub tls: Option<TlsConfig>,
+    pub http: Option<HttpConfig>,
+    pub timeouts: TimeoutsConfig,
+    pub log: LogConfig,
+    pub jwt: Option<JwtSettings>,
+    pub ip_filter: Option<IpFilterConfig>,
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+/// Unix domain socket 监听配置
+///
+/// # 字段
+/// - `path`: socket 文件路径
+/// - `mode`: socket 文件权限，字符串按八进制解析 (例如 `"660"`)，为 None 时保持默认权限
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnixSocketConfig {
+    pub path: PathBuf,
+    #[serde(deserialize_with = "file_mode")]
+    pub mode: Option<u32>,
+}
+
+/// TLS 配置
+///
+/// # 字段
+/// - `cert`: TLS 证书文件路径
+/// - `key`: TLS 私钥文件路径
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+}
+
+/// 明文 HTTP 监听配置
+///
+/// # 字段
+/// - `listen`: 明文 HTTP 监听地址列表
+/// - `redirect`: 是否把所有请求重定向到 HTTPS
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    #[serde(deserialize_with = "one_or_many")]
+    pub listen: Vec<String>,
+    #[serde(deserialize_with = "lenient_bool")]
+    pub redirect: bool,
+}
+
+/// 超时配置
+///
+/// 为 None 时使用 [`Server`] 的默认值，参见 [`TimeoutParam`]
+///
+/// # 字段
+/// - `idle`: 连接空闲超时，必须大于 0
+/// - `proxy_header`: PROXY protocol 头读取超时，必须大于 0
+/// - `tls_handshake`: TLS 握手超时，必须大于 0
+/// - `header_read`: 请求头读取超时，为 0 时不限制
+/// - `max_lifetime`: 连接最长存活时间，为 0 时不限制
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    #[serde(deserialize_with = "duration_text")]
+    pub idle: Option<String>,
+    #[serde(deserialize_with = "duration_text")]
+    pub proxy_header: Option<String>,
+    #[serde(deserialize_with = "duration_text")]
+    pub tls_handshake: Option<String>,
+    #[serde(deserialize_with = "duration_text")]
+    pub header_read: Option<String>,
+    #[serde(deserialize_with = "duration_text")]
+    pub max_lifetime: Option<String>,
+}
+
+/// 日志配置
+///
+/// # 字段
+/// - `filter`: 日志过滤规则，语法与 `RUST_LOG` 相同，传给 [`crate::init_log`] 的 `init_with_filter`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub filter: Option<String>,
+}
+
+/// JWT 配置
+///
+/// `Debug` 输出中不包含密钥，打印配置时不会泄露
+///
+/// # 字段
+/// - `secret`: 签名和验证 JWT 的密钥，不能为空
+#[derive(Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtSettings {
+    pub secret: String,
+}
+
+impl fmt::Debug for JwtSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtSettings").field("secret", &"<redacted>").finish()
+    }
+}
+
+/// IP 过滤配置
+///
+/// 规则与 [`IpFilterRules`] 相同，拒绝列表优先，允许列表为空时允许所有不在拒绝列表中的地址
+///
+/// # 字段
+/// - `allow`: 允许的 CIDR 网段或 IP 地址
+/// - `deny`: 拒绝的 CIDR 网段或 IP 地址
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IpFilterConfig {
+    #[serde(deserialize_with = "one_or_many")]
+    pub allow: Vec<String>,
+    #[serde(deserialize_with = "one_or_many")]
+    pub deny: Vec<String>,
+}
+
+/// 配置文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// 按扩展名判断配置文件格式
+    ///
+    /// # 返回
+    /// `.toml`、`.yaml`、`.yml` 或 `.json` 之外的扩展名返回 None
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// 解析为 JSON 值，空文件解析为空对象
+    fn parse(self, content: &str) -> Result<Value, ConfigError> {
+        let value = match self {
+            Self::Toml => toml::from_str(content).map_err(|e| ConfigError::Parse(e.to_string()))?,
+            Self::Yaml => serde_yaml::from_str(content).map_err(|e| ConfigError::Parse(e.to_string()))?,
+            Self::Json => serde_json::from_str(content).map_err(|e| ConfigError::Parse(e.to_string()))?,
+        };
+        Ok(match value {
+            Value::Null => Value::Object(Map::new()),
+            value => value,
+        })
+    }
+}
+
+/// 配置错误
+///
+/// # 变体
+/// - `Io`: 读取配置文件失败
+/// - `Parse`: 配置文件语法错误、未知字段或类型错误
+/// - `Invalid`: 校验失败，包含所有问题
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "read config: {e}"),
+            ConfigError::Parse(msg) => write!(f, "parse config: {msg}"),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid config:")?;
+                for problem in problems {
+                    write!(f, "\n  - {problem}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// 校验后的配置
+struct Resolved {
+    addrs: Vec<SocketAddr>,
+    #[cfg(unix)]
+    unix_sockets: Vec<crate::UnixSocketParam>,
+    tls: Option<TlsParam>,
+    http: Option<HttpParam>,
+    idle: Option<Duration>,
+    timeouts: TimeoutParam,
+    ip_rules: Option<IpFilterRules>,
+}
+
+impl ServerConfig {
+    /// 读取配置文件并应用环境变量覆盖
+    ///
+    /// # 参数
+    /// - `path`: 配置文件路径，格式按扩展名判断
+    ///
+    /// # 返回
+    /// - `Ok(ServerConfig)`: 未经校验的配置
+    /// - `Err(ConfigError)`: 读取或解析失败
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)
+            .ok_or_else(|| ConfigError::Parse(format!("{}: unknown format, expect .toml, .yaml, .yml or .json", path.display())))?;
+        let content = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(io::Error::new(e.kind(), format!("{}: {e}", path.display()))))?;
+        let value = format.parse(&content).map_err(|e| match e {
+            ConfigError::Parse(msg) => ConfigError::Parse(format!("{}: {msg}", path.display())),
+            e => e,
+        })?;
+        let mut config = Self::load(value, env_vars())?;
+        config.source = Some(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// 只从环境变量加载配置
+    ///
+    /// # 返回
+    /// - `Ok(ServerConfig)`: 未经校验的配置
+    /// - `Err(ConfigError)`: 环境变量的值无法解析
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::load(Value::Object(Map::new()), env_vars())
+    }
+
+    /// 解析配置内容，不应用环境变量覆盖
+    ///
+    /// # 参数
+    /// - `content`: 配置内容
+    /// - `format`: 配置格式
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self, ConfigError> {
+        Self::load(format.parse(content)?, std::iter::empty())
+    }
+
+    /// 应用环境变量覆盖后反序列化
+    fn load(mut value: Value, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self, ConfigError> {
+        for (key, raw) in vars {
+            let Some(path) = key.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let path: Vec<String> = path.split("__").map(str::to_ascii_lowercase).collect();
+            if path.iter().any(String::is_empty) {
+                return Err(ConfigError::Parse(format!("{key}: empty config path segment")));
+            }
+            let value_of_var = match raw.trim_start().starts_with(['[', '{']) {
+                true => serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+                false => Value::String(raw),
+            };
+            set_path(&mut value, &path, value_of_var);
+        }
+        Self::deserialize(value).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    /// 校验配置
+    ///
+    /// 检查监听地址、TLS 文件、时长、日志过滤规则和 JWT 密钥，一次性返回所有问题
+    ///
+    /// # 返回
+    /// - `Ok(())`: 配置有效
+    /// - `Err(ConfigError::Invalid)`: 所有问题的列表
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.resolve().map(|_| ())
+    }
+
+    /// 创建 JWT 配置
+    ///
+    /// # 返回
+    /// 没有配置 JWT 时返回 None
+    #[cfg(feature = "jwt")]
+    pub fn jwt_config(&self) -> Option<crate::jwt::JwtConfig> {
+        self.jwt.as_ref().map(|jwt| crate::jwt::JwtConfig::new(&jwt.secret))
+    }
+
+    /// 校验并转换为服务器参数
+    fn resolve(&self) -> Result<Resolved, ConfigError> {
+        let mut problems = Vec::new();
+
+        let addrs = parse_addrs("listen", &self.listen, &mut problems);
+        #[cfg(unix)]
+        let unix_sockets: Vec<_> = self
+            .unix_sockets
+            .iter()
+            .enumerate()
+            .filter_map(|(index, socket)| {
+                if socket.path.as_os_str().is_empty() {
+                    problems.push(format!("unix_sockets[{index}].path: missing"));
+                    return None;
+                }
+                Some(crate::UnixSocketParam {
+                    path: socket.path.clone(),
+                    mode: socket.mode,
+                    proxy_protocol: None,
+                })
+            })
+            .collect();
+        #[cfg(unix)]
+        let has_unix_sockets = !self.unix_sockets.is_empty();
+        #[cfg(not(unix))]
+        let has_unix_sockets = {
+            if !self.unix_sockets.is_empty() {
+                problems.push("unix_sockets: not supported on this platform".to_string());
+            }
+            false
+        };
+        if self.listen.is_empty() && !has_unix_sockets {
+            problems.push("listen: no listener configured, set `listen` or `unix_sockets`".to_string());
+        }
+
+        let tls = self.tls.as_ref().map(|tls| {
+            for (name, path) in [("cert", &tls.cert), ("key", &tls.key)] {
+                if path.is_empty() {
+                    problems.push(format!("tls.{name}: missing"));
+                } else if !Path::new(path).is_file() {
+                    problems.push(format!("tls.{name}: file `{path}` does not exist"));
+                }
+            }
+            TlsParam {
+                tls: true,
+                cert: tls.cert.clone(),
+                key: tls.key.clone(),
+            }
+        });
+
+        let http = self.http.as_ref().map(|http| {
+            if tls.is_none() {
+                problems.push("http: requires tls, use `listen` for plain http".to_string());
+            }
+            HttpParam {
+                addrs: parse_addrs("http.listen", &http.listen, &mut problems),
+                redirect: http.redirect.then(HttpRedirectParam::default),
+            }
+        });
+
+        let mut duration = |name: &str, value: &Option<String>, allow_zero: bool| -> Option<Duration> {
+            let value = value.as_deref()?;
+            match parse_duration(value) {
+                Ok(duration) if duration.is_zero() && !allow_zero => {
+                    problems.push(format!("timeouts.{name}: must be greater than 0"));
+                    None
+                }
+                Ok(duration) => Some(duration),
+                Err(e) => {
+                    problems.push(format!("timeouts.{name}: {e}"));
+                    None
+                }
+            }
+        };
+        let idle = duration("idle", &self.timeouts.idle, false);
+        let mut timeouts = TimeoutParam::default();
+        if let Some(proxy_header) = duration("proxy_header", &self.timeouts.proxy_header, false) {
+            timeouts.proxy_header = proxy_header;
+        }
+        if let Some(tls_handshake) = duration("tls_handshake", &self.timeouts.tls_handshake, false) {
+            timeouts.tls_handshake = tls_handshake;
+        }
+        if let Some(header_read) = duration("header_read", &self.timeouts.header_read, true) {
+            timeouts.header_read = Some(header_read).filter(|d| !d.is_zero());
+        }
+        if let Some(max_lifetime) = duration("max_lifetime", &self.timeouts.max_lifetime, true) {
+            timeouts.max_lifetime = Some(max_lifetime).filter(|d| !d.is_zero());
+        }
+
+        #[cfg(feature = "use_tracing_subscriber")]
+        if let Some(filter) = &self.log.filter {
+            if let Err(e) = tracing_subscriber::EnvFilter::try_new(filter) {
+                problems.push(format!("log.filter: {e}"));
+            }
+        }
+
+        if let Some(jwt) = &self.jwt {
+            if jwt.secret.is_empty() {
+                problems.push("jwt.secret: missing".to_string());
+            }
+        }
+
+        let ip_rules = self.ip_filter.as_ref().map(|ip_filter| {
+            let mut rules = IpFilterRules::default();
+            for (name, list, nets) in [("allow", &ip_filter.allow, &mut rules.allow), ("deny", &ip_filter.deny, &mut rules.deny)] {
+                for (index, net) in list.iter().enumerate() {
+                    match net.trim().parse() {
+                        Ok(net) => nets.push(net),
+                        Err(e) => problems.push(format!("ip_filter.{name}[{index}]: {e}")),
+                    }
+                }
+            }
+            rules
+        });
+
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid(problems));
+        }
+        Ok(Resolved {
+            addrs,
+            #[cfg(unix)]
+            unix_sockets,
+            tls,
+            http,
+            idle,
+            timeouts,
+            ip_rules,
+        })
+    }
+}
+
+impl Server {
+    /// 根据配置创建服务器
+    ///
+    /// 先校验配置，再设置监听地址、TLS、明文 HTTP 监听和超时，其余参数保持 [`new_server`] 的默认值，
+    /// 可以继续调用其它 `with_*` 方法。日志和 JWT 配置需要分别传给 [`crate::init_log`] 和 JWT 中间件
+    ///
+    /// 配置了 `ip_filter` 时设置 [`IpFilter`] 作为连接钩子，之后调用 [`Server::with_connection_hook`] 会替换它。
+    /// 配置来自文件时添加重新加载钩子，见模块文档
+    ///
+    /// # 参数
+    /// - `config`: 服务器配置
+    /// - `router`: Axum 路由
+    /// - `shutdown_rx`: 关闭信号接收器
+    ///
+    /// # 返回
+    /// - `Ok(Server)`: 配置好的服务器实例
+    /// - `Err(ConfigError::Invalid)`: 配置的所有问题
+    pub fn from_config(config: ServerConfig, router: Router, shutdown_rx: broadcast::Receiver<()>) -> Result<Self, ConfigError> {
+        let resolved = config.resolve()?;
+        let mut server = new_server(0, router, shutdown_rx);
+        let default_idle = server.idle_timeout;
+        server = server
+            .with_addrs(resolved.addrs)
+            .with_tls_param(resolved.tls)
+            .with_http_param(resolved.http)
+            .with_timeouts(resolved.timeouts);
+        if let Some(idle) = resolved.idle {
+            server = server.with_timeout(idle);
+        }
+        #[cfg(unix)]
+        for socket in resolved.unix_sockets {
+            server = server.with_unix_socket(socket);
+        }
+        let ip_filter = resolved.ip_rules.map(IpFilter::new);
+        if let Some(ip_filter) = &ip_filter {
+            server = server.with_connection_hook(ip_filter.clone());
+        }
+        if let Some(path) = config.source.clone() {
+            server = server.with_reload_hook(ConfigReloader {
+                path,
+                default_idle,
+                ip_filter,
+                current: Mutex::new(config),
+            });
+        }
+        Ok(server)
+    }
+}
+
+/// 重新读取配置文件的重新加载钩子
+///
+/// # 字段
+/// - `path`: 配置文件路径
+/// - `default_idle`: 配置中没有空闲超时时使用的默认值
+/// - `ip_filter`: 启动时创建的 IP 过滤器
+/// - `current`: 当前生效的配置
+struct ConfigReloader {
+    path: PathBuf,
+    default_idle: Duration,
+    ip_filter: Option<IpFilter>,
+    current: Mutex<ServerConfig>,
+}
+
+impl ReloadHook for ConfigReloader {
+    fn reload(&self, settings: &RuntimeSettings) -> io::Result<()> {
+        let config = ServerConfig::from_file(&self.path).map_err(io::Error::other)?;
+        let resolved = config.resolve().map_err(io::Error::other)?;
+        let mut current = self.current.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // 唯一可能失败的步骤放在最前面，失败时其它参数也保持不变
+        if config.log.filter != current.log.filter {
+            match &config.log.filter {
+                #[cfg(feature = "use_tracing_subscriber")]
+                Some(filter) => {
+                    crate::init_log::tracing::set_filter(filter).map_err(|e| io::Error::other(format!("log.filter: {e}")))?;
+                    info!("[reload]: log filter -> {filter}");
+                }
+                #[cfg(not(feature = "use_tracing_subscriber"))]
+                Some(_) => warn!("[reload]: log.filter changed, reloading it requires the use_tracing_subscriber feature"),
+                None => warn!("[reload]: log.filter removed, restart to restore the default filter"),
+            }
+        }
+        settings.set_idle_timeout(resolved.idle.unwrap_or(self.default_idle));
+        match (&self.ip_filter, resolved.ip_rules) {
+            (Some(ip_filter), rules) => {
+                let rules = rules.unwrap_or_default();
+                info!("[reload]: ip filter {} allow, {} deny", rules.allow.len(), rules.deny.len());
+                ip_filter.set_rules(rules);
+            }
+            (None, Some(_)) => warn!("[reload]: ip_filter added, restart to apply"),
+            (None, None) => {}
+        }
+
+        let restart_required: Vec<_> = [
+            ("listen", config.listen != current.listen),
+            ("unix_sockets", config.unix_sockets != current.unix_sockets),
+            ("tls", config.tls != current.tls),
+            ("http", config.http != current.http),
+            ("timeouts", {
+                let (new, old) = (&config.timeouts, &current.timeouts);
+                (&new.proxy_header, &new.tls_handshake, &new.header_read, &new.max_lifetime)
+                    != (&old.proxy_header, &old.tls_handshake, &old.header_read, &old.max_lifetime)
+            }),
+            ("jwt", config.jwt != current.jwt),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect();
+        if !restart_required.is_empty() {
+            warn!("[reload]: {} changed, restart to apply", restart_required.join(", "));
+        }
+        *current = config;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "config"
+    }
+}
+
+/// 读取所有环境变量，忽略不是 UTF-8 的变量
+fn env_vars() -> impl Iterator<Item = (String, String)> {
+    std::env::vars_os().filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+}
+
+/// 设置嵌套路径上的值，路径上不是对象的值被替换为对象
+fn set_path(root: &mut Value, path: &[String], value: Value) {
+    let mut current = root;
+    for segment in path {
+        if !current.is_object() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(map) = current else {
+            return;
+        };
+        current = map.entry(segment.clone()).or_insert(Value::Null);
+    }
+    *current = value;
+}
+
+/// 解析监听地址，记录无法解析的地址
+fn parse_addrs(name: &str, addrs: &[String], problems: &mut Vec<String>) -> Vec<SocketAddr> {
+    addrs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, addr)| match addr.trim().parse() {
+            Ok(addr) => Some(addr),
+            Err(_) => {
+                problems.push(format!("{name}[{index}]: invalid socket address `{addr}`"));
+                None
+            }
+        })
+        .collect()
+}
+
+/// 解析时长
+///
+/// 支持整数秒和带单位 (`ms`、`s`、`m`、`h`、`d`) 的组合，例如 `1h30m`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{s}`, expect e.g. `30s`, `500ms` or `1h30m`");
+    let text = s.trim();
+    if let Ok(secs) = text.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    if text.is_empty() {
+        return Err(invalid());
+    }
+    let mut total = Duration::ZERO;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let value: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit = match rest[..unit_len].trim() {
+            "ms" => Duration::from_millis(1),
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            "d" => Duration::from_secs(24 * 60 * 60),
+            _ => return Err(invalid()),
+        };
+        rest = &rest[unit_len..];
+        total = u32::try_from(value)
+            .ok()
+            .and_then(|value| unit.checked_mul(value))
+            .and_then(|duration| total.checked_add(duration))
+            .ok_or_else(invalid)?;
+    }
+    Ok(total)
+}
+
+/// 接受字符串或字符串列表，字符串按逗号分隔
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(s) => s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect(),
+        OneOrMany::Many(list) => list,
+    })
+}
+
+/// 接受布尔值或 `true`/`false` 字符串
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BoolOrString {
+        Bool(bool),
+        String(String),
+    }
+    match BoolOrString::deserialize(deserializer)? {
+        BoolOrString::Bool(b) => Ok(b),
+        BoolOrString::String(s) => s.trim().parse().map_err(|_| serde::de::Error::custom(format!("invalid boolean `{s}`"))),
+    }
+}
+
+/// 接受整数或八进制字符串
+fn file_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ModeOrString {
+        Mode(u32),
+        String(String),
+    }
+    match Option::<ModeOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(ModeOrString::Mode(mode)) => Ok(Some(mode)),
+        Some(ModeOrString::String(s)) => {
+            let digits = s.trim();
+            let digits = digits.strip_prefix("0o").unwrap_or(digits);
+            u32::from_str_radix(digits, 8)
+                .map(Some)
+                .map_err(|_| serde::de::Error::custom(format!("invalid file mode `{s}`, expect octal e.g. `660`")))
+        }
+    }
+}
+
+/// 接受整数秒或时长字符串，时长在校验时解析
+fn duration_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SecondsOrString {
+        Seconds(u64),
+        String(String),
+    }
+    Ok(Option::<SecondsOrString>::deserialize(deserializer)?.map(|value| match value {
+        SecondsOrString::Seconds(secs) => secs.to_string(),
+        SecondsOrString::String(s) => s,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let toml = r#"
+            listen = ["127.0.0.1:8080"]
+
+            [tls]
+            cert = "cert.pem"
+            key = "privkey.pem"
+
+            [timeouts]
+            idle = 300
+            header_read = "10s"
+
+            [log]
+            filter = "info"
+        "#;
+        let yaml = "
+            listen: 127.0.0.1:8080
+            tls:
+              cert: cert.pem
+              key: privkey.pem
+            timeouts:
+              idle: 300
+              header_read: 10s
+            log:
+              filter: info
+        ";
+        let config = ServerConfig::parse(toml, ConfigFormat::Toml).unwrap();
+        assert_eq!(config, ServerConfig::parse(yaml, ConfigFormat::Yaml).unwrap());
+        assert_eq!(config.timeouts.idle.as_deref(), Some("300"));
+
+        let vars = [
+            ("AXUM_BOOTSTRAP__TLS__CERT", "/etc/app/cert.pem"),
+            ("AXUM_BOOTSTRAP__LISTEN", r#"["[::]:443", "[::]:8443"]"#),
+            ("AXUM_BOOTSTRAP__HTTP__LISTEN", "[::]:80, [::]:8080"),
+            ("AXUM_BOOTSTRAP__HTTP__REDIRECT", "true"),
+            ("AXUM_BOOTSTRAP__TIMEOUTS__MAX_LIFETIME", "1h"),
+            ("AXUM_BOOTSTRAP__JWT__SECRET", "123456"),
+            ("OTHER__TLS__KEY", "ignored"),
+        ]
+        .map(|(key, value)| (key.to_string(), value.to_string()));
+        let config = ServerConfig::load(ConfigFormat::Toml.parse(toml).unwrap(), vars).unwrap();
+        assert_eq!(config.listen, ["[::]:443", "[::]:8443"]);
+        assert_eq!(
+            config.tls,
+            Some(TlsConfig {
+                cert: "/etc/app/cert.pem".to_string(),
+                key: "privkey.pem".to_string(),
+            })
+        );
+        assert_eq!(
+            config.http,
+            Some(HttpConfig {
+                listen: vec!["[::]:80".to_string(), "[::]:8080".to_string()],
+                redirect: true,
+            })
+        );
+        assert_eq!(config.timeouts.max_lifetime.as_deref(), Some("1h"));
+        assert!(!format!("{config:?}").contains("123456"));
+        assert_eq!(config.jwt.map(|jwt| jwt.secret).as_deref(), Some("123456"));
+
+        // 未知字段和类型错误
+        assert!(matches!(ServerConfig::parse("listne = []", ConfigFormat::Toml), Err(ConfigError::Parse(_))));
+        let vars = [("AXUM_BOOTSTRAP__TIMEOUTS__IDEL".to_string(), "1s".to_string())];
+        assert!(matches!(ServerConfig::load(Value::Null, vars), Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1d 2s"), Ok(Duration::from_secs(86402)));
+        for invalid in ["", "s", "10x", "1.5s", "-1s"] {
+            assert!(parse_duration(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = ServerConfig::parse(
+            r#"
+                listen = ["127.0.0.1:8080", "localhost:80"]
+
+                [tls]
+                cert = "/nonexistent/cert.pem"
+
+                [timeouts]
+                idle = "0s"
+                header_read = "soon"
+                max_lifetime = 0
+
+                [jwt]
+                secret = ""
+            "#,
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        let Err(ConfigError::Invalid(problems)) = config.validate() else {
+            panic!("expect invalid config");
+        };
+        assert_eq!(
+            problems,
+            [
+                "listen[1]: invalid socket address `localhost:80`",
+                "tls.cert: file `/nonexistent/cert.pem` does not exist",
+                "tls.key: missing",
+                "timeouts.idle: must be greater than 0",
+                "timeouts.header_read: invalid duration `soon`, expect e.g. `30s`, `500ms` or `1h30m`",
+                "jwt.secret: missing",
+            ]
+        );
+
+        #[cfg(feature = "use_tracing_subscriber")]
+        {
+            let config = ServerConfig {
+                log: LogConfig {
+                    filter: Some("info,my_app=loud".to_string()),
+                },
+                ..Default::default()
+            };
+            let Err(ConfigError::Invalid(problems)) = config.validate() else {
+                panic!("expect invalid config");
+            };
+            assert_eq!(problems.len(), 2);
+            assert_eq!(problems[0], "listen: no listener configured, set `listen` or `unix_sockets`");
+            assert!(problems[1].starts_with("log.filter: "), "{problems:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        use crate::{connection::ConnectAction, util::io::PeerAddr};
+
+        let path = std::env::temp_dir().join(format!("axum-bootstrap-config-{}.toml", std::process::id()));
+        let write = |content: &str| std::fs::write(&path, content).unwrap();
+        write(
+            r#"
+                listen = "127.0.0.1:0"
+                [timeouts]
+                idle = "5s"
+                [ip_filter]
+                deny = "10.0.0.0/8"
+            "#,
+        );
+        let config = ServerConfig::from_file(&path).unwrap();
+        assert_eq!(config.source.as_deref(), Some(path.as_path()));
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let server = Server::from_config(config, Router::new(), shutdown_rx).unwrap();
+        let (Some(hook), [reload]) = (&server.connection_hook, &server.reload_hooks[..]) else {
+            panic!("expect ip filter and config reload hook");
+        };
+        let settings = RuntimeSettings::new(server.idle_timeout);
+        let allowed = |ip: [u8; 4]| matches!(hook.on_connect(&PeerAddr::Tcp((ip, 1234).into()), None), ConnectAction::Accept);
+        assert!(!allowed([10, 0, 0, 1]));
+
+        // 校验失败时保留原来的配置
+        write(
+            r#"
+                listen = "127.0.0.1:0"
+                [timeouts]
+                idle = "1s"
+                [ip_filter]
+                deny = "10.0.0.0/33"
+            "#,
+        );
+        let err = reload.reload(&settings).unwrap_err().to_string();
+        assert!(err.contains("ip_filter.deny[0]: invalid cidr: 10.0.0.0/33"), "{err}");
+        assert_eq!(settings.idle_timeout(), Duration::from_secs(5));
+        assert!(!allowed([10, 0, 0, 1]));
+
+        write(
+            r#"
+                listen = "127.0.0.1:0"
+                [ip_filter]
+                deny = "192.168.0.0/16"
+            "#,
+        );
+        reload.reload(&settings).unwrap();
+        assert_eq!(settings.idle_timeout(), Duration::from_secs(120));
+        assert!(allowed([10, 0, 0, 1]));
+        assert!(!allowed([192, 168, 1, 1]));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_from_config() {
+        let config = ServerConfig::parse(
+            "
+            listen: 127.0.0.1:0
+            timeouts:
+              idle: 5s
+              proxy_header: 2s
+              header_read: 0
+              max_lifetime: 1h
+            ",
+            ConfigFormat::Yaml,
+        )
+        .unwrap();
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let server = Server::from_config(config, Router::new(), shutdown_rx).unwrap();
+        assert_eq!(server.addrs, [SocketAddr::from(([127, 0, 0, 1], 0))]);
+        assert_eq!(server.idle_timeout, Duration::from_secs(5));
+        assert_eq!(server.timeouts.proxy_header, Duration::from_secs(2));
+        assert_eq!(server.timeouts.header_read, None);
+        assert_eq!(server.timeouts.max_lifetime, Some(Duration::from_secs(3600)));
+        assert_eq!(server.timeouts.tls_handshake, TimeoutParam::default().tls_handshake);
+        assert!(server.tls_param.is_none());
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let result = Server::from_config(ServerConfig::default(), Router::new(), shutdown_rx);
+        assert!(matches!(result, Err(ConfigError::Invalid(problems)) if problems.len() == 1));
+    }
+}
@@ -0,0 +1,755 @@
+//! # 连接管理模块
+//!
+//! 提供连接数限制、连接统计和连接信息
+//!
+//! # 主要组件
+//! - `ConnectionInfo`: 连接的本地地址、对端地址、TLS 信息和连接 ID
+//! - `ConnectionHook`: 在 accept 之后、TLS 握手之前过滤连接，并在连接关闭时回调
+//! - `ShutdownSignal`: 服务器停止接受新连接的信号，供升级后的连接主动关闭
+//! - `ConnectionLimitParam`: 全局和单 IP 的最大连接数配置
+//! - `LimitAction`: 达到全局上限时的处理方式
+//! - `ConnectionStats`: 当前打开的连接数、被拒绝的连接数和暂停 accept 的次数
+//! - `AcceptBackoff`: 对 accept 错误分类，资源耗尽时指数退避
+//! - `WarnThrottle`: 警告日志限流，accept 错误、超限暂停 accept 和超限关闭连接的警告共用
+
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use log::{debug, info, warn};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, watch};
+use tokio_rustls::rustls::{CipherSuite, ProtocolVersion, ServerConnection, pki_types::CertificateDer};
+
+use crate::util::io::{Listener, PeerAddr, Stream};
+
+/// 连接数限制参数
+///
+/// # 字段
+/// - `max_connections`: 所有监听器合计的最大连接数，为 None 时不限制
+/// - `max_connections_per_ip`: 单个源 IP 的最大连接数，为 None 时不限制。
+///   启用 PROXY protocol 时按头部中的真实客户端地址计算，Unix domain socket 连接不受限制
+/// - `action`: 达到全局上限时的处理方式；单 IP 超限的连接总是被关闭
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimitParam {
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    pub action: LimitAction,
+}
+
+/// 达到全局连接数上限时的处理方式
+///
+/// # 变体
+/// - `Backpressure`: 暂停 accept，新连接在内核的监听队列中等待，直到有连接关闭
+/// - `Close`: 继续 accept，并立即关闭超出上限的连接
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LimitAction {
+    #[default]
+    Backpressure,
+    Close,
+}
+
+/// 连接统计
+///
+/// 可以克隆并在服务器运行期间随时读取，所有克隆共享同一份计数
+///
+/// # 示例
+///
+/// ```no_run
+/// use axum::Router;
+/// use axum_bootstrap::{new_server, generate_shutdown_receiver};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let server = new_server(8080, Router::new(), generate_shutdown_receiver());
+///     let stats = server.connection_stats();
+///     tokio::spawn(async move {
+///         loop {
+///             tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+///             log::info!("open connections: {}", stats.open_connections());
+///         }
+///     });
+///     server.run().await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionStats {
+    inner: Arc<StatsInner>,
+}
+
+/// 连接统计的共享计数
+#[derive(Debug, Default)]
+struct StatsInner {
+    open: AtomicUsize,
+    rejected_max_connections: AtomicU64,
+    rejected_per_ip: AtomicU64,
+    paused_max_connections: AtomicU64,
+    per_ip: Mutex<HashMap<IpAddr, usize>>,
+    max_connections_warn: Mutex<WarnThrottle>,
+    paused_warn: Mutex<WarnThrottle>,
+    per_ip_warn: Mutex<WarnThrottle>,
+}
+
+impl ConnectionStats {
+    /// 当前打开的连接数
+    pub fn open_connections(&self) -> usize {
+        self.inner.open.load(Ordering::Relaxed)
+    }
+
+    /// 指定源 IP 当前打开的连接数 (仅在配置了单 IP 上限时统计)
+    pub fn open_connections_of(&self, ip: IpAddr) -> usize {
+        self.per_ip().get(&ip.to_canonical()).copied().unwrap_or_default()
+    }
+
+    /// 因达到全局上限而被关闭的连接数
+    pub fn rejected_by_max_connections(&self) -> u64 {
+        self.inner.rejected_max_connections.load(Ordering::Relaxed)
+    }
+
+    /// 因达到单 IP 上限而被关闭的连接数
+    pub fn rejected_by_max_connections_per_ip(&self) -> u64 {
+        self.inner.rejected_per_ip.load(Ordering::Relaxed)
+    }
+
+    /// `Backpressure` 模式下因达到全局上限而暂停 accept 的次数
+    pub fn paused_by_max_connections(&self) -> u64 {
+        self.inner.paused_max_connections.load(Ordering::Relaxed)
+    }
+
+    fn per_ip(&self) -> std::sync::MutexGuard<'_, HashMap<IpAddr, usize>> {
+        self.inner.per_ip.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 判断是否输出超限暂停 accept 或关闭连接的警告，限流状态在所有 accept 循环和连接任务之间共享
+fn should_warn_rejected(throttle: &Mutex<WarnThrottle>) -> Option<u64> {
+    throttle
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .should_warn(REJECT_WARN_INTERVAL)
+}
+
+/// 资源耗尽时 accept 退避的初始时长
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(5);
+/// 资源耗尽时 accept 退避的最大时长
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+/// accept 错误警告日志的最小间隔
+const ACCEPT_WARN_INTERVAL: Duration = Duration::from_secs(5);
+/// 超限暂停 accept 和关闭连接警告日志的最小间隔，连接突增时由 `rejected_*` 和 `paused_*` 计数反映总量
+const REJECT_WARN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 警告日志限流
+///
+/// 同类警告在间隔内只输出一条，并统计期间省略的次数
+#[derive(Debug, Default)]
+struct WarnThrottle {
+    last_warn: Option<Instant>,
+    suppressed: u64,
+}
+
+impl WarnThrottle {
+    /// 距离上次警告超过间隔时返回期间省略的警告数，否则计数并返回 None
+    fn should_warn(&mut self, interval: Duration) -> Option<u64> {
+        let now = Instant::now();
+        match self.last_warn {
+            Some(last) if now.duration_since(last) < interval => {
+                self.suppressed += 1;
+                None
+            }
+            _ => {
+                self.last_warn = Some(now);
+                Some(std::mem::take(&mut self.suppressed))
+            }
+        }
+    }
+}
+
+/// accept 错误的分类
+///
+/// # 变体
+/// - `Connection`: 单个连接的错误 (如 `ECONNABORTED`)，忽略并立即继续 accept
+/// - `Resource`: 资源耗尽 (如 `EMFILE`、`ENFILE`) 或未知错误，退避后重试
+/// - `Fatal`: 监听 socket 本身不可用 (如 `EBADF`、`EINVAL`)，停止 accept 并返回错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AcceptErrorKind {
+    Connection,
+    Resource,
+    Fatal,
+}
+
+impl AcceptErrorKind {
+    fn of(e: &io::Error) -> Self {
+        use io::ErrorKind::*;
+        match e.kind() {
+            ConnectionAborted | ConnectionReset | ConnectionRefused | Interrupted | WouldBlock | TimedOut | NotConnected | PermissionDenied
+            | HostUnreachable | NetworkUnreachable | NetworkDown => return Self::Connection,
+            OutOfMemory => return Self::Resource,
+            InvalidInput => return Self::Fatal,
+            _ => {}
+        }
+        #[cfg(unix)]
+        match e.raw_os_error() {
+            Some(libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM) => return Self::Resource,
+            Some(libc::EPROTO | libc::ENOPROTOOPT | libc::EHOSTDOWN | libc::EOPNOTSUPP) => return Self::Connection,
+            Some(libc::EBADF | libc::ENOTSOCK | libc::EFAULT) => return Self::Fatal,
+            _ => {}
+        }
+        Self::Resource
+    }
+}
+
+/// accept 错误处理状态
+///
+/// 每个 accept 循环持有一个：单个连接的错误被忽略；资源耗尽时从 5ms 开始指数退避，最长 1s，
+/// 成功 accept 后复位；警告日志每 5s 最多输出一条并统计期间省略的次数；致命错误返回给调用方
+#[derive(Debug, Default)]
+pub(crate) struct AcceptBackoff {
+    delay: Option<Duration>,
+    warn: WarnThrottle,
+}
+
+impl AcceptBackoff {
+    /// 接受新连接，按错误分类重试
+    ///
+    /// # 参数
+    /// - `listener`: 监听器
+    ///
+    /// # 返回
+    /// - `Ok((Stream, PeerAddr))`: 新连接和对端描述
+    /// - `Err(io::Error)`: 致命错误，监听器不能继续使用
+    pub(crate) async fn accept(&mut self, listener: &Listener) -> io::Result<(Stream, PeerAddr)> {
+        loop {
+            let e = match listener.accept().await {
+                Ok(accepted) => {
+                    if self.delay.take().is_some() {
+                        info!("accept on {listener} recovered");
+                    }
+                    return Ok(accepted);
+                }
+                Err(e) => e,
+            };
+            match AcceptErrorKind::of(&e) {
+                AcceptErrorKind::Connection => debug!("accept error on {listener}: {e}, ignored"),
+                AcceptErrorKind::Fatal => return Err(e),
+                AcceptErrorKind::Resource => {
+                    let delay = self.next_delay();
+                    if let Some(suppressed) = self.warn.should_warn(ACCEPT_WARN_INTERVAL) {
+                        warn!("accept error on {listener}: {e}, retry in {delay:?} ({suppressed} similar errors suppressed)");
+                    }
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.delay.map_or(ACCEPT_BACKOFF_MIN, |delay| (delay * 2).min(ACCEPT_BACKOFF_MAX));
+        self.delay = Some(delay);
+        delay
+    }
+}
+
+/// 连接数限制器
+///
+/// 在所有监听器的 accept 循环之间共享
+#[derive(Clone)]
+pub(crate) struct ConnectionLimiter {
+    param: Option<ConnectionLimitParam>,
+    semaphore: Option<Arc<Semaphore>>,
+    stats: ConnectionStats,
+}
+
+impl ConnectionLimiter {
+    /// 创建连接数限制器
+    ///
+    /// # 参数
+    /// - `param`: 连接数限制参数，为 None 时只统计不限制
+    /// - `stats`: 连接统计
+    pub(crate) fn new(param: Option<ConnectionLimitParam>, stats: ConnectionStats) -> Self {
+        let semaphore = param
+            .and_then(|param| param.max_connections)
+            .map(|max| Arc::new(Semaphore::new(max.min(Semaphore::MAX_PERMITS))));
+        Self { param, semaphore, stats }
+    }
+
+    /// 在全局连接数限制下接受新连接
+    ///
+    /// # 参数
+    /// - `listener`: 监听器
+    /// - `backoff`: 该监听器 accept 循环的错误处理状态
+    ///
+    /// # 返回
+    /// - `Ok(Some((Stream, PeerAddr, ConnectionGuard)))`: 新连接，guard 在连接任务结束时释放
+    /// - `Ok(None)`: `Close` 模式下连接超出上限，已被关闭
+    /// - `Err(io::Error)`: 致命的 accept 错误
+    pub(crate) async fn accept(&self, listener: &Listener, backoff: &mut AcceptBackoff) -> io::Result<Option<(Stream, PeerAddr, ConnectionGuard)>> {
+        let (Some(param), Some(semaphore)) = (self.param, &self.semaphore) else {
+            let (conn, peer_addr) = backoff.accept(listener).await?;
+            return Ok(Some((conn, peer_addr, self.guard(None))));
+        };
+        match param.action {
+            LimitAction::Backpressure => {
+                let permit = match semaphore.clone().try_acquire_owned() {
+                    Ok(permit) => permit,
+                    Err(_) => {
+                        self.pause(param, listener);
+                        semaphore.clone().acquire_owned().await.map_err(io::Error::other)?
+                    }
+                };
+                let (conn, peer_addr) = backoff.accept(listener).await?;
+                Ok(Some((conn, peer_addr, self.guard(Some(permit)))))
+            }
+            LimitAction::Close => {
+                let (conn, peer_addr) = backoff.accept(listener).await?;
+                match semaphore.clone().try_acquire_owned() {
+                    Ok(permit) => Ok(Some((conn, peer_addr, self.guard(Some(permit))))),
+                    Err(_) => {
+                        self.reject(param, &peer_addr);
+                        Ok(None)
+                    }
+                }
+            }
+        }
+    }
+
+    /// 为无法暂停 accept 的连接 (如 QUIC) 占用限额
+    ///
+    /// 达到全局上限时总是按 `Close` 处理，同时检查单 IP 上限
+    ///
+    /// # 参数
+    /// - `peer_addr`: 对端描述
+    ///
+    /// # 返回
+    /// - `Some(ConnectionGuard)`: 占用的限额，在连接任务结束时释放
+    /// - `None`: 超过全局或单 IP 上限，调用方应当拒绝连接
+    #[cfg_attr(not(feature = "http3"), allow(dead_code))]
+    pub(crate) fn try_acquire(&self, peer_addr: &PeerAddr) -> Option<ConnectionGuard> {
+        let permit = match (self.param, &self.semaphore) {
+            (Some(param), Some(semaphore)) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    self.reject(param, peer_addr);
+                    return None;
+                }
+            },
+            _ => None,
+        };
+        let mut guard = self.guard(permit);
+        guard.acquire_ip(peer_addr).then_some(guard)
+    }
+
+    fn pause(&self, param: ConnectionLimitParam, listener: &Listener) {
+        let paused = self.stats.inner.paused_max_connections.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(suppressed) = should_warn_rejected(&self.stats.inner.paused_warn) {
+            warn!(
+                "max connections {} reached, pause accepting on {listener} (paused {paused} times in total, {suppressed} similar warnings suppressed)",
+                param.max_connections.unwrap_or_default()
+            );
+        }
+    }
+
+    fn reject(&self, param: ConnectionLimitParam, peer_addr: &PeerAddr) {
+        let rejected = self.stats.inner.rejected_max_connections.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(suppressed) = should_warn_rejected(&self.stats.inner.max_connections_warn) {
+            warn!(
+                "max connections {} reached, close connection from {peer_addr} (rejected {rejected} in total, {suppressed} similar warnings suppressed)",
+                param.max_connections.unwrap_or_default()
+            );
+        }
+    }
+
+    fn guard(&self, permit: Option<OwnedSemaphorePermit>) -> ConnectionGuard {
+        self.stats.inner.open.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard {
+            stats: self.stats.clone(),
+            max_per_ip: self.param.and_then(|param| param.max_connections_per_ip),
+            ip: None,
+            _permit: permit,
+        }
+    }
+}
+
+/// 连接占用的限额
+///
+/// 由连接任务持有，drop 时释放全局和单 IP 的限额
+pub(crate) struct ConnectionGuard {
+    stats: ConnectionStats,
+    max_per_ip: Option<usize>,
+    ip: Option<IpAddr>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl ConnectionGuard {
+    /// 占用源 IP 的限额
+    ///
+    /// # 参数
+    /// - `peer_addr`: 对端描述 (启用 PROXY protocol 时为真实客户端地址)
+    ///
+    /// # 返回
+    /// - `true`: 未配置单 IP 上限、不是 IP 连接，或者没有超过上限
+    /// - `false`: 超过单 IP 上限，调用方应当关闭连接
+    pub(crate) fn acquire_ip(&mut self, peer_addr: &PeerAddr) -> bool {
+        let (Some(max), PeerAddr::Tcp(addr)) = (self.max_per_ip, peer_addr) else {
+            return true;
+        };
+        let ip = addr.ip().to_canonical();
+        let mut per_ip = self.stats.per_ip();
+        if per_ip.get(&ip).copied().unwrap_or_default() >= max {
+            drop(per_ip);
+            let rejected = self.stats.inner.rejected_per_ip.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(suppressed) = should_warn_rejected(&self.stats.inner.per_ip_warn) {
+                warn!(
+                    "max connections per ip {max} reached, close connection from {peer_addr} (rejected {rejected} in total, {suppressed} similar warnings suppressed)"
+                );
+            }
+            return false;
+        }
+        *per_ip.entry(ip).or_default() += 1;
+        self.ip = Some(ip);
+        true
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.stats.inner.open.fetch_sub(1, Ordering::Relaxed);
+        if let Some(ip) = self.ip {
+            let mut per_ip = self.stats.per_ip();
+            if let Some(count) = per_ip.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+/// 下一个连接 ID
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 连接信息
+///
+/// 每个连接创建一次，插入该连接上每个请求的 extensions。拦截器可以通过
+/// `req.extensions().get::<ConnectionInfo>()` 获取，路由处理器可以直接把它作为 extractor 使用
+///
+/// # 字段
+/// - `id`: 连接 ID，进程内唯一，可用于关联同一连接上的请求和日志
+/// - `local_addr`: 本地地址，Unix domain socket 连接为 None；启用 PROXY protocol 时为头部中的目标地址
+/// - `peer_addr`: 对端描述，启用 PROXY protocol 时为头部中的源地址
+/// - `tls`: TLS 信息，明文连接为 None
+///
+/// # 示例
+///
+/// ```no_run
+/// use axum::{Router, routing::get};
+/// use axum_bootstrap::connection::ConnectionInfo;
+///
+/// async fn handler(info: ConnectionInfo) -> String {
+///     let sni = info.tls.as_ref().and_then(|tls| tls.server_name.clone());
+///     format!("connection {} from {}, sni: {sni:?}", info.id, info.peer_addr)
+/// }
+///
+/// let app: Router = Router::new().route("/", get(handler));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub local_addr: Option<SocketAddr>,
+    pub peer_addr: PeerAddr,
+    pub tls: Option<Arc<TlsInfo>>,
+}
+
+impl ConnectionInfo {
+    /// 为新连接创建连接信息，分配新的连接 ID
+    pub(crate) fn new(local_addr: Option<SocketAddr>, peer_addr: PeerAddr) -> Self {
+        Self {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            local_addr,
+            peer_addr,
+            tls: None,
+        }
+    }
+
+    /// 是否为 TLS 连接
+    pub fn is_tls(&self) -> bool {
+        self.tls.is_some()
+    }
+}
+
+/// TLS 连接信息
+///
+/// # 字段
+/// - `version`: 协商的 TLS 版本
+/// - `cipher_suite`: 协商的密码套件，HTTP/3 连接为 None
+/// - `server_name`: 客户端通过 SNI 发送的服务器名
+/// - `alpn_protocol`: 协商的 ALPN 协议，例如 `h2`、`http/1.1`、`h3`
+/// - `peer_certificates`: 客户端证书链，没有要求客户端证书时为空
+#[derive(Debug, Clone)]
+pub struct TlsInfo {
+    pub version: Option<ProtocolVersion>,
+    pub cipher_suite: Option<CipherSuite>,
+    pub server_name: Option<String>,
+    pub alpn_protocol: Option<Vec<u8>>,
+    pub peer_certificates: Vec<CertificateDer<'static>>,
+}
+
+impl TlsInfo {
+    /// 从握手完成的 TLS 连接中获取信息
+    pub(crate) fn from_connection(conn: &ServerConnection) -> Self {
+        Self {
+            version: conn.protocol_version(),
+            cipher_suite: conn.negotiated_cipher_suite().map(|suite| suite.suite()),
+            server_name: conn.server_name().map(str::to_string),
+            alpn_protocol: conn.alpn_protocol().map(<[u8]>::to_vec),
+            peer_certificates: conn.peer_certificates().map(<[CertificateDer<'static>]>::to_vec).unwrap_or_default(),
+        }
+    }
+}
+
+/// 连接过滤结果
+///
+/// # 变体
+/// - `Accept`: 接受连接
+/// - `Drop`: 丢弃连接，TCP 连接以 RST 关闭，QUIC 连接不回复任何数据包，
+///   日志级别由 [`Server::with_drop_log_level`](crate::Server::with_drop_log_level) 配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectAction {
+    Accept,
+    Drop,
+}
+
+/// 连接关闭时的统计信息
+///
+/// # 字段
+/// - `connection`: 连接信息，TLS 握手失败的连接没有 TLS 信息
+/// - `duration`: 从 accept 到连接关闭的时长
+/// - `bytes_read`: 从连接读取的字节数，包括 PROXY protocol 头和 TLS 记录；HTTP/3 连接为收到的 UDP 字节数
+/// - `bytes_written`: 向连接写入的字节数；HTTP/3 连接为发送的 UDP 字节数
+#[derive(Debug, Clone)]
+pub struct DisconnectInfo {
+    pub connection: ConnectionInfo,
+    pub duration: Duration,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+/// 连接级别的钩子
+///
+/// `on_connect` 在 accept 之后、TLS 握手和 HTTP 解析之前调用，用较低的成本拒绝恶意客户端。
+/// 启用 PROXY protocol 的监听器在读取头部之后调用，此时对端地址为真实客户端地址。
+/// `on_connect` 在 accept 循环中同步调用，不应该阻塞
+///
+/// 被接受的连接关闭时调用 `on_disconnect`，包括 TLS 握手失败和超过单 IP 上限的连接
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::net::{IpAddr, SocketAddr};
+///
+/// use axum::Router;
+/// use axum_bootstrap::{
+///     connection::{ConnectAction, ConnectionHook, DisconnectInfo},
+///     generate_shutdown_receiver, new_server,
+///     util::io::PeerAddr,
+/// };
+///
+/// struct Blocklist(Vec<IpAddr>);
+///
+/// impl ConnectionHook for Blocklist {
+///     fn on_connect(&self, peer_addr: &PeerAddr, _local_addr: Option<SocketAddr>) -> ConnectAction {
+///         match peer_addr {
+///             PeerAddr::Tcp(addr) if self.0.contains(&addr.ip()) => ConnectAction::Drop,
+///             _ => ConnectAction::Accept,
+///         }
+///     }
+///
+///     fn on_disconnect(&self, info: &DisconnectInfo) {
+///         log::info!("{} closed after {:?}, {} bytes sent", info.connection.peer_addr, info.duration, info.bytes_written);
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let server = new_server(8080, Router::new(), generate_shutdown_receiver()).with_connection_hook(Blocklist(vec![]));
+///     server.run().await.unwrap();
+/// }
+/// ```
+pub trait ConnectionHook: Send + Sync + 'static {
+    /// 决定是否接受新连接
+    ///
+    /// # 参数
+    /// - `peer_addr`: 对端描述
+    /// - `local_addr`: 本地地址，Unix domain socket 连接为 None
+    fn on_connect(&self, peer_addr: &PeerAddr, local_addr: Option<SocketAddr>) -> ConnectAction {
+        let _ = (peer_addr, local_addr);
+        ConnectAction::Accept
+    }
+
+    /// 被接受的连接关闭
+    ///
+    /// # 参数
+    /// - `info`: 连接信息、持续时长和读写字节数
+    fn on_disconnect(&self, info: &DisconnectInfo) {
+        let _ = info;
+    }
+}
+
+/// 服务器关闭信号
+///
+/// 服务器把该信号插入每个请求的 extensions 中，所有监听器停止接受新连接后触发。
+/// 升级后的连接 (如 CONNECT 隧道、WebSocket) 脱离了 hyper 的优雅关闭，可以等待该信号主动关闭
+///
+/// # 示例
+/// ```rust,no_run
+/// use axum::{extract::Request, http::StatusCode};
+/// use axum_bootstrap::connection::ShutdownSignal;
+///
+/// async fn upgrade(mut request: Request) -> StatusCode {
+///     let Some(shutdown) = request.extensions().get::<ShutdownSignal>().cloned() else {
+///         return StatusCode::INTERNAL_SERVER_ERROR;
+///     };
+///     let upgrade = hyper::upgrade::on(&mut request);
+///     tokio::spawn(async move {
+///         tokio::select! {
+///             _ = shutdown.wait() => {}
+///             upgraded = upgrade => drop(upgraded),
+///         }
+///     });
+///     StatusCode::SWITCHING_PROTOCOLS
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    /// 创建关闭信号
+    ///
+    /// # 返回
+    /// 触发信号的发送器和关闭信号
+    pub(crate) fn new() -> (watch::Sender<bool>, Self) {
+        let (tx, rx) = watch::channel(false);
+        (tx, Self(rx))
+    }
+
+    /// 服务器是否已开始关闭
+    pub fn is_shutdown(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// 等待服务器开始关闭，服务器已退出时立即返回
+    pub async fn wait(mut self) {
+        let _ = self.0.wait_for(|shutdown| *shutdown).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connection_limits() {
+        let listener = Listener::Tcp(crate::util::io::create_listener("127.0.0.1:0".parse().unwrap()).await.unwrap());
+        let Listener::Tcp(tcp) = &listener else { unreachable!() };
+        let addr = tcp.local_addr().unwrap();
+        let stats = ConnectionStats::default();
+        let param = ConnectionLimitParam {
+            max_connections: Some(2),
+            max_connections_per_ip: Some(1),
+            action: LimitAction::Close,
+        };
+        let limiter = ConnectionLimiter::new(Some(param), stats.clone());
+        let mut backoff = AcceptBackoff::default();
+
+        let _clients: Vec<_> = futures_util::future::try_join_all((0..3).map(|_| tokio::net::TcpStream::connect(addr)))
+            .await
+            .unwrap();
+        let (_, peer1, mut guard1) = limiter.accept(&listener, &mut backoff).await.unwrap().unwrap();
+        let (_, peer2, mut guard2) = limiter.accept(&listener, &mut backoff).await.unwrap().unwrap();
+        assert!(limiter.accept(&listener, &mut backoff).await.unwrap().is_none());
+        assert_eq!(stats.open_connections(), 2);
+        assert_eq!(stats.rejected_by_max_connections(), 1);
+
+        assert!(guard1.acquire_ip(&peer1));
+        assert!(!guard2.acquire_ip(&peer2));
+        assert_eq!(stats.open_connections_of(peer1.socket_addr().ip()), 1);
+        assert_eq!(stats.rejected_by_max_connections_per_ip(), 1);
+        // 连续被拒绝时只计数，警告在间隔内只输出一条
+        assert!(!guard2.acquire_ip(&peer2));
+        assert_eq!(stats.rejected_by_max_connections_per_ip(), 2);
+        assert_eq!(stats.inner.per_ip_warn.lock().unwrap().suppressed, 1);
+
+        drop(guard1);
+        drop(guard2);
+        assert_eq!(stats.open_connections(), 0);
+        assert_eq!(stats.open_connections_of(peer1.socket_addr().ip()), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_backpressure() {
+        let listener = Listener::Tcp(crate::util::io::create_listener("127.0.0.1:0".parse().unwrap()).await.unwrap());
+        let Listener::Tcp(tcp) = &listener else { unreachable!() };
+        let addr = tcp.local_addr().unwrap();
+        let stats = ConnectionStats::default();
+        let param = ConnectionLimitParam {
+            max_connections: Some(1),
+            max_connections_per_ip: None,
+            action: LimitAction::Backpressure,
+        };
+        let limiter = ConnectionLimiter::new(Some(param), stats.clone());
+        let mut backoff = AcceptBackoff::default();
+
+        let _clients: Vec<_> = futures_util::future::try_join_all((0..3).map(|_| tokio::net::TcpStream::connect(addr)))
+            .await
+            .unwrap();
+        let (_, _, mut guard) = limiter.accept(&listener, &mut backoff).await.unwrap().unwrap();
+        // 每次释放限额后只 accept 一个连接，随后再次暂停；警告在间隔内只输出一条
+        for paused in 1..=2 {
+            let accept = limiter.accept(&listener, &mut backoff);
+            tokio::pin!(accept);
+            assert!(tokio::time::timeout(Duration::from_millis(50), &mut accept).await.is_err());
+            assert_eq!(stats.paused_by_max_connections(), paused);
+            drop(guard);
+            (_, _, guard) = accept.await.unwrap().unwrap();
+        }
+        assert_eq!(stats.inner.paused_warn.lock().unwrap().suppressed, 1);
+        assert_eq!(stats.rejected_by_max_connections(), 0);
+    }
+
+    #[test]
+    fn test_accept_error_kind() {
+        let kind = |e: io::Error| AcceptErrorKind::of(&e);
+        assert_eq!(kind(io::ErrorKind::ConnectionAborted.into()), AcceptErrorKind::Connection);
+        assert_eq!(kind(io::ErrorKind::InvalidInput.into()), AcceptErrorKind::Fatal);
+        assert_eq!(kind(io::Error::other("unknown")), AcceptErrorKind::Resource);
+        #[cfg(unix)]
+        {
+            assert_eq!(kind(io::Error::from_raw_os_error(libc::EMFILE)), AcceptErrorKind::Resource);
+            assert_eq!(kind(io::Error::from_raw_os_error(libc::ENFILE)), AcceptErrorKind::Resource);
+            assert_eq!(kind(io::Error::from_raw_os_error(libc::ECONNABORTED)), AcceptErrorKind::Connection);
+            assert_eq!(kind(io::Error::from_raw_os_error(libc::EPROTO)), AcceptErrorKind::Connection);
+            assert_eq!(kind(io::Error::from_raw_os_error(libc::EBADF)), AcceptErrorKind::Fatal);
+        }
+    }
+
+    #[test]
+    fn test_accept_backoff() {
+        let mut backoff = AcceptBackoff::default();
+        let delays: Vec<_> = (0..10).map(|_| backoff.next_delay()).collect();
+        assert_eq!(delays[0], ACCEPT_BACKOFF_MIN);
+        assert_eq!(delays[1], ACCEPT_BACKOFF_MIN * 2);
+        assert_eq!(delays[9], ACCEPT_BACKOFF_MAX);
+
+        assert_eq!(backoff.warn.should_warn(ACCEPT_WARN_INTERVAL), Some(0));
+        assert_eq!(backoff.warn.should_warn(ACCEPT_WARN_INTERVAL), None);
+        assert_eq!(backoff.warn.should_warn(ACCEPT_WARN_INTERVAL), None);
+        backoff.warn.last_warn = Some(Instant::now() - ACCEPT_WARN_INTERVAL);
+        assert_eq!(backoff.warn.should_warn(ACCEPT_WARN_INTERVAL), Some(2));
+    }
+}
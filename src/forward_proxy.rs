@@ -0,0 +1,713 @@
+//! # 正向代理模块
+//!
+//! 提供处理 HTTP `CONNECT` 请求的请求拦截器，在客户端和目标地址之间建立 TCP 隧道
+//!
+//! # 主要组件
+//! - `ConnectProxy`: CONNECT 隧道拦截器，支持 `Proxy-Authorization` Basic 认证、目标地址规则和连接超时，
+//!   默认拒绝本机和内网目标地址
+//! - `DestinationRule`: 目标地址规则，按主机名、域名后缀或 CIDR 网段以及端口匹配
+//! - `TunnelStats`: 当前打开的隧道数和隧道转发的字节数
+//!
+//! # 隧道
+//! 拦截器连接目标地址成功后返回 200 响应，hyper 完成协议升级后在独立的任务中双向转发数据，
+//! 隧道关闭时记录持续时长和双向字节数。升级后的连接不受优雅关闭管理，
+//! 服务器停止接受新连接时 ([`ShutdownSignal`]) 隧道立即关闭。
+//! HTTP/1 和 HTTP/2 请求都可以建立隧道，HTTP/3 请求返回 501
+
+use std::{
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    response::IntoResponse,
+};
+use hyper::{
+    Method, StatusCode,
+    header::{self, HeaderValue},
+};
+use hyper_util::rt::TokioIo;
+use log::{debug, info, warn};
+use tokio::{net::TcpStream, sync::Notify};
+
+use crate::{
+    InterceptResult, ReqInterceptor,
+    connection::ShutdownSignal,
+    ip_filter::IpNet,
+    util::io::{CountingIO, PeerAddr},
+};
+
+/// 默认的目标地址连接超时时间
+const DEFAULT_DIAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 要求认证时返回的 `Proxy-Authenticate` 头
+const PROXY_AUTHENTICATE: &str = "Basic realm=\"proxy\"";
+
+/// 目标主机的匹配方式
+///
+/// # 变体
+/// - `Any`: 匹配任意主机，规则写作 `*`
+/// - `Suffix(String)`: 匹配该域名的所有子域名 (不包括域名本身)，规则写作 `*.example.com`
+/// - `Exact(String)`: 匹配该主机名，不区分大小写
+/// - `Net(IpNet)`: 匹配 CIDR 网段中的地址，目标为主机名时按解析出的地址匹配
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostPattern {
+    Any,
+    Suffix(String),
+    Exact(String),
+    Net(IpNet),
+}
+
+impl fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HostPattern::Any => f.write_str("*"),
+            HostPattern::Suffix(suffix) => write!(f, "*.{suffix}"),
+            HostPattern::Exact(name) => f.write_str(name),
+            HostPattern::Net(net) => write!(f, "{net}"),
+        }
+    }
+}
+
+/// 目标地址规则
+///
+/// 从字符串解析时格式为 `主机[:端口]`，主机可以是 `*`、`*.example.com`、主机名、IP 地址或 CIDR 网段，
+/// 带端口的 IPv6 地址需要用方括号括起来，例如 `[2001:db8::1]:443`
+///
+/// # 字段
+/// - `host`: 目标主机的匹配方式
+/// - `port`: 目标端口，为 None 时匹配所有端口
+///
+/// # 示例
+///
+/// ```
+/// use axum_bootstrap::forward_proxy::{DestinationRule, HostPattern};
+///
+/// let rule: DestinationRule = "*.example.com:443".parse().unwrap();
+/// assert_eq!(rule.host, HostPattern::Suffix("example.com".to_string()));
+/// assert_eq!(rule.port, Some(443));
+/// let rule: DestinationRule = "10.0.0.0/8".parse().unwrap();
+/// assert_eq!(rule.port, None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DestinationRule {
+    pub host: HostPattern,
+    pub port: Option<u16>,
+}
+
+impl DestinationRule {
+    /// 目标地址是否匹配该规则
+    ///
+    /// # 参数
+    /// - `host`: 小写、去掉方括号和末尾 `.` 的目标主机
+    /// - `port`: 目标端口
+    /// - `ip`: 解析出的目标地址，尚未解析时为 None
+    fn matches(&self, host: &str, port: u16, ip: Option<IpAddr>) -> bool {
+        if self.port.is_some_and(|p| p != port) {
+            return false;
+        }
+        match &self.host {
+            HostPattern::Any => true,
+            HostPattern::Suffix(suffix) => host.strip_suffix(suffix.as_str()).is_some_and(|rest| rest.ends_with('.')),
+            HostPattern::Exact(name) => host == name,
+            HostPattern::Net(net) => ip.is_some_and(|ip| net.contains(ip)),
+        }
+    }
+}
+
+impl FromStr for DestinationRule {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid destination rule: {s}"));
+        let (host, port) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+                match rest {
+                    "" => (host, None),
+                    _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+                }
+            }
+            // 不带方括号的 IPv6 地址中的 `:` 不是端口分隔符
+            None if s.matches(':').count() > 1 => (s, None),
+            None => match s.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (s, None),
+            },
+        };
+        let port = port.map(|port| port.parse::<u16>().map_err(|_| invalid())).transpose()?;
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let host = if host == "*" {
+            HostPattern::Any
+        } else if let Some(suffix) = host.strip_prefix("*.") {
+            HostPattern::Suffix(suffix.to_string())
+        } else if let Ok(net) = host.parse::<IpNet>() {
+            HostPattern::Net(net)
+        } else if !host.is_empty() && !host.contains(['*', '/', ':']) {
+            HostPattern::Exact(host)
+        } else {
+            return Err(invalid());
+        };
+        Ok(Self { host, port })
+    }
+}
+
+/// 隧道统计
+///
+/// 可以克隆，所有克隆共享同一份计数。上行为客户端发往目标地址的数据，下行为目标地址发往客户端的数据，
+/// 字节数在隧道关闭时累加
+#[derive(Debug, Clone, Default)]
+pub struct TunnelStats {
+    inner: Arc<TunnelStatsInner>,
+}
+
+/// 隧道统计的共享计数
+#[derive(Debug, Default)]
+struct TunnelStatsInner {
+    open: AtomicUsize,
+    total: AtomicU64,
+    bytes_up: AtomicU64,
+    bytes_down: AtomicU64,
+    closed: Notify,
+}
+
+impl TunnelStats {
+    /// 当前打开的隧道数
+    pub fn open_tunnels(&self) -> usize {
+        self.inner.open.load(Ordering::Relaxed)
+    }
+
+    /// 建立过的隧道总数
+    pub fn total_tunnels(&self) -> u64 {
+        self.inner.total.load(Ordering::Relaxed)
+    }
+
+    /// 已关闭的隧道上行的总字节数
+    pub fn bytes_up(&self) -> u64 {
+        self.inner.bytes_up.load(Ordering::Relaxed)
+    }
+
+    /// 已关闭的隧道下行的总字节数
+    pub fn bytes_down(&self) -> u64 {
+        self.inner.bytes_down.load(Ordering::Relaxed)
+    }
+
+    fn open(&self) {
+        self.inner.open.fetch_add(1, Ordering::Relaxed);
+        self.inner.total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn close(&self, bytes_up: u64, bytes_down: u64) {
+        self.inner.bytes_up.fetch_add(bytes_up, Ordering::Relaxed);
+        self.inner.bytes_down.fetch_add(bytes_down, Ordering::Relaxed);
+        self.inner.open.fetch_sub(1, Ordering::Relaxed);
+        self.inner.closed.notify_waiters();
+    }
+
+    /// 等待所有打开的隧道关闭，返回时字节数已经累加
+    #[cfg(test)]
+    async fn wait_closed(&self) {
+        loop {
+            // 先注册再检查，避免错过检查之后的关闭通知
+            let closed = self.inner.closed.notified();
+            if self.open_tunnels() == 0 {
+                return;
+            }
+            closed.await;
+        }
+    }
+}
+
+/// CONNECT 隧道拦截器
+///
+/// 处理 `CONNECT` 请求，其他请求原样交给后续拦截器和路由。可以克隆，所有克隆共享同一份配置和统计。
+/// 依次检查:
+/// - 配置了 Basic 认证时校验 `Proxy-Authorization` 头，失败返回 407
+/// - 目标地址规则，拒绝列表优先；允许列表不为空时只允许匹配的目标。主机名规则按请求中的主机名匹配，
+///   CIDR 规则按解析出的每个地址匹配，避免通过解析到内网地址的域名绕过规则。被拒绝返回 403
+/// - 在超时时间内解析并连接目标地址，超时返回 504，失败返回 502
+///
+/// 默认拒绝解析到环回、私有 (RFC 1918、IPv6 ULA)、共享 (`100.64.0.0/10`)、链路本地、未指定、广播和组播地址的目标，
+/// 包括 `169.254.169.254`、`100.100.100.200` 等云元数据地址和内嵌这些 IPv4 地址的 NAT64、6to4 地址。
+/// 允许列表也不能放开这些地址，避免拦截器成为访问本机端口和内网的开放代理。
+/// 确实需要代理到这些地址时调用 [`ConnectProxy::allow_private_destinations`]
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use axum::Router;
+/// use axum_bootstrap::{forward_proxy::ConnectProxy, generate_shutdown_receiver, new_server};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let proxy = ConnectProxy::new()
+///         .with_basic_auth("user", "password")
+///         .with_allow("*:443".parse().unwrap())
+///         .with_deny("*.internal.example.com".parse().unwrap())
+///         .with_dial_timeout(Duration::from_secs(5));
+///     let stats = proxy.stats().clone();
+///     let server = new_server(8080, Router::new(), generate_shutdown_receiver()).with_interceptor(proxy);
+///     server.run().await.unwrap();
+///     println!("tunnels: {}, up: {}, down: {}", stats.total_tunnels(), stats.bytes_up(), stats.bytes_down());
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConnectProxy {
+    inner: Arc<ConnectProxyInner>,
+    stats: TunnelStats,
+}
+
+/// CONNECT 隧道拦截器的配置
+#[derive(Debug, Clone, Default)]
+struct ConnectProxyInner {
+    credentials: Vec<String>,
+    allow: Vec<DestinationRule>,
+    deny: Vec<DestinationRule>,
+    dial_timeout: Option<Duration>,
+    allow_private: bool,
+}
+
+/// 连接目标地址失败的原因
+enum DialError {
+    Denied,
+    Timeout,
+    Io(io::Error),
+}
+
+impl ConnectProxy {
+    /// 创建不要求认证、允许除本机和内网地址以外所有目标地址的拦截器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加 Basic 认证的用户，可以多次调用添加多个用户
+    ///
+    /// # 参数
+    /// - `username`: 用户名
+    /// - `password`: 密码
+    pub fn with_basic_auth(mut self, username: impl AsRef<str>, password: impl AsRef<str>) -> Self {
+        let credential = base64_encode(format!("{}:{}", username.as_ref(), password.as_ref()).as_bytes());
+        Arc::make_mut(&mut self.inner).credentials.push(credential);
+        self
+    }
+
+    /// 添加允许列表规则
+    pub fn with_allow(mut self, rule: DestinationRule) -> Self {
+        Arc::make_mut(&mut self.inner).allow.push(rule);
+        self
+    }
+
+    /// 添加拒绝列表规则
+    pub fn with_deny(mut self, rule: DestinationRule) -> Self {
+        Arc::make_mut(&mut self.inner).deny.push(rule);
+        self
+    }
+
+    /// 设置目标地址的连接超时时间
+    ///
+    /// # 参数
+    /// - `timeout`: 包括 DNS 解析和 TCP 连接的总时间，默认 10 秒
+    pub fn with_dial_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.inner).dial_timeout = Some(timeout);
+        self
+    }
+
+    /// 允许连接环回、私有、共享、链路本地、未指定、广播和组播地址
+    ///
+    /// 之后这些地址和其他地址一样只受允许列表和拒绝列表约束，通常需要同时配置认证或规则
+    pub fn allow_private_destinations(mut self) -> Self {
+        Arc::make_mut(&mut self.inner).allow_private = true;
+        self
+    }
+
+    /// 隧道统计
+    pub fn stats(&self) -> &TunnelStats {
+        &self.stats
+    }
+
+    /// 校验 `Proxy-Authorization` 头，没有配置认证时总是通过
+    fn authorized(&self, headers: &hyper::HeaderMap) -> bool {
+        if self.inner.credentials.is_empty() {
+            return true;
+        }
+        let Some((scheme, token)) = headers
+            .get(header::PROXY_AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().split_once(' '))
+        else {
+            return false;
+        };
+        scheme.eq_ignore_ascii_case("basic")
+            && self
+                .inner
+                .credentials
+                .iter()
+                .any(|credential| constant_time_eq(credential.as_bytes(), token.trim().as_bytes()))
+    }
+
+    /// 目标地址是否被允许
+    ///
+    /// # 参数
+    /// - `host`: 小写、去掉方括号和末尾 `.` 的目标主机
+    /// - `port`: 目标端口
+    /// - `ip`: 解析出的目标地址，尚未解析时为 None，此时只检查拒绝列表中的主机名规则
+    fn is_allowed(&self, host: &str, port: u16, ip: Option<IpAddr>) -> bool {
+        if self.inner.deny.iter().any(|rule| rule.matches(host, port, ip)) {
+            return false;
+        }
+        if !self.inner.allow_private && ip.is_some_and(is_private) {
+            return false;
+        }
+        ip.is_none() || self.inner.allow.is_empty() || self.inner.allow.iter().any(|rule| rule.matches(host, port, ip))
+    }
+
+    /// 在超时时间内解析并连接目标地址，依次尝试每个被允许的地址
+    async fn dial(&self, host: &str, port: u16) -> Result<TcpStream, DialError> {
+        let dial = async {
+            let mut last_error = None;
+            let mut denied = true;
+            for addr in tokio::net::lookup_host((host, port)).await.map_err(DialError::Io)? {
+                if !self.is_allowed(host, port, Some(addr.ip())) {
+                    continue;
+                }
+                denied = false;
+                match TcpStream::connect(addr).await {
+                    Ok(stream) => {
+                        let _ = stream.set_nodelay(true);
+                        return Ok(stream);
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            match last_error {
+                Some(e) => Err(DialError::Io(e)),
+                None if denied => Err(DialError::Denied),
+                None => Err(DialError::Io(io::ErrorKind::NotFound.into())),
+            }
+        };
+        let timeout = self.inner.dial_timeout.unwrap_or(DEFAULT_DIAL_TIMEOUT);
+        tokio::time::timeout(timeout, dial).await.unwrap_or(Err(DialError::Timeout))
+    }
+}
+
+impl ReqInterceptor for ConnectProxy {
+    type Error = StatusCode;
+
+    async fn intercept(&self, mut req: Request, ip: SocketAddr) -> InterceptResult<Self::Error> {
+        if req.method() != Method::CONNECT {
+            return InterceptResult::Continue(req);
+        }
+        let peer_addr = match req.extensions().get::<ConnectInfo<PeerAddr>>() {
+            Some(ConnectInfo(peer_addr)) => peer_addr.clone(),
+            None => PeerAddr::Tcp(ip),
+        };
+        if !self.authorized(req.headers()) {
+            debug!("[connect proxy]: unauthorized CONNECT {} from {peer_addr}", req.uri());
+            let authenticate = [(header::PROXY_AUTHENTICATE, HeaderValue::from_static(PROXY_AUTHENTICATE))];
+            return InterceptResult::Return((StatusCode::PROXY_AUTHENTICATION_REQUIRED, authenticate).into_response());
+        }
+        // HTTP/3 没有协议升级
+        if req.extensions().get::<hyper::upgrade::OnUpgrade>().is_none() {
+            return InterceptResult::Error(StatusCode::NOT_IMPLEMENTED);
+        }
+        let Some((host, port)) = req
+            .uri()
+            .authority()
+            .and_then(|authority| Some((authority.host(), authority.port_u16()?)))
+        else {
+            return InterceptResult::Error(StatusCode::BAD_REQUEST);
+        };
+        let target = format!("{host}:{port}");
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        if !self.is_allowed(&host, port, None) {
+            debug!("[connect proxy]: denied CONNECT {target} from {peer_addr}");
+            return InterceptResult::Error(StatusCode::FORBIDDEN);
+        }
+        let upstream = match self.dial(&host, port).await {
+            Ok(upstream) => upstream,
+            Err(DialError::Denied) => {
+                debug!("[connect proxy]: denied CONNECT {target} from {peer_addr}");
+                return InterceptResult::Error(StatusCode::FORBIDDEN);
+            }
+            Err(DialError::Timeout) => {
+                info!("[connect proxy]: dial {target} timeout from {peer_addr}");
+                return InterceptResult::Error(StatusCode::GATEWAY_TIMEOUT);
+            }
+            Err(DialError::Io(e)) => {
+                info!("[connect proxy]: dial {target} error: {e} from {peer_addr}");
+                return InterceptResult::Error(StatusCode::BAD_GATEWAY);
+            }
+        };
+        let on_upgrade = hyper::upgrade::on(&mut req);
+        let shutdown = req.extensions().get::<ShutdownSignal>().cloned();
+        let tunnel = Tunnel {
+            peer_addr,
+            target,
+            stats: self.stats.clone(),
+        };
+        tokio::spawn(async move {
+            match on_upgrade.await {
+                Ok(upgraded) => tunnel.run(TokioIo::new(upgraded), upstream, shutdown).await,
+                Err(e) => warn!("[connect proxy]: upgrade error: {e} from {}", tunnel.peer_addr),
+            }
+        });
+        InterceptResult::Return(StatusCode::OK.into_response())
+    }
+}
+
+/// 建立成功的隧道
+struct Tunnel {
+    peer_addr: PeerAddr,
+    target: String,
+    stats: TunnelStats,
+}
+
+impl Tunnel {
+    /// 双向转发数据，直到任一方向出错、双方都关闭或服务器开始关闭
+    async fn run(self, client: TokioIo<hyper::upgrade::Upgraded>, mut upstream: TcpStream, shutdown: Option<ShutdownSignal>) {
+        let started_at = Instant::now();
+        let (mut client, count) = CountingIO::new(client);
+        self.stats.open();
+        debug!("[connect proxy]: tunnel {} -> {} opened", self.peer_addr, self.target);
+        let shutdown = async {
+            match shutdown {
+                Some(shutdown) => shutdown.wait().await,
+                None => std::future::pending().await,
+            }
+        };
+        let result = tokio::select! {
+            result = tokio::io::copy_bidirectional(&mut client, &mut upstream) => result.map(|_| ()),
+            _ = shutdown => Err(io::Error::other("server shutting down")),
+        };
+        self.stats.close(count.read(), count.written());
+        let reason = match result {
+            Ok(()) => String::new(),
+            Err(e) => format!(" ({e})"),
+        };
+        info!(
+            "[connect proxy]: tunnel {} -> {} closed after {:?}, up {} bytes, down {} bytes{reason}",
+            self.peer_addr,
+            self.target,
+            started_at.elapsed(),
+            count.read(),
+            count.written()
+        );
+    }
+}
+
+/// 是否为环回、私有、共享 (CGNAT)、链路本地、未指定、广播或组播地址
+///
+/// IPv4 映射 (`::ffff:0:0/96`)、NAT64 (`64:ff9b::/96`) 和 6to4 (`2002::/16`) 的 IPv6 地址按内嵌的 IPv4 地址判断
+fn is_private(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // 100.64.0.0/10，包括阿里云的元数据地址 100.100.100.200
+                || (a == 100 && b & 0xc0 == 64)
+        }
+        IpAddr::V6(ip) => match ip.segments() {
+            [0x64, 0xff9b, 0, 0, 0, 0, high, low] | [0x2002, high, low, ..] => {
+                is_private(IpAddr::V4(Ipv4Addr::from(u32::from(high) << 16 | u32::from(low))))
+            }
+            _ => ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local() || ip.is_unspecified() || ip.is_multicast(),
+        },
+    }
+}
+
+/// 使用标准字母表编码 base64，带填充
+fn base64_encode(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// 比较两个字节串，耗时与内容无关
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_destination_rules() {
+        let rule = |s: &str| s.parse::<DestinationRule>().unwrap();
+        assert_eq!(rule("*").host, HostPattern::Any);
+        assert_eq!(
+            rule("Example.COM.:8080"),
+            DestinationRule {
+                host: HostPattern::Exact("example.com".into()),
+                port: Some(8080)
+            }
+        );
+        assert_eq!(rule("2001:db8::/32").port, None);
+        assert_eq!(rule("[2001:db8::1]:443").port, Some(443));
+        assert_eq!(rule("*.example.com").host.to_string(), "*.example.com");
+        for invalid in ["", "*:http", "a*b.com", "[::1", "10.0.0.0/33"] {
+            assert!(invalid.parse::<DestinationRule>().is_err(), "{invalid}");
+        }
+
+        let proxy = ConnectProxy::new()
+            .allow_private_destinations()
+            .with_allow(rule("*.example.com:443"))
+            .with_allow(rule("10.0.0.0/8"))
+            .with_deny(rule("secret.example.com"))
+            .with_deny(rule("10.1.0.0/16"));
+        let ip = |s: &str| Some(s.parse().unwrap());
+        assert!(proxy.is_allowed("www.example.com", 443, ip("1.1.1.1")));
+        assert!(!proxy.is_allowed("example.com", 443, ip("1.1.1.1")));
+        assert!(!proxy.is_allowed("www.example.com", 80, ip("1.1.1.1")));
+        assert!(!proxy.is_allowed("secret.example.com", 443, None));
+        assert!(proxy.is_allowed("internal", 22, ip("10.2.0.1")));
+        // 解析到拒绝网段的域名被拒绝
+        assert!(!proxy.is_allowed("www.example.com", 443, ip("10.1.0.1")));
+
+        // 默认拒绝本机和内网地址，允许列表也不能放开
+        let proxy = ConnectProxy::new().with_allow(rule("10.0.0.0/8"));
+        assert!(!proxy.is_allowed("internal", 22, ip("10.2.0.1")));
+        let proxy = ConnectProxy::new();
+        for private in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "100.64.0.1",
+            "100.100.100.200",
+            "100.127.255.254",
+            "255.255.255.255",
+            "224.0.0.1",
+            "239.255.255.250",
+            "ff02::1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "2002:c0a8:101::1",
+        ] {
+            assert!(!proxy.is_allowed("example.com", 443, ip(private)), "{private}");
+        }
+        assert!(proxy.is_allowed("example.com", 443, ip("93.184.216.34")));
+        assert!(proxy.is_allowed("example.com", 443, ip("2606:2800:220:1::1")));
+        assert!(proxy.is_allowed("example.com", 443, ip("100.128.0.1")));
+        assert!(proxy.is_allowed("example.com", 443, ip("64:ff9b::5db8:d822")));
+        assert!(proxy.is_allowed("localhost", 443, None));
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"Aladdin:open sesame"), "QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+
+        let proxy = ConnectProxy::new().with_basic_auth("Aladdin", "open sesame");
+        let headers = |value: &'static str| hyper::HeaderMap::from_iter([(header::PROXY_AUTHORIZATION, HeaderValue::from_static(value))]);
+        assert!(proxy.authorized(&headers("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==")));
+        assert!(proxy.authorized(&headers("basic  QWxhZGRpbjpvcGVuIHNlc2FtZQ==")));
+        assert!(!proxy.authorized(&headers("Basic QWxhZGRpbjpvcGVuIHNlc2FtZR==")));
+        assert!(!proxy.authorized(&headers("Bearer QWxhZGRpbjpvcGVuIHNlc2FtZQ==")));
+        assert!(!proxy.authorized(&hyper::HeaderMap::new()));
+        assert!(ConnectProxy::new().authorized(&hyper::HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_connect_tunnel() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // 回显服务器
+        let echo = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let echo_addr = echo.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = echo.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = stream.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+
+        let (_shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let proxy = ConnectProxy::new()
+            .with_basic_auth("user", "pass")
+            .allow_private_destinations()
+            .with_deny(format!("*:{}", echo_addr.port() + 1).parse().unwrap());
+        let stats = proxy.stats().clone();
+        let router = axum::Router::new().route("/", axum::routing::get(|| async { "ok" }));
+        let (handle, addr) = crate::spawn_loopback(crate::new_server(0, router, shutdown_rx).with_interceptor(proxy)).await;
+        let connect = |target: String, auth: &'static str| async move {
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client
+                .write_all(format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n{auth}\r\n").as_bytes())
+                .await
+                .unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                let mut byte = [0];
+                if client.read(&mut byte).await.unwrap() == 0 {
+                    break;
+                }
+                head.push(byte[0]);
+            }
+            (client, String::from_utf8(head).unwrap())
+        };
+        let auth = "Proxy-Authorization: Basic dXNlcjpwYXNz\r\n";
+
+        let (_, head) = connect(echo_addr.to_string(), "").await;
+        assert!(head.starts_with("HTTP/1.1 407"));
+        assert!(head.to_ascii_lowercase().contains("proxy-authenticate: basic realm=\"proxy\""));
+        let (_, head) = connect(format!("127.0.0.1:{}", echo_addr.port() + 1), auth).await;
+        assert!(head.starts_with("HTTP/1.1 403"));
+
+        let (mut client, head) = connect(echo_addr.to_string(), auth).await;
+        assert!(head.starts_with("HTTP/1.1 200"));
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        assert_eq!(stats.open_tunnels(), 1);
+
+        // 服务器关闭时隧道随之关闭
+        handle.stop().await;
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+        tokio::time::timeout(Duration::from_secs(5), stats.wait_closed()).await.unwrap();
+        assert_eq!(stats.total_tunnels(), 1);
+        assert_eq!((stats.bytes_up(), stats.bytes_down()), (5, 5));
+    }
+}
@@ -0,0 +1,403 @@
+//! # 热重启模块
+//!
+//! 在不中断服务的情况下升级二进制：收到 SIGUSR2 后重新执行当前程序，
+//! 把监听 socket 交给新进程，新进程就绪后旧进程停止 accept 并优雅关闭已有连接
+//!
+//! # 交接协议
+//! - `AXUM_BOOTSTRAP_UPGRADE_FDS`: 新进程继承的监听 socket，格式为 `fd:name,fd:name`
+//! - `AXUM_BOOTSTRAP_READY_FD`: 就绪通知 socket，新进程完成启动后写入一个字节并关闭
+//! - `AXUM_BOOTSTRAP_UPGRADE_PID`: 旧进程的 PID
+//!
+//! 与 systemd 的 `LISTEN_PID` 类似，只有父进程 PID 与 `AXUM_BOOTSTRAP_UPGRADE_PID` 相同时新进程才接管文件描述符，
+//! 继承了环境变量的其它进程 (例如新进程启动的子进程) 会忽略它们；接管之前还会检查文件描述符确实是 socket。
+//! 监听 socket 在交接期间始终处于监听状态，新连接会在 backlog 中排队，不会被拒绝。
+//! 旧进程中的监听 socket 始终保留 `FD_CLOEXEC`，只在新进程 exec 之前清除，不会泄露给其它线程启动的子进程
+
+use std::{
+    ffi::OsString,
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use log::{error, info, warn};
+use socket2::{SockRef, Socket};
+use tokio::{io::AsyncReadExt, sync::broadcast, task::JoinHandle};
+
+use crate::util::io::{Listener, listener_from_fd};
+
+/// 继承的监听 socket 环境变量
+const UPGRADE_FDS_ENV: &str = "AXUM_BOOTSTRAP_UPGRADE_FDS";
+
+/// 就绪通知 socket 环境变量
+const READY_FD_ENV: &str = "AXUM_BOOTSTRAP_READY_FD";
+
+/// 旧进程 PID 环境变量
+const UPGRADE_PID_ENV: &str = "AXUM_BOOTSTRAP_UPGRADE_PID";
+
+/// 继承的监听 socket 是否已被取走，文件描述符只能转换一次
+static INHERITED_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// 是否已经通知过就绪，文件描述符只能转换一次
+static READY_NOTIFIED: AtomicBool = AtomicBool::new(false);
+
+/// 获取旧进程交接的监听器
+///
+/// # 返回
+/// - `Ok(Some(Vec<(Listener, String)>))`: 继承的监听器及其名称
+/// - `Ok(None)`: 当前进程不是由热重启启动的，或环境变量不是由父进程设置给当前进程的
+/// - `Err(io::Error)`: 环境变量格式错误或文件描述符不是监听 socket，此时不会关闭任何文件描述符
+pub(crate) fn inherited_listeners() -> io::Result<Option<Vec<(Listener, String)>>> {
+    let Ok(fds) = std::env::var(UPGRADE_FDS_ENV) else {
+        return Ok(None);
+    };
+    if !handed_over_by_parent() || INHERITED_TAKEN.swap(true, Ordering::SeqCst) {
+        return Ok(None);
+    }
+    let fds = parse_upgrade_fds(&fds)?;
+    for (fd, _) in &fds {
+        check_stream_socket(*fd, true)?;
+    }
+    fds.into_iter()
+        .map(|(fd, name)| {
+            // SAFETY: 父进程是交接的旧进程，文件描述符由它清除 FD_CLOEXEC 后传递给当前进程，
+            // 并且已经确认是监听 socket；INHERITED_TAKEN 保证只会被转换一次
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            Ok((listener_from_fd(fd)?, name))
+        })
+        .collect::<io::Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// 解析 `AXUM_BOOTSTRAP_UPGRADE_FDS` 环境变量
+///
+/// # 参数
+/// - `fds`: 环境变量的值，格式为 `fd:name,fd:name`
+///
+/// # 返回
+/// - `Ok(Vec<(RawFd, String)>)`: 文件描述符及其名称
+/// - `Err(io::Error)`: 格式错误
+fn parse_upgrade_fds(fds: &str) -> io::Result<Vec<(RawFd, String)>> {
+    fds.split(',')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (fd, name) = entry.split_once(':').unwrap_or((entry, ""));
+            match fd.parse::<RawFd>() {
+                Ok(fd) if fd >= 0 => Ok((fd, name.to_string())),
+                _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid {UPGRADE_FDS_ENV}: {entry}"))),
+            }
+        })
+        .collect()
+}
+
+/// 交接的环境变量是否由父进程设置给当前进程
+///
+/// 旧进程导出自己的 PID，只有父进程与之相同时才是交接的新进程
+fn handed_over_by_parent() -> bool {
+    std::env::var(UPGRADE_PID_ENV).ok().and_then(|pid| pid.parse::<u32>().ok()) == Some(std::os::unix::process::parent_id())
+}
+
+/// 检查文件描述符是否为流式 socket
+///
+/// 在转换为 `OwnedFd` 之前调用，检查失败时不会关闭该文件描述符
+///
+/// # 参数
+/// - `fd`: 继承的文件描述符
+/// - `listening`: 是否还要求处于监听状态
+fn check_stream_socket(fd: RawFd, listening: bool) -> io::Result<()> {
+    let option = |name| {
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: 只读取 socket 选项，value 和 len 在调用期间有效；fd 未打开或不是 socket 时返回错误
+        match unsafe { libc::getsockopt(fd, libc::SOL_SOCKET, name, (&raw mut value).cast(), &mut len) } {
+            0 => Ok(value),
+            _ => Err(io::Error::last_os_error()),
+        }
+    };
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("inherited fd {fd} is not a stream socket"));
+    if option(libc::SO_TYPE).map_err(|_| invalid())? != libc::SOCK_STREAM {
+        return Err(invalid());
+    }
+    if listening && option(libc::SO_ACCEPTCONN)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("inherited fd {fd} is not listening")));
+    }
+    Ok(())
+}
+
+/// 通知旧进程当前进程已经就绪
+///
+/// 当前进程不是由热重启启动时，或环境变量不是由父进程设置给当前进程时什么都不做
+pub(crate) fn notify_ready() {
+    let Some(fd) = std::env::var(READY_FD_ENV).ok().and_then(|fd| fd.parse::<RawFd>().ok()) else {
+        return;
+    };
+    if !handed_over_by_parent() || READY_NOTIFIED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if let Err(e) = check_stream_socket(fd, false) {
+        warn!("notify hot restart ready error: {e}");
+        return;
+    }
+    // SAFETY: 父进程是交接的旧进程，文件描述符由它清除 FD_CLOEXEC 后传递给当前进程，
+    // 并且已经确认是流式 socket；READY_NOTIFIED 保证只会被转换一次
+    let mut stream = std::os::unix::net::UnixStream::from(unsafe { OwnedFd::from_raw_fd(fd) });
+    if let Err(e) = io::Write::write_all(&mut stream, b"1") {
+        warn!("notify hot restart ready error: {e}");
+    }
+}
+
+/// 热重启时重新执行的程序
+///
+/// # 字段
+/// - `path`: 程序路径
+/// - `args`: 命令行参数，不包括程序名
+struct Executable {
+    path: PathBuf,
+    args: Vec<OsString>,
+}
+
+impl Executable {
+    /// 记录当前程序的路径和命令行参数
+    ///
+    /// 必须在启动时调用：以 rename 方式替换二进制之后，Linux 上的 `/proc/self/exe` 会变成 `"/path/app (deleted)"`，
+    /// 收到 SIGUSR2 时再解析就无法启动新版本的程序
+    fn current() -> io::Result<Self> {
+        Ok(Self {
+            path: std::env::current_exe()?,
+            args: std::env::args_os().skip(1).collect(),
+        })
+    }
+}
+
+/// 启动热重启任务
+///
+/// 记录当前程序的路径并复制所有监听 socket，在后台等待 SIGUSR2。收到信号后启动新进程并交接监听 socket，
+/// 新进程在 `ready_timeout` 内就绪后通过 `stop_tx` 停止当前进程的 accept 循环
+///
+/// # 参数
+/// - `listeners`: 监听器及其交接名称
+/// - `ready_timeout`: 等待新进程就绪的超时时间，超时后终止新进程并继续提供服务
+/// - `stop_tx`: 停止 accept 循环的发送器
+///
+/// # 返回
+/// - `Ok(JoinHandle<bool>)`: 热重启任务，监听 socket 交接给新进程后以 `true` 结束；
+///   服务器停止时应当 abort 该任务，以关闭复制的监听 socket
+/// - `Err(io::Error)`: 获取当前程序路径、复制监听 socket 或注册信号失败
+pub(crate) fn spawn(listeners: &[(&Listener, &str)], ready_timeout: Duration, stop_tx: broadcast::Sender<()>) -> io::Result<JoinHandle<bool>> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let executable = Executable::current()?;
+    let sockets = listeners
+        .iter()
+        .map(|(listener, name)| Ok((SockRef::from(*listener).try_clone()?, name.to_string())))
+        .collect::<io::Result<Vec<(Socket, String)>>>()?;
+    let mut upgrade_signal = signal(SignalKind::user_defined2())?;
+    Ok(tokio::spawn(async move {
+        while upgrade_signal.recv().await.is_some() {
+            info!("receive SIGUSR2, start hot restart");
+            match upgrade(&sockets, &executable, ready_timeout).await {
+                Ok(pid) => {
+                    info!("new process {pid} is ready, stop accepting");
+                    let _ = stop_tx.send(());
+                    return true;
+                }
+                Err(e) => error!("hot restart failed, keep serving: {e}"),
+            }
+        }
+        false
+    }))
+}
+
+/// 启动新进程并等待其就绪
+///
+/// # 参数
+/// - `sockets`: 要交接的监听 socket 及其名称
+/// - `executable`: 启动时记录的程序路径和命令行参数
+/// - `ready_timeout`: 等待新进程就绪的超时时间
+///
+/// # 返回
+/// - `Ok(u32)`: 新进程的 PID
+/// - `Err(io::Error)`: 启动失败、新进程退出或就绪超时
+async fn upgrade(sockets: &[(Socket, String)], executable: &Executable, ready_timeout: Duration) -> io::Result<u32> {
+    let (ready_rx, ready_tx) = std::os::unix::net::UnixStream::pair()?;
+    let fds = sockets
+        .iter()
+        .map(|(socket, name)| format!("{}:{}", socket.as_raw_fd(), name))
+        .collect::<Vec<_>>()
+        .join(",");
+    let inherited: Vec<RawFd> = sockets
+        .iter()
+        .map(|(socket, _)| socket.as_raw_fd())
+        .chain([ready_tx.as_raw_fd()])
+        .collect();
+    let mut command = tokio::process::Command::new(&executable.path);
+    command
+        .args(&executable.args)
+        .env(UPGRADE_FDS_ENV, fds)
+        .env(READY_FD_ENV, ready_tx.as_raw_fd().to_string())
+        .env(UPGRADE_PID_ENV, std::process::id().to_string());
+    // SAFETY: 闭包在 fork 之后、exec 之前运行，只调用 async-signal-safe 的 fcntl，不分配内存也不加锁
+    unsafe {
+        command.pre_exec(move || inherited.iter().try_for_each(|fd| clear_cloexec(*fd)));
+    }
+    let spawned = command.spawn();
+    drop(ready_tx);
+    // 新进程就绪之前失败或被取消 (例如旧进程收到关闭信号) 时终止新进程
+    let mut child = ChildGuard(Some(spawned?));
+    let pid = child.0.as_ref().and_then(|child| child.id()).unwrap_or_default();
+    info!("spawned new process {pid}, waiting for it to be ready");
+
+    ready_rx.set_nonblocking(true)?;
+    let mut ready_rx = tokio::net::UnixStream::from_std(ready_rx)?;
+    let mut buf = [0u8; 1];
+    let result = match tokio::time::timeout(ready_timeout, ready_rx.read(&mut buf)).await {
+        Ok(Ok(1)) => Ok(pid),
+        Ok(Ok(_)) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("new process {pid} exited before ready"))),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, format!("new process {pid} not ready in {ready_timeout:?}"))),
+    };
+    if result.is_ok() {
+        // 新进程已就绪，之后独立运行
+        child.0.take();
+    }
+    result
+}
+
+/// 清除文件描述符的 `FD_CLOEXEC`，使其在 exec 之后仍然打开
+///
+/// 只在新进程 exec 之前调用，旧进程中的文件描述符不受影响
+fn clear_cloexec(fd: RawFd) -> io::Result<()> {
+    // SAFETY: 只读取和修改文件描述符标志，fd 在调用期间有效
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// 在 drop 时终止尚未就绪的新进程
+struct ChildGuard(Option<tokio::process::Child>);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        if let Some(child) = self.0.as_mut() {
+            if let Err(e) = child.start_kill() {
+                warn!("kill new process error: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    /// 文件描述符是否设置了 `FD_CLOEXEC`
+    fn is_cloexec(fd: RawFd) -> bool {
+        // SAFETY: 只读取文件描述符标志
+        unsafe { libc::fcntl(fd, libc::F_GETFD) & libc::FD_CLOEXEC != 0 }
+    }
+
+    #[tokio::test]
+    async fn test_upgrade() {
+        let listener = Listener::Tcp(tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap());
+        let Listener::Tcp(tcp) = &listener else { unreachable!() };
+        let addr = tcp.local_addr().unwrap();
+        let sockets = vec![(SockRef::from(&listener).try_clone().unwrap(), "main".to_string())];
+        // 由当前测试程序扮演新进程，只运行 test_upgrade_child
+        let executable = Executable {
+            path: std::env::current_exe().unwrap(),
+            args: ["hot_restart::tests::test_upgrade_child", "--exact"].map(OsString::from).into(),
+        };
+        upgrade(&sockets, &executable, Duration::from_secs(10)).await.unwrap();
+        // 旧进程中的 socket 保留 FD_CLOEXEC
+        assert!(sockets.iter().all(|(socket, _)| is_cloexec(socket.as_raw_fd())));
+
+        // 旧进程关闭监听 socket 后，排队和新建的连接都由新进程处理
+        drop((listener, sockets));
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert_eq!(response, "new process");
+    }
+
+    /// 由 `test_upgrade` 作为新进程启动，直接运行时什么都不做；
+    /// 由 `test_upgrade_grandchild` 通过 shell 间接启动时父进程不是旧进程，不能接管文件描述符
+    #[tokio::test]
+    async fn test_upgrade_child() {
+        if std::env::var_os(UPGRADE_FDS_ENV).is_none() {
+            return;
+        }
+        if !handed_over_by_parent() {
+            assert!(inherited_listeners().unwrap().is_none());
+            notify_ready();
+            assert!(!READY_NOTIFIED.load(Ordering::SeqCst));
+            return;
+        }
+        let mut listeners = inherited_listeners().unwrap().unwrap();
+        assert_eq!(listeners.len(), 1);
+        let (listener, name) = listeners.remove(0);
+        assert_eq!(name, "main");
+        notify_ready();
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream.write_all(b"new process").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_grandchild() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let sockets = vec![(Socket::from(listener), "main".to_string())];
+        // 继承了环境变量的孙进程忽略交接的文件描述符后 shell 正常退出，否则等待到超时
+        let executable = Executable {
+            path: "/bin/sh".into(),
+            args: vec![
+                "-c".into(),
+                "\"$0\" hot_restart::tests::test_upgrade_child --exact --quiet >/dev/null || sleep 10".into(),
+                std::env::current_exe().unwrap().into(),
+            ],
+        };
+        let err = upgrade(&sockets, &executable, Duration::from_secs(5)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_not_ready() {
+        let sh = |script: &str| Executable {
+            path: "/bin/sh".into(),
+            args: vec!["-c".into(), script.into()],
+        };
+        let err = upgrade(&[], &sh("exit 0"), Duration::from_secs(10)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = upgrade(&[], &sh("sleep 10"), Duration::from_millis(100)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_check_stream_socket() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        check_stream_socket(listener.as_raw_fd(), true).unwrap();
+        let stream = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        check_stream_socket(stream.as_raw_fd(), false).unwrap();
+        assert!(check_stream_socket(stream.as_raw_fd(), true).is_err());
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(check_stream_socket(udp.as_raw_fd(), false).is_err());
+        // 普通文件检查失败后仍然打开
+        let file = std::fs::File::open(std::env::current_exe().unwrap()).unwrap();
+        assert!(check_stream_socket(file.as_raw_fd(), false).is_err());
+        file.metadata().unwrap();
+    }
+
+    #[test]
+    fn test_parse_upgrade_fds() {
+        let fds = parse_upgrade_fds("5:main,7:http,").unwrap();
+        assert_eq!(fds, vec![(5, "main".to_string()), (7, "http".to_string())]);
+        assert_eq!(parse_upgrade_fds("9").unwrap(), vec![(9, String::new())]);
+        assert!(parse_upgrade_fds("").unwrap().is_empty());
+        assert!(parse_upgrade_fds("x:main").is_err());
+        assert!(parse_upgrade_fds("-1:main").is_err());
+    }
+}
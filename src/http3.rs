@@ -0,0 +1,507 @@
+//! # HTTP/3 模块
+//!
+//! 在 HTTPS 监听器的同一 UDP 端口上提供基于 QUIC 的 HTTP/3 服务 (需要 `http3` feature)
+//!
+//! # 主要组件
+//! - `bind`: 为 HTTPS 监听地址绑定 QUIC 端点
+//! - `serve`: QUIC 端点的 accept 循环，与 TCP 监听器共享路由、拦截器、证书刷新和关闭信号
+//! - `alt_svc`: 生成声明 HTTP/3 服务的 `Alt-Svc` 头
+
+use std::{io, net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{Router, body::Body, extract::ConnectInfo};
+use bytes::{Buf, Bytes};
+use futures_util::StreamExt;
+use hyper::header::HeaderValue;
+use log::{debug, info, warn};
+use quinn::crypto::rustls::QuicServerConfig;
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinSet,
+};
+use tokio_rustls::rustls::{ProtocolVersion, ServerConfig, pki_types::CertificateDer};
+use tower::Service;
+
+use crate::{
+    DynError, InterceptorDrop, ListenerRole, ReqInterceptor, ServeContext,
+    connection::{ConnectionGuard, ConnectionInfo, TlsInfo},
+    handle, unwrap_infallible,
+    util::io::{HeaderReadTimeout, PeerAddr},
+};
+
+type H3Connection = h3::server::Connection<h3_quinn::Connection, Bytes>;
+type H3Resolver = h3::server::RequestResolver<h3_quinn::Connection, Bytes>;
+
+/// 优雅关闭时等待客户端关闭连接的最长时间
+///
+/// QUIC 连接关闭会丢弃尚未确认的流数据，所以处理完剩余请求后先等待客户端主动关闭，超时后再由服务端关闭
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 为 HTTPS 监听地址绑定 QUIC 端点
+///
+/// # 参数
+/// - `addrs`: HTTPS TCP 监听器实际绑定的地址，重复的地址只绑定一次
+/// - `config`: TLS 服务器配置
+/// - `idle_timeout`: QUIC 连接空闲超时时间
+///
+/// # 返回
+/// - `Ok(Vec<quinn::Endpoint>)`: QUIC 端点
+/// - `Err(io::Error)`: TLS 配置不支持 QUIC 或绑定失败
+pub(crate) fn bind(addrs: &[SocketAddr], config: &Arc<ServerConfig>, idle_timeout: Duration) -> io::Result<Vec<quinn::Endpoint>> {
+    let mut endpoints: Vec<quinn::Endpoint> = Vec::new();
+    let mut bound = Vec::new();
+    for addr in addrs {
+        if bound.contains(addr) {
+            continue;
+        }
+        let endpoint = quinn::Endpoint::new(
+            quinn::EndpointConfig::default(),
+            Some(server_config(config, idle_timeout)?),
+            udp_socket(*addr)?,
+            Arc::new(quinn::TokioRuntime),
+        )?;
+        endpoints.push(endpoint);
+        bound.push(*addr);
+    }
+    Ok(endpoints)
+}
+
+/// 创建 UDP socket
+///
+/// 绑定到 [::] 时支持 IPv4 + IPv6 双栈；Unix 平台设置 `SO_REUSEPORT`，使热重启的新进程可以绑定同一端口
+fn udp_socket(addr: SocketAddr) -> io::Result<std::net::UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if let SocketAddr::V6(v6) = addr {
+        if v6.ip().is_unspecified() {
+            socket.set_only_v6(false)?;
+        }
+    }
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// 从 TLS 配置创建 QUIC 服务器配置
+///
+/// 复制 TLS 配置并把 ALPN 改为 `h3`，QUIC 要求 TLS 1.3
+fn server_config(config: &Arc<ServerConfig>, idle_timeout: Duration) -> io::Result<quinn::ServerConfig> {
+    let mut config = (**config).clone();
+    config.alpn_protocols = vec![b"h3".to_vec()];
+    let crypto = QuicServerConfig::try_from(config).map_err(io::Error::other)?;
+    let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    let mut transport = quinn::TransportConfig::default();
+    transport.max_idle_timeout(Some(idle_timeout.try_into().map_err(io::Error::other)?));
+    server_config.transport_config(Arc::new(transport));
+    Ok(server_config)
+}
+
+/// 生成声明 HTTP/3 服务的 `Alt-Svc` 头
+///
+/// # 参数
+/// - `port`: HTTP/3 服务的 UDP 端口
+/// - `max_age`: 客户端记住 HTTP/3 服务的时长
+///
+/// # 返回
+/// 例如 `h3=":443"; ma=86400`
+pub(crate) fn alt_svc(port: u16, max_age: Duration) -> HeaderValue {
+    #[allow(clippy::expect_used)]
+    HeaderValue::try_from(format!("h3=\":{port}\"; ma={}", max_age.as_secs())).expect("alt-svc header value should be valid")
+}
+
+/// 运行 QUIC 端点的 accept 循环
+///
+/// 支持 TLS 证书动态更新，收到关闭信号后停止接受新连接，已有连接发送 GOAWAY 后处理完剩余请求再关闭
+///
+/// # 参数
+/// - `ctx`: 连接处理上下文
+/// - `graceful`: 优雅关闭句柄，连接任务结束前优雅关闭会一直等待
+/// - `endpoint`: QUIC 端点
+/// - `alt_svc`: 响应附加的 `Alt-Svc` 头 (可选)
+/// - `config_rx`: TLS 配置更新接收器
+/// - `shutdown_rx`: 关闭信号接收器
+///
+/// # 返回
+/// - `Ok(())`: 收到关闭信号，accept 循环正常退出
+/// - `Err(io::Error)`: 运行过程中出现错误
+pub(crate) async fn serve<I>(
+    ctx: &Arc<ServeContext<I>>, graceful: &hyper_util::server::graceful::GracefulShutdown, endpoint: quinn::Endpoint, alt_svc: Option<HeaderValue>,
+    mut config_rx: broadcast::Receiver<Arc<ServerConfig>>, mut shutdown_rx: broadcast::Receiver<()>,
+) -> io::Result<()>
+where
+    I: ReqInterceptor + Clone + Send + Sync + 'static,
+{
+    let local_addr = endpoint.local_addr().ok();
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                info!("start graceful shutdown!");
+                // 拒绝新连接，已有连接继续处理
+                endpoint.set_server_config(None);
+                break;
+            }
+            message = config_rx.recv() => {
+                match message {
+                    Ok(new_config) => match server_config(&new_config, ctx.settings.idle_timeout()) {
+                        Ok(server_config) => {
+                            endpoint.set_server_config(Some(server_config));
+                            info!("replaced http3 tls config");
+                        }
+                        Err(e) => warn!("create http3 config error: {e}"),
+                    },
+                    Err(RecvError::Closed) => {
+                        warn!("this channel should not be closed!");
+                        break;
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        warn!("lagged {n} messages, this may cause tls config not updated in time");
+                    }
+                }
+            }
+            incoming = endpoint.accept() => {
+                let Some(incoming) = incoming else {
+                    break;
+                };
+                let peer_addr = PeerAddr::Tcp(incoming.remote_address());
+                if !ctx.accept_connection(&peer_addr, local_addr) {
+                    incoming.ignore();
+                    continue;
+                }
+                let accepted_at = std::time::Instant::now();
+                let Some(guard) = ctx.limiter.try_acquire(&peer_addr) else {
+                    ctx.disconnect(ConnectionInfo::new(local_addr, peer_addr), accepted_at, 0, 0);
+                    incoming.refuse();
+                    continue;
+                };
+                let ctx = ctx.clone();
+                let watcher = graceful.watcher();
+                let shutdown_rx = shutdown_rx.resubscribe();
+                let alt_svc = alt_svc.clone();
+                tokio::spawn(async move {
+                    // 持有 watcher 使优雅关闭等待该连接
+                    let _watcher = watcher;
+                    let conn = match tokio::time::timeout(ctx.timeouts.tls_handshake, incoming).await {
+                        Ok(Ok(conn)) => conn,
+                        Ok(Err(e)) => {
+                            debug!("[h3]: handshake {e} from {peer_addr}");
+                            ctx.disconnect(ConnectionInfo::new(local_addr, peer_addr), accepted_at, 0, 0);
+                            return;
+                        }
+                        Err(_) => {
+                            info!("[tls handshake timeout]: not completed in {:?} from {peer_addr}", ctx.timeouts.tls_handshake);
+                            ctx.disconnect(ConnectionInfo::new(local_addr, peer_addr), accepted_at, 0, 0);
+                            return;
+                        }
+                    };
+                    let info = connection_info(&conn, peer_addr.clone(), local_addr);
+                    let serve = serve_connection(conn.clone(), info.clone(), guard, alt_svc, &ctx, shutdown_rx);
+                    match ctx.timeouts.max_lifetime {
+                        Some(lifetime) => {
+                            if tokio::time::timeout(lifetime, serve).await.is_err() {
+                                info!("[max lifetime]: closed after {lifetime:?} from {peer_addr}");
+                            }
+                        }
+                        None => serve.await,
+                    }
+                    let stats = conn.stats();
+                    ctx.disconnect(info, accepted_at, stats.udp_rx.bytes, stats.udp_tx.bytes);
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 在 QUIC 连接上处理 HTTP/3 请求，直到连接关闭
+///
+/// # 参数
+/// - `conn`: QUIC 连接
+/// - `info`: 连接信息
+/// - `_guard`: 连接占用的限额
+/// - `alt_svc`: 响应附加的 `Alt-Svc` 头 (可选)
+/// - `ctx`: 连接处理上下文
+/// - `shutdown_rx`: 关闭信号接收器
+async fn serve_connection<I>(
+    conn: quinn::Connection, info: ConnectionInfo, _guard: ConnectionGuard, alt_svc: Option<HeaderValue>, ctx: &Arc<ServeContext<I>>,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) where
+    I: ReqInterceptor + Clone + Send + Sync + 'static,
+{
+    let peer_addr = info.peer_addr.clone();
+    let mut h3_conn: H3Connection = match h3::server::Connection::new(h3_quinn::Connection::new(conn.clone())).await {
+        Ok(h3_conn) => h3_conn,
+        Err(e) => {
+            warn!("[h3]: {e} from {peer_addr}");
+            return;
+        }
+    };
+    let mut app = ctx.app.clone().into_make_service_with_connect_info::<SocketAddr>();
+    let app = unwrap_infallible(app.call(peer_addr.socket_addr()).await);
+    let mut requests = JoinSet::new();
+    let mut shutting_down = false;
+    loop {
+        if shutting_down && requests.is_empty() {
+            break;
+        }
+        tokio::select! {
+            _ = shutdown_rx.recv(), if !shutting_down => {
+                shutting_down = true;
+                // 发送 GOAWAY，GOAWAY 中的流 ID 需要在最后一个已接受的请求之后，否则客户端会认为该请求未被处理
+                if let Err(e) = h3_conn.shutdown(1).await {
+                    handle_connection_error(&peer_addr, e);
+                    break;
+                }
+            }
+            // 发送 GOAWAY 后继续 accept，h3 会拒绝之后到达的请求
+            resolver = h3_conn.accept() => {
+                match resolver {
+                    Ok(Some(resolver)) => {
+                        let info = info.clone();
+                        let app = app.clone();
+                        let alt_svc = alt_svc.clone();
+                        let ctx = ctx.clone();
+                        requests.spawn(async move {
+                            let peer_addr = info.peer_addr.clone();
+                            if let Err(e) = handle_request(resolver, info, app, alt_svc, &ctx).await {
+                                warn!("[h3]: {e} from {peer_addr}");
+                            }
+                        });
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        handle_connection_error(&peer_addr, e);
+                        break;
+                    }
+                }
+            }
+            Some(_) = requests.join_next(), if !requests.is_empty() => {}
+        }
+    }
+    // 等待已接受的请求处理完毕
+    while requests.join_next().await.is_some() {}
+    if shutting_down {
+        let _ = tokio::time::timeout(DRAIN_TIMEOUT, conn.closed()).await;
+    }
+    debug!("dropped: {peer_addr}");
+}
+
+/// 创建 HTTP/3 连接的连接信息
+///
+/// QUIC 总是使用 TLS 1.3，quinn 不提供协商的密码套件
+///
+/// # 参数
+/// - `conn`: 握手完成的 QUIC 连接
+/// - `peer_addr`: 对端描述
+/// - `local_addr`: QUIC 端点绑定的地址，绑定 `[::]` 时替换为连接实际使用的本地 IP
+fn connection_info(conn: &quinn::Connection, peer_addr: PeerAddr, local_addr: Option<SocketAddr>) -> ConnectionInfo {
+    let local_addr = local_addr.map(|addr| conn.local_ip().map_or(addr, |ip| SocketAddr::new(ip, addr.port())));
+    let handshake = conn
+        .handshake_data()
+        .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok());
+    let peer_certificates = conn
+        .peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+        .map(|certificates| *certificates)
+        .unwrap_or_default();
+    let mut info = ConnectionInfo::new(local_addr, peer_addr);
+    info.tls = Some(Arc::new(TlsInfo {
+        version: Some(ProtocolVersion::TLSv1_3),
+        cipher_suite: None,
+        server_name: handshake.as_ref().and_then(|data| data.server_name.clone()),
+        alpn_protocol: handshake.and_then(|data| data.protocol),
+        peer_certificates,
+    }));
+    info
+}
+
+/// 处理单个 HTTP/3 请求
+///
+/// 把 HTTP/3 请求转换为 axum 请求，交给与 HTTP/1、HTTP/2 相同的处理流程，再把响应写回请求流
+///
+/// 请求头必须在请求头读取超时内到达，超时后放弃该请求流；拦截器丢弃请求时重置该请求流
+async fn handle_request<I>(
+    resolver: H3Resolver, info: ConnectionInfo, app: axum::middleware::AddExtension<Router, ConnectInfo<SocketAddr>>, alt_svc: Option<HeaderValue>,
+    ctx: &ServeContext<I>,
+) -> Result<(), DynError>
+where
+    I: ReqInterceptor + Clone + Send + Sync + 'static,
+{
+    let resolved = match ctx.timeouts.header_read {
+        Some(timeout) => match tokio::time::timeout(timeout, resolver.resolve_request()).await {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                info!("[header read timeout]: {} from {}", HeaderReadTimeout(timeout), info.peer_addr);
+                return Ok(());
+            }
+        },
+        None => resolver.resolve_request().await,
+    };
+    let (request, stream) = resolved?;
+    let (mut send, recv) = stream.split();
+    let body = Body::from_stream(futures_util::stream::unfold(Some(recv), |recv| async move {
+        let mut recv = recv?;
+        match recv.recv_data().await {
+            Ok(Some(mut data)) => Some((Ok(data.copy_to_bytes(data.remaining())), Some(recv))),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), None)),
+        }
+    }));
+    let (method, uri) = (request.method().clone(), request.uri().clone());
+    let mut request = request.map(|_| body);
+    request.extensions_mut().insert(ctx.shutdown.clone());
+    let peer_addr = info.peer_addr.clone();
+    let response = match handle(request, info, ListenerRole::Https, app, ctx.interceptor.clone(), ctx.hsts.clone(), alt_svc).await {
+        Ok(response) => response,
+        Err(e) => {
+            send.stop_stream(h3::error::Code::H3_REQUEST_REJECTED);
+            if InterceptorDrop::is(&e) {
+                if let Some(level) = ctx.drop_log_level.to_level() {
+                    log::log!(level, "[interceptor]: dropped {method} {uri} from {peer_addr}");
+                }
+                return Ok(());
+            }
+            return Err(e.into());
+        }
+    };
+    let (parts, body) = response.into_parts();
+    send.send_response(hyper::Response::from_parts(parts, ())).await?;
+    let mut data = body.into_data_stream();
+    while let Some(chunk) = data.next().await {
+        send.send_data(chunk?).await?;
+    }
+    send.finish().await?;
+    Ok(())
+}
+
+/// 记录 HTTP/3 连接错误，正常关闭、对端关闭和空闲超时只记录 debug 日志
+fn handle_connection_error(peer_addr: &PeerAddr, e: h3::error::ConnectionError) {
+    use h3::error::ConnectionError;
+    if e.is_h3_no_error() || matches!(e, ConnectionError::Remote { .. } | ConnectionError::Timeout { .. }) {
+        debug!("[h3]: {e} from {peer_addr}");
+    } else {
+        warn!("[h3]: {e} from {peer_addr}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use axum::routing::get;
+    use hyper::{StatusCode, header::ALT_SVC};
+
+    use super::*;
+    use crate::{Http3Param, TlsParam};
+
+    type H3Client = (h3::client::Connection<h3_quinn::Connection, Bytes>, h3::client::SendRequest<h3_quinn::OpenStreams, Bytes>);
+
+    /// 生成 localhost 的自签名证书写入 `dir`，返回证书
+    fn write_certificate(dir: &Path) -> CertificateDer<'static> {
+        let rcgen::CertifiedKey { cert, signing_key } = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        std::fs::write(dir.join("cert.pem"), cert.pem()).unwrap();
+        std::fs::write(dir.join("privkey.pem"), signing_key.serialize_pem()).unwrap();
+        cert.der().clone()
+    }
+
+    /// 建立 HTTP/3 连接，只信任 `certificate`
+    async fn connect(addr: SocketAddr, certificate: &CertificateDer<'static>) -> Result<H3Client, DynError> {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(certificate.clone())?;
+        let mut config = rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+        config.alpn_protocols = vec![b"h3".to_vec()];
+        let mut endpoint = quinn::Endpoint::client(([127, 0, 0, 1], 0).into())?;
+        endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(quinn::crypto::rustls::QuicClientConfig::try_from(config)?)));
+        let conn = endpoint.connect(addr, "localhost")?.await?;
+        Ok(h3::client::new(h3_quinn::Connection::new(conn)).await?)
+    }
+
+    /// 发送 GET 请求，返回响应头和响应体
+    async fn request(
+        send_request: &mut h3::client::SendRequest<h3_quinn::OpenStreams, Bytes>, path: &str,
+    ) -> Result<(hyper::Response<()>, String), DynError> {
+        let mut stream = send_request
+            .send_request(hyper::Request::get(format!("https://localhost{path}")).body(())?)
+            .await?;
+        stream.finish().await?;
+        let response = stream.recv_response().await?;
+        let mut body = Vec::new();
+        while let Some(mut chunk) = stream.recv_data().await? {
+            body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+        }
+        Ok((response, String::from_utf8(body)?))
+    }
+
+    #[tokio::test]
+    async fn test_http3() {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let dir = std::env::temp_dir().join(format!("axum-bootstrap-http3-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let old_certificate = write_certificate(&dir);
+        let router = Router::new().route("/", get(|| async { "h3" })).route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                "slow"
+            }),
+        );
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let server = crate::new_server(0, router, shutdown_rx)
+            .with_tls_param(Some(TlsParam {
+                tls: true,
+                cert: dir.join("cert.pem").display().to_string(),
+                key: dir.join("privkey.pem").display().to_string(),
+            }))
+            .with_http3(Some(Http3Param::default()));
+        let (handle, addr) = crate::spawn_loopback(server).await;
+
+        // HTTPS 端口的同一 UDP 端口提供 HTTP/3，响应带有 Alt-Svc
+        let (mut driver, mut send_request) = connect(addr, &old_certificate).await.unwrap();
+        let driver = tokio::spawn(async move { driver.wait_idle().await });
+        let (response, body) = request(&mut send_request, "/").await.unwrap();
+        assert_eq!((response.status(), body.as_str()), (StatusCode::OK, "h3"));
+        assert_eq!(response.headers()[ALT_SVC], alt_svc(addr.port(), Http3Param::default().alt_svc_max_age));
+
+        // 重新加载证书后新连接使用新证书，已有连接不受影响
+        let new_certificate = write_certificate(&dir);
+        assert!(connect(addr, &new_certificate).await.is_err());
+        handle.reload().await.unwrap();
+        let mut refreshed = None;
+        for _ in 0..50 {
+            if let Ok(client) = connect(addr, &new_certificate).await {
+                refreshed = Some(client);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let (mut new_driver, mut new_send_request) = refreshed.expect("http3 tls config should be refreshed");
+        let new_driver = tokio::spawn(async move { new_driver.wait_idle().await });
+        assert_eq!(request(&mut new_send_request, "/").await.unwrap().1, "h3");
+        assert_eq!(request(&mut send_request, "/").await.unwrap().1, "h3");
+
+        // 关闭时发送 GOAWAY，已接受的请求处理完毕，之后的新请求被拒绝
+        let slow = tokio::spawn(async move {
+            let result = request(&mut send_request, "/slow").await.map(|(_, body)| body);
+            (result, send_request)
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.shutdown_with_timeout(Duration::from_secs(5));
+        let (result, mut send_request) = slow.await.unwrap();
+        assert_eq!(result.unwrap(), "slow");
+        let rejected = send_request
+            .send_request(hyper::Request::get("https://localhost/").body(()).unwrap())
+            .await;
+        assert!(matches!(rejected, Err(h3::error::StreamError::RemoteClosing { .. })));
+        drop((send_request, new_send_request));
+        handle.await.unwrap();
+        driver.await.unwrap();
+        new_driver.await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_alt_svc() {
+        assert_eq!(alt_svc(443, Duration::from_secs(86400)), "h3=\":443\"; ma=86400");
+        assert_eq!(alt_svc(8443, Duration::from_millis(1500)), "h3=\":8443\"; ma=1");
+    }
+}
@@ -1,6 +1,15 @@
 use env_logger::Env;
 
 pub fn init(env_cargo_crate_name: &str) {
+    init_with_filter(env_cargo_crate_name, None)
+}
+
+/// 使用指定的过滤规则初始化日志
+///
+/// # 参数
+/// - `env_cargo_crate_name`: 当前 crate 名称，用于默认过滤规则
+/// - `filter`: 过滤规则，语法与 `RUST_LOG` 相同，优先于 `RUST_LOG`；为 None 时与 [`init`] 相同
+pub fn init_with_filter(env_cargo_crate_name: &str, filter: Option<&str>) {
     use chrono::Local;
     use env_logger;
     use std::io::Write;
@@ -10,7 +19,15 @@ pub fn init(env_cargo_crate_name: &str) {
     } else {
         format!("error,{env_cargo_crate_name}=info")
     };
-    let _ = env_logger::Builder::from_env(Env::default().default_filter_or(&default_filter))
+    let mut builder = match filter {
+        Some(filter) => {
+            let mut builder = env_logger::Builder::new();
+            builder.parse_filters(filter);
+            builder
+        }
+        None => env_logger::Builder::from_env(Env::default().default_filter_or(&default_filter)),
+    };
+    let _ = builder
         .format(|buf, record| {
             writeln!(
                 buf,
@@ -1,26 +1,75 @@
+use std::sync::OnceLock;
+
 use time::macros::format_description;
 use time::UtcOffset;
+use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::fmt::time::OffsetTime;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
 use crate::DynError;
+
+/// 替换过滤规则的句柄，初始化日志后设置
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
 pub fn init(env_cargo_crate_name: &str) -> Result<(), DynError> {
+    init_with_filter(env_cargo_crate_name, None)
+}
+
+/// 使用指定的过滤规则初始化日志
+///
+/// # 参数
+/// - `env_cargo_crate_name`: 当前 crate 名称，用于默认过滤规则
+/// - `filter`: 过滤规则，语法与 `RUST_LOG` 相同，优先于 `RUST_LOG`；为 None 时与 [`init`] 相同
+pub fn init_with_filter(env_cargo_crate_name: &str, filter: Option<&str>) -> Result<(), DynError> {
     let offset = UtcOffset::current_local_offset()?;
     let timer = OffsetTime::new(offset, format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"));
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+    let env_filter = match filter {
+        Some(filter) => EnvFilter::try_new(filter)?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| {
             if cfg!(debug_assertions) {
                 format!("info,{env_cargo_crate_name}=debug,tower_http=error").into()
             } else {
                 format!("error,{env_cargo_crate_name}=info,tower_http=error").into()
             }
-        }))
+        }),
+    };
+    let (env_filter, handle) = reload::Layer::new(env_filter);
+    tracing_subscriber::registry()
+        .with(env_filter)
         .with(
             tracing_subscriber::fmt::layer().with_thread_ids(true).with_ansi(true).with_timer(timer),
             // .with_timer(tracing_subscriber::fmt::time::LocalTime::rfc_3339()), // 需要tracing-subscriber的local-time feature
             // .with_timer(tracing_subscriber::fmt::time::OffsetTime::local_rfc_3339().expect("could not get local offset!")), // 这个需要放在tokio runtime fork thread之前
         )
         .init();
+    let _ = FILTER_HANDLE.set(handle);
+    Ok(())
+}
+
+/// 替换日志过滤规则
+///
+/// 只对通过本模块初始化的日志生效，同时调整 `log` crate 的最大日志级别
+///
+/// # 参数
+/// - `filter`: 过滤规则，语法与 `RUST_LOG` 相同
+///
+/// # 返回
+/// - `Ok(())`: 过滤规则已替换
+/// - `Err(DynError)`: 日志没有通过本模块初始化或过滤规则无效，原来的规则保持不变
+pub fn set_filter(filter: &str) -> Result<(), DynError> {
+    let handle = FILTER_HANDLE.get().ok_or("log is not initialized by init_log::tracing")?;
+    let filter = EnvFilter::try_new(filter)?;
+    let max_level = match filter.max_level_hint() {
+        Some(LevelFilter::OFF) => log::LevelFilter::Off,
+        Some(LevelFilter::ERROR) => log::LevelFilter::Error,
+        Some(LevelFilter::WARN) => log::LevelFilter::Warn,
+        Some(LevelFilter::INFO) => log::LevelFilter::Info,
+        Some(LevelFilter::DEBUG) => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    handle.reload(filter)?;
+    log::set_max_level(max_level);
     Ok(())
 }
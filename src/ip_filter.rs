@@ -0,0 +1,476 @@
+//! # IP 过滤模块
+//!
+//! 按 IPv4/IPv6 CIDR 允许列表和拒绝列表过滤客户端
+//!
+//! # 主要组件
+//! - `IpNet`: CIDR 网段，IPv4 映射的 IPv6 地址按 IPv4 处理
+//! - `IpFilterRules`: 允许列表和拒绝列表，可以从规则文件解析
+//! - `IpFilter`: 可热重载的 IP 过滤器，既是请求拦截器 (`ReqInterceptor`)，也是连接钩子 (`ConnectionHook`)。
+//!   从文件创建的过滤器还可以作为重新加载钩子 (`ReloadHook`)，收到 `SIGHUP` 时重新读取规则文件
+//! - `RejectAction`: 拒绝请求的方式 (403 响应 / 丢弃请求)
+//!
+//! # 规则文件格式
+//! 每行一条规则，`allow` 或 `deny` 后跟 CIDR 网段或单个 IP 地址，`#` 之后的内容为注释:
+//!
+//! ```text
+//! # 内网
+//! allow 10.0.0.0/8
+//! allow 2001:db8::/32
+//! deny 10.1.2.3
+//! ```
+
+use std::{
+    fmt, io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock, Weak},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    response::IntoResponse,
+};
+use hyper::StatusCode;
+use log::{debug, info, warn};
+
+use crate::{
+    InterceptResult, ReqInterceptor,
+    connection::{ConnectAction, ConnectionHook},
+    reload::{ReloadHook, RuntimeSettings},
+    util::io::PeerAddr,
+};
+
+/// CIDR 网段
+///
+/// 解析时 IPv4 映射的 IPv6 网段 (如 `::ffff:10.0.0.0/104`) 会转换为 IPv4 网段，
+/// 匹配时地址先经过 `to_canonical()` 转换，与 [`SocketAddrFormat`](crate::util::format::SocketAddrFormat) 的输出一致
+///
+/// # 示例
+///
+/// ```
+/// use axum_bootstrap::ip_filter::IpNet;
+///
+/// let net: IpNet = "10.0.0.0/8".parse().unwrap();
+/// assert!(net.contains("10.1.2.3".parse().unwrap()));
+/// assert!(net.contains("::ffff:10.1.2.3".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    /// 创建 CIDR 网段
+    ///
+    /// # 参数
+    /// - `addr`: 网段中的任意地址，主机位会被清零
+    /// - `prefix_len`: 前缀长度，IPv4 不超过 32，IPv6 不超过 128
+    ///
+    /// # 返回
+    /// - `Some(IpNet)`: CIDR 网段
+    /// - `None`: 前缀长度超出范围
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<Self> {
+        let (addr, prefix_len) = match addr {
+            IpAddr::V6(v6) if prefix_len >= 96 => match v6.to_ipv4_mapped() {
+                Some(v4) => (IpAddr::V4(v4), prefix_len - 96),
+                None => (addr, prefix_len),
+            },
+            _ => (addr, prefix_len),
+        };
+        let addr = match addr {
+            IpAddr::V4(v4) if prefix_len <= 32 => IpAddr::V4((u32::from(v4) & mask(prefix_len, 32) as u32).into()),
+            IpAddr::V6(v6) if prefix_len <= 128 => IpAddr::V6((u128::from(v6) & mask(prefix_len, 128)).into()),
+            _ => return None,
+        };
+        Some(Self { addr, prefix_len })
+    }
+
+    /// 网段的网络地址
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// 前缀长度
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// 网段是否包含指定地址
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => u32::from(ip) & mask(self.prefix_len, 32) as u32 == u32::from(net),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => u128::from(ip) & mask(self.prefix_len, 128) == u128::from(net),
+            _ => false,
+        }
+    }
+}
+
+/// 生成指定位宽的前缀掩码
+fn mask(prefix_len: u8, bits: u32) -> u128 {
+    match prefix_len {
+        0 => 0,
+        len => (u128::MAX << (128 - u32::from(len))) >> (128 - bits),
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("invalid cidr: {s}"));
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, prefix_len)) => (addr, Some(prefix_len.parse::<u8>().map_err(|_| invalid())?)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+        let prefix_len = prefix_len.unwrap_or(if addr.is_ipv4() { 32 } else { 128 });
+        Self::new(addr, prefix_len).ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// IP 过滤规则
+///
+/// # 字段
+/// - `allow`: 允许列表，为空时允许所有不在拒绝列表中的地址
+/// - `deny`: 拒绝列表，优先于允许列表
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpFilterRules {
+    pub allow: Vec<IpNet>,
+    pub deny: Vec<IpNet>,
+}
+
+impl IpFilterRules {
+    /// 地址是否被允许
+    ///
+    /// 在拒绝列表中的地址总是被拒绝；允许列表不为空时，只允许在允许列表中的地址
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        if self.deny.iter().any(|net| net.contains(ip)) {
+            return false;
+        }
+        self.allow.is_empty() || self.allow.iter().any(|net| net.contains(ip))
+    }
+
+    /// 读取规则文件
+    ///
+    /// # 参数
+    /// - `path`: 规则文件路径，格式见模块文档
+    ///
+    /// # 返回
+    /// - `Ok(IpFilterRules)`: 解析出的规则
+    /// - `Err(io::Error)`: 读取失败或规则格式错误
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e: io::Error| io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+    }
+}
+
+impl FromStr for IpFilterRules {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = Self::default();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {msg}", index + 1));
+            let (list, net) = match line.split_once(char::is_whitespace) {
+                Some(("allow", net)) => (&mut rules.allow, net),
+                Some(("deny", net)) => (&mut rules.deny, net),
+                _ => return Err(invalid(format!("expect `allow <cidr>` or `deny <cidr>`, got `{line}`"))),
+            };
+            list.push(net.trim().parse().map_err(|e: io::Error| invalid(e.to_string()))?);
+        }
+        Ok(rules)
+    }
+}
+
+/// 拒绝请求的方式
+///
+/// 只对请求拦截器生效，连接钩子总是丢弃连接
+///
+/// # 变体
+/// - `Forbidden`: 返回 403 响应
+/// - `Drop`: 返回 [`InterceptResult::Drop`]，不写出任何响应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RejectAction {
+    #[default]
+    Forbidden,
+    Drop,
+}
+
+/// IP 过滤器
+///
+/// 可以克隆，所有克隆共享同一份规则。作为请求拦截器时按 [`RejectAction`] 拒绝请求；
+/// 作为连接钩子时在 TLS 握手之前丢弃连接，成本更低。启用 PROXY protocol 时按真实客户端地址过滤，
+/// Unix domain socket 连接总是被允许
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use axum::Router;
+/// use axum_bootstrap::{generate_shutdown_receiver, ip_filter::IpFilter, new_server};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let filter = IpFilter::from_file("ip_rules.txt").unwrap();
+///     // 规则文件修改后自动重新加载
+///     filter.watch(Duration::from_secs(10));
+///     let server = new_server(8080, Router::new(), generate_shutdown_receiver())
+///         .with_connection_hook(filter.clone())
+///         .with_interceptor(filter);
+///     server.run().await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct IpFilter {
+    inner: Arc<IpFilterInner>,
+    action: RejectAction,
+}
+
+/// IP 过滤器的共享状态
+#[derive(Debug)]
+struct IpFilterInner {
+    rules: RwLock<Arc<IpFilterRules>>,
+    path: Option<PathBuf>,
+}
+
+impl IpFilter {
+    /// 使用给定的规则创建过滤器
+    pub fn new(rules: IpFilterRules) -> Self {
+        Self::with_path(rules, None)
+    }
+
+    /// 从规则文件创建过滤器，之后可以通过 [`IpFilter::reload`] 或 [`IpFilter::watch`] 重新加载
+    ///
+    /// # 参数
+    /// - `path`: 规则文件路径
+    ///
+    /// # 返回
+    /// - `Ok(IpFilter)`: IP 过滤器
+    /// - `Err(io::Error)`: 读取失败或规则格式错误
+    pub fn from_file(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let rules = IpFilterRules::from_file(&path)?;
+        Ok(Self::with_path(rules, Some(path)))
+    }
+
+    fn with_path(rules: IpFilterRules, path: Option<PathBuf>) -> Self {
+        Self {
+            inner: Arc::new(IpFilterInner {
+                rules: RwLock::new(Arc::new(rules)),
+                path,
+            }),
+            action: RejectAction::default(),
+        }
+    }
+
+    /// 设置拒绝请求的方式
+    ///
+    /// # 参数
+    /// - `action`: 拒绝请求的方式，默认为 [`RejectAction::Forbidden`]
+    pub fn with_reject_action(mut self, action: RejectAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// 当前的规则
+    pub fn rules(&self) -> Arc<IpFilterRules> {
+        self.inner.rules.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    /// 替换规则，所有克隆立即生效
+    pub fn set_rules(&self, rules: IpFilterRules) {
+        self.inner.set_rules(rules);
+    }
+
+    /// 重新读取规则文件
+    ///
+    /// 读取或解析失败时保留原来的规则
+    ///
+    /// # 返回
+    /// - `Ok(())`: 规则已更新
+    /// - `Err(io::Error)`: 过滤器不是从文件创建的、读取失败或规则格式错误
+    pub fn reload(&self) -> io::Result<()> {
+        self.inner.reload()
+    }
+
+    /// 启动后台任务，按间隔检查规则文件的修改时间，修改后重新加载
+    ///
+    /// 所有克隆被 drop 之后任务自动退出
+    ///
+    /// # 参数
+    /// - `interval`: 检查间隔
+    pub fn watch(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let inner: Weak<IpFilterInner> = Arc::downgrade(&self.inner);
+        let mut modified = self.inner.modified();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(inner) = inner.upgrade() else {
+                    break;
+                };
+                let current = inner.modified();
+                if current == modified {
+                    continue;
+                }
+                modified = current;
+                if let Err(e) = inner.reload() {
+                    warn!("reload ip filter error: {e}");
+                }
+            }
+        })
+    }
+
+    /// 地址是否被允许
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        self.rules().is_allowed(ip)
+    }
+}
+
+impl IpFilterInner {
+    fn set_rules(&self, rules: IpFilterRules) {
+        *self.rules.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(rules);
+    }
+
+    fn reload(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "ip filter is not created from a file"));
+        };
+        let rules = IpFilterRules::from_file(path)?;
+        info!("reload ip filter from {}: {} allow, {} deny", path.display(), rules.allow.len(), rules.deny.len());
+        self.set_rules(rules);
+        Ok(())
+    }
+
+    /// 规则文件的修改时间
+    fn modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(self.path.as_ref()?).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+impl ReqInterceptor for IpFilter {
+    type Error = StatusCode;
+
+    async fn intercept(&self, req: Request, ip: SocketAddr) -> InterceptResult<Self::Error> {
+        if let Some(ConnectInfo(PeerAddr::Unix(_))) = req.extensions().get::<ConnectInfo<PeerAddr>>() {
+            return InterceptResult::Continue(req);
+        }
+        if self.is_allowed(ip.ip()) {
+            return InterceptResult::Continue(req);
+        }
+        match self.action {
+            RejectAction::Forbidden => {
+                debug!("[ip filter]: forbidden {} {} from {ip}", req.method(), req.uri());
+                InterceptResult::Return(StatusCode::FORBIDDEN.into_response())
+            }
+            RejectAction::Drop => InterceptResult::Drop,
+        }
+    }
+}
+
+impl ConnectionHook for IpFilter {
+    fn on_connect(&self, peer_addr: &PeerAddr, _local_addr: Option<SocketAddr>) -> ConnectAction {
+        match peer_addr {
+            PeerAddr::Tcp(addr) if !self.is_allowed(addr.ip()) => ConnectAction::Drop,
+            _ => ConnectAction::Accept,
+        }
+    }
+}
+
+impl ReloadHook for IpFilter {
+    fn reload(&self, _settings: &RuntimeSettings) -> io::Result<()> {
+        IpFilter::reload(self)
+    }
+
+    fn name(&self) -> &str {
+        "ip filter"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_net() {
+        let net: IpNet = "10.1.2.3/8".parse().unwrap();
+        assert_eq!(net.to_string(), "10.0.0.0/8");
+        assert!(net.contains("10.255.0.1".parse().unwrap()));
+        assert!(net.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!net.contains("11.0.0.1".parse().unwrap()));
+
+        let mapped: IpNet = "::ffff:192.168.0.0/112".parse().unwrap();
+        assert_eq!(mapped.to_string(), "192.168.0.0/16");
+        assert!(mapped.contains("192.168.1.1".parse().unwrap()));
+
+        let v6: IpNet = "2001:db8::/32".parse().unwrap();
+        assert!(v6.contains("2001:db8:1::1".parse().unwrap()));
+        assert!(!v6.contains("2001:db9::1".parse().unwrap()));
+
+        let host: IpNet = "::1".parse().unwrap();
+        assert_eq!(host.prefix_len(), 128);
+        assert!("0.0.0.0/0".parse::<IpNet>().unwrap().contains("1.2.3.4".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("10.0.0/8".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules: IpFilterRules = "# comment\nallow 10.0.0.0/8\n\ndeny 10.0.0.1 # blocked\nallow ::1\n".parse().unwrap();
+        assert_eq!(rules.allow.len(), 2);
+        assert!(rules.is_allowed("10.0.0.2".parse().unwrap()));
+        assert!(!rules.is_allowed("10.0.0.1".parse().unwrap()));
+        assert!(!rules.is_allowed("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!rules.is_allowed("192.168.0.1".parse().unwrap()));
+        assert!(rules.is_allowed("::1".parse().unwrap()));
+
+        let deny_only: IpFilterRules = "deny 192.168.0.0/16".parse().unwrap();
+        assert!(deny_only.is_allowed("10.0.0.1".parse().unwrap()));
+        assert!(!deny_only.is_allowed("192.168.3.4".parse().unwrap()));
+
+        let err = "allow 10.0.0.0/8\npermit 1.2.3.4".parse::<IpFilterRules>().unwrap_err();
+        assert!(err.to_string().starts_with("line 2:"));
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        let path = std::env::temp_dir().join(format!("axum-bootstrap-ip-filter-{}.txt", std::process::id()));
+        std::fs::write(&path, "deny 127.0.0.1\n").unwrap();
+        let filter = IpFilter::from_file(&path).unwrap();
+        let ip = SocketAddr::from(([127, 0, 0, 1], 1234));
+        let request = || Request::new(axum::body::Body::empty());
+        let InterceptResult::Return(response) = filter.intercept(request(), ip).await else {
+            panic!("expect forbidden");
+        };
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(filter.on_connect(&PeerAddr::Tcp(ip), None), ConnectAction::Drop);
+
+        let dropping = filter.clone().with_reject_action(RejectAction::Drop);
+        assert!(matches!(dropping.intercept(request(), ip).await, InterceptResult::Drop));
+
+        // 格式错误时保留原来的规则
+        std::fs::write(&path, "deny 127.0.0.1/40\n").unwrap();
+        assert!(filter.reload().is_err());
+        assert!(!dropping.is_allowed(ip.ip()));
+
+        std::fs::write(&path, "deny 10.0.0.0/8\n").unwrap();
+        filter.reload().unwrap();
+        assert!(matches!(dropping.intercept(request(), ip).await, InterceptResult::Continue(_)));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
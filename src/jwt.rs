@@ -244,6 +244,13 @@ impl<T> Claims<T> {
     }
 }
 
+/// 从请求头的 Cookie 中获取 JWT token
+pub(crate) fn token_from_headers(headers: &axum::http::HeaderMap) -> Option<String> {
+    CookieJar::from_headers(headers)
+        .get(AXUM_BOOTSTRAP_TOKEN)
+        .map(|cookie| cookie.value().to_string())
+}
+
 /// JWT 认证中间件
 ///
 /// 从 Cookie 中提取并验证 JWT token，将 Claims 存入 request extensions
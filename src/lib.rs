@@ -6,7 +6,18 @@
 //! - 请求拦截机制
 //! - 优雅关闭 (Graceful Shutdown)
 //! - IPv4/IPv6 双栈支持
-//! - 连接超时控制
+//! - 同时监听多个地址
+//! - `SO_REUSEPORT` 多 acceptor 模式 (仅 Unix 平台)
+//! - PROXY protocol v1/v2，获取负载均衡器之后的真实客户端地址
+//! - Unix domain socket 监听 (仅 Unix 平台)
+//! - 同时监听 HTTP 和 HTTPS，可自动重定向到 HTTPS
+//! - HTTP/3 (QUIC，需要 `http3` feature)
+//! - systemd socket activation (仅 Unix 平台)
+//! - 收到 SIGUSR2 时热重启，不中断监听 (仅 Unix 平台)
+//! - 全局和单 IP 连接数限制
+//! - 按 CIDR 允许/拒绝列表过滤客户端 IP，规则可热重载
+//! - 按客户端 IP、请求头、JWT 用户名或路由限流
+//! - 连接超时控制 (空闲、TLS 握手、请求头读取、最长存活时间)
 //! - TLS 证书动态更新
 //!
 //! # 示例
@@ -24,46 +35,86 @@
 //! }
 //! ```
 
-use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
 
+/// 声明式配置模块 (需要启用 config feature)
+#[cfg(feature = "config")]
+pub mod config;
+/// 连接管理模块
+pub mod connection;
 /// 错误处理模块
 pub mod error;
+/// 正向代理模块
+pub mod forward_proxy;
+/// 热重启模块
+#[cfg(unix)]
+mod hot_restart;
+#[cfg(feature = "http3")]
+mod http3;
 /// 日志初始化模块
 pub mod init_log;
+/// IP 过滤模块
+pub mod ip_filter;
 /// JWT 认证模块 (需要启用 jwt feature)
 #[cfg(feature = "jwt")]
 pub mod jwt;
+/// 限流模块
+pub mod rate_limit;
+/// 重新加载模块
+pub mod reload;
+/// 反向代理模块
+pub mod reverse_proxy;
 /// 工具函数模块
 pub mod util;
+/// 虚拟主机模块
+pub mod vhost;
 
 /// 动态错误类型别名
 type DynError = Box<dyn std::error::Error + Send + Sync>;
 
+use crate::connection::{
+    AcceptBackoff, ConnectAction, ConnectionGuard, ConnectionHook, ConnectionInfo, ConnectionLimitParam, ConnectionLimiter, ConnectionStats,
+    DisconnectInfo, ShutdownSignal, TlsInfo,
+};
+use crate::reload::{ReloadHook, Reloader, RuntimeSettings, TlsReload};
 use crate::util::{
-    io::{self, create_dual_stack_listener},
-    tls::{TlsAcceptor, tls_config},
+    io::{self, Listener, PeerAddr, Stream, create_listener_with_backlog},
+    proxy_protocol::{ProxyProtocolMode, Rewind, read_proxy_header},
+    tls::{TlsAcceptor, tls_config, tls_config_with_resolver},
 };
 
 use axum::{
     Router,
-    extract::Request,
+    extract::{ConnectInfo, Request},
     response::{IntoResponse, Response},
 };
 
-use hyper::body::Incoming;
-use hyper_util::rt::TokioExecutor;
-use log::{info, warn};
-use tokio::{
-    sync::broadcast::{self, Receiver, Sender, error::RecvError},
-    time,
+use futures_util::future::Either;
+use hyper::header::{self, HeaderValue};
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use log::{error, info, warn};
+use tokio::sync::{
+    broadcast::{self, Receiver, Sender, error::RecvError},
+    watch,
 };
 use tokio_rustls::rustls::ServerConfig;
 use tower::{Service, ServiceExt};
-use util::format::SocketAddrFormat;
 
 /// TLS 配置刷新间隔 (24小时)
 const REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
 
+/// systemd socket activation 中明文 HTTP socket 的名称 (`FileDescriptorName=http`)
+#[cfg(unix)]
+const SYSTEMD_HTTP_FD_NAME: &str = "http";
+
+/// 热重启时交接给新进程的明文 HTTP 监听器名称
+#[cfg(unix)]
+const HOT_RESTART_HTTP_NAME: &str = "http";
+
+/// 热重启时交接给新进程的其它监听器名称
+#[cfg(unix)]
+const HOT_RESTART_MAIN_NAME: &str = "main";
+
 /// 优雅关闭等待超时时间 (10秒)
 const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
@@ -73,18 +124,60 @@ const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 /// - `I`: 请求拦截器类型，必须实现 `ReqInterceptor` trait
 ///
 /// # 字段
-/// - `port`: 监听端口
+/// - `addrs`: 监听地址列表，每个地址一个 accept 循环，共享路由、拦截器和关闭信号
+/// - `unix_sockets`: Unix domain socket 监听列表 (仅 Unix 平台)
+/// - `systemd_socket_activation`: 是否使用 systemd socket activation 传递的监听 socket (仅 Unix 平台)
+/// - `hot_restart`: 热重启参数 (可选，仅 Unix 平台)
+/// - `backlog`: TCP 监听队列大小
+/// - `reuse_port_acceptors`: 每个 TCP 监听地址的 `SO_REUSEPORT` 监听器数量，为 1 时不启用 (仅 Unix 平台)
+/// - `proxy_protocol`: 按 TCP 监听地址配置的 PROXY protocol 模式
+/// - `connection_limit`: 连接数限制参数 (可选)
+/// - `connection_stats`: 连接统计
+/// - `connection_hook`: 连接级别的钩子 (可选)
+/// - `reload_hooks`: 重新加载钩子
+/// - `sighup_reload`: 收到 `SIGHUP` 时是否重新加载 (仅 Unix 平台)
 /// - `tls_param`: TLS 配置参数 (可选)
+/// - `http_param`: 启用 TLS 时额外的明文 HTTP 监听参数 (可选)
+/// - `hsts`: HTTPS 响应的 HSTS 参数 (可选)
+/// - `protocol`: HTTP 协议参数
+/// - `http3`: HTTP/3 参数 (可选，需要 `http3` feature)
 /// - `router`: Axum 路由
+/// - `sni_certificates`: 虚拟主机的独立证书，按 SNI 选择
 /// - `interceptor`: 请求拦截器实例 (可选)
+/// - `drop_log_level`: 拦截器丢弃请求时的日志级别
 /// - `idle_timeout`: 连接空闲超时时间
+/// - `timeouts`: TLS 握手、请求头读取和连接存活时间的超时参数
 /// - `shutdown_rx`: 关闭信号接收器
 pub struct Server<I: ReqInterceptor = DummyInterceptor> {
-    pub port: u16,
+    pub addrs: Vec<SocketAddr>,
+    #[cfg(unix)]
+    pub unix_sockets: Vec<UnixSocketParam>,
+    #[cfg(unix)]
+    pub systemd_socket_activation: bool,
+    #[cfg(unix)]
+    pub hot_restart: Option<HotRestartParam>,
+    pub backlog: u32,
+    #[cfg(unix)]
+    pub reuse_port_acceptors: usize,
+    pub proxy_protocol: HashMap<SocketAddr, ProxyProtocolMode>,
+    pub connection_limit: Option<ConnectionLimitParam>,
+    connection_stats: ConnectionStats,
+    pub connection_hook: Option<Arc<dyn ConnectionHook>>,
+    pub reload_hooks: Vec<Arc<dyn ReloadHook>>,
+    #[cfg(unix)]
+    pub sighup_reload: bool,
     pub tls_param: Option<TlsParam>,
+    pub http_param: Option<HttpParam>,
+    pub hsts: Option<HstsParam>,
+    pub protocol: ProtocolParam,
+    #[cfg(feature = "http3")]
+    pub http3: Option<Http3Param>,
     router: Router,
+    sni_certificates: Vec<(String, TlsParam)>,
     pub interceptor: Option<I>,
+    pub drop_log_level: log::LevelFilter,
     pub idle_timeout: Duration,
+    pub timeouts: TimeoutParam,
     shutdown_rx: broadcast::Receiver<()>,
 }
 
@@ -101,22 +194,443 @@ pub struct TlsParam {
     pub key: String,
 }
 
+/// Unix domain socket 监听参数
+///
+/// # 字段
+/// - `path`: socket 文件路径，启动时会清理上次遗留的 socket 文件，关闭时删除
+/// - `mode`: socket 文件权限 (例如 `0o660`)，为 None 时保持 umask 决定的默认权限
+/// - `proxy_protocol`: PROXY protocol 模式，为 None 时不解析
+#[cfg(unix)]
+#[derive(Debug, Clone)]
+pub struct UnixSocketParam {
+    pub path: std::path::PathBuf,
+    pub mode: Option<u32>,
+    pub proxy_protocol: Option<ProxyProtocolMode>,
+}
+
+/// 明文 HTTP 监听参数
+///
+/// 启用 TLS 时，`Server.addrs` 监听 HTTPS，这里配置额外监听的明文 HTTP 地址，
+/// 例如同时监听 80 端口 (HTTP) 和 443 端口 (HTTPS)
+///
+/// # 字段
+/// - `addrs`: 明文 HTTP 监听地址列表
+/// - `redirect`: 重定向参数，为 Some 时所有请求重定向到 HTTPS；为 None 时与 HTTPS 一样使用路由处理请求
+#[derive(Debug, Clone)]
+pub struct HttpParam {
+    pub addrs: Vec<SocketAddr>,
+    pub redirect: Option<HttpRedirectParam>,
+}
+
+/// 重定向到 HTTPS 的参数
+///
+/// # 字段
+/// - `status`: 重定向状态码，默认 308 (保留请求方法)，也可以使用 301
+/// - `https_port`: `Location` 中的 HTTPS 端口，为 None 或 443 时不携带端口
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpRedirectParam {
+    pub status: hyper::StatusCode,
+    pub https_port: Option<u16>,
+}
+
+impl Default for HttpRedirectParam {
+    fn default() -> Self {
+        Self {
+            status: hyper::StatusCode::PERMANENT_REDIRECT,
+            https_port: None,
+        }
+    }
+}
+
+/// HSTS (`Strict-Transport-Security`) 参数
+///
+/// 配置后所有 HTTPS 响应都会附加 `Strict-Transport-Security` 头 (响应中已有该头时不覆盖)
+///
+/// # 字段
+/// - `max_age`: 浏览器记住仅使用 HTTPS 的时长
+/// - `include_subdomains`: 是否对子域名生效
+/// - `preload`: 是否声明加入浏览器 preload 列表
+#[derive(Debug, Clone)]
+pub struct HstsParam {
+    pub max_age: Duration,
+    pub include_subdomains: bool,
+    pub preload: bool,
+}
+
+impl HstsParam {
+    /// 生成 `Strict-Transport-Security` 头的值
+    ///
+    /// # 返回
+    /// 例如 `max-age=31536000; includeSubDomains`
+    pub fn header_value(&self) -> HeaderValue {
+        let mut value = format!("max-age={}", self.max_age.as_secs());
+        if self.include_subdomains {
+            value.push_str("; includeSubDomains");
+        }
+        if self.preload {
+            value.push_str("; preload");
+        }
+        #[allow(clippy::expect_used)]
+        HeaderValue::try_from(value).expect("hsts header value should be valid")
+    }
+}
+
+/// 连接超时参数
+///
+/// 与空闲超时 (`idle_timeout`) 分开计算，防止客户端缓慢发送数据 (slowloris) 长期占用连接。
+/// 空闲超时在每次读写后重置，只限制两次 IO 之间的间隔，以下期限不会因为收到数据而延长
+///
+/// # 字段
+/// - `proxy_header`: 启用 PROXY protocol 的监听器上，PROXY protocol 头必须在 accept 之后此时间内到达。
+///   负载均衡器建立连接后立即发送该头，所以默认只等待 5 秒，也不受运行中修改的空闲超时影响
+/// - `tls_handshake`: TLS 握手必须在此时间内完成，HTTP/3 为 QUIC 握手
+/// - `header_read`: 完整的请求头必须在此时间内到达，为 None 时不限制，只对 HTTP/1 和 HTTP/3 生效。
+///   HTTP/1 使用 hyper 的 `header_read_timeout`，从开始等待请求头时计时，包括 keep-alive 连接等待下一个请求的时间，
+///   因此也限制了 keep-alive 连接的空闲时间；读取请求体和写出响应的时间不计入。HTTP/3 从请求流打开时计时
+/// - `max_lifetime`: 连接从开始处理 HTTP 起的最长存活时间，到期后直接关闭连接 (包括正在处理的请求)，为 None 时不限制
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use axum::Router;
+/// use axum_bootstrap::{TimeoutParam, generate_shutdown_receiver, new_server};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let router = Router::new();
+///     let server = new_server(8080, router, generate_shutdown_receiver())
+///         .with_timeout(Duration::from_secs(300))
+///         .with_timeouts(TimeoutParam {
+///             header_read: Some(Duration::from_secs(10)),
+///             max_lifetime: Some(Duration::from_secs(3600)),
+///             ..Default::default()
+///         });
+///     server.run().await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TimeoutParam {
+    pub proxy_header: Duration,
+    pub tls_handshake: Duration,
+    pub header_read: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+}
+
+impl Default for TimeoutParam {
+    fn default() -> Self {
+        Self {
+            proxy_header: Duration::from_secs(5),
+            tls_handshake: Duration::from_secs(10),
+            header_read: Some(Duration::from_secs(30)),
+            max_lifetime: None,
+        }
+    }
+}
+
+/// HTTP 协议参数
+///
+/// 应用到每个连接使用的 hyper 连接构建器，默认值与 hyper 的默认配置一致
+///
+/// # 字段
+/// - `versions`: 允许的 HTTP 版本
+/// - `http1`: HTTP/1 参数
+/// - `http2`: HTTP/2 参数
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use axum::Router;
+/// use axum_bootstrap::{Http2Param, HttpVersions, ProtocolParam, generate_shutdown_receiver, new_server};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let server = new_server(8080, Router::new(), generate_shutdown_receiver()).with_protocol(ProtocolParam {
+///         versions: HttpVersions::Http2Only,
+///         http2: Http2Param {
+///             max_concurrent_streams: Some(100),
+///             keep_alive_interval: Some(Duration::from_secs(30)),
+///             ..Default::default()
+///         },
+///         ..Default::default()
+///     });
+///     server.run().await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProtocolParam {
+    pub versions: HttpVersions,
+    pub http1: Http1Param,
+    pub http2: Http2Param,
+}
+
+/// 允许的 HTTP 版本
+///
+/// 启用 TLS 时同时决定 ALPN 中声明的协议
+///
+/// # 变体
+/// - `Auto`: 同时支持 HTTP/1 和 HTTP/2，明文连接根据 HTTP/2 连接前言自动识别
+/// - `Http1Only`: 只支持 HTTP/1
+/// - `Http2Only`: 只支持 HTTP/2，明文连接即 h2c prior knowledge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpVersions {
+    #[default]
+    Auto,
+    Http1Only,
+    Http2Only,
+}
+
+/// HTTP/1 参数
+///
+/// # 字段
+/// - `keep_alive`: 是否启用 keep-alive，默认启用
+/// - `half_close`: 是否支持半关闭，即客户端关闭写端后仍然发送响应，默认不支持
+/// - `max_headers`: 请求头的最大数量，为 None 时使用 hyper 默认值 (100)
+/// - `max_header_size`: 读缓冲区的最大字节数，即请求行和请求头的最大长度，不能小于 8192，为 None 时使用 hyper 默认值 (约 400KB)
+#[derive(Debug, Clone, Copy)]
+pub struct Http1Param {
+    pub keep_alive: bool,
+    pub half_close: bool,
+    pub max_headers: Option<usize>,
+    pub max_header_size: Option<usize>,
+}
+
+impl Default for Http1Param {
+    fn default() -> Self {
+        Self {
+            keep_alive: true,
+            half_close: false,
+            max_headers: None,
+            max_header_size: None,
+        }
+    }
+}
+
+/// HTTP/2 参数
+///
+/// 为 None 的字段使用 hyper 默认值
+///
+/// # 字段
+/// - `max_concurrent_streams`: 单个连接的最大并发流数量
+/// - `initial_stream_window_size`: 流级别的初始流控窗口大小，不能超过 2^31-1
+/// - `initial_connection_window_size`: 连接级别的初始流控窗口大小，不能超过 2^31-1
+/// - `adaptive_window`: 是否根据带宽延迟积自动调整窗口大小，启用后忽略上面两个窗口大小
+/// - `keep_alive_interval`: 发送 keep-alive PING 的间隔，为 None 时不发送
+/// - `keep_alive_timeout`: 等待 PING 响应的超时时间，超时后关闭连接，仅在设置了 `keep_alive_interval` 时生效
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Http2Param {
+    pub max_concurrent_streams: Option<u32>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    pub adaptive_window: bool,
+    pub keep_alive_interval: Option<Duration>,
+    pub keep_alive_timeout: Option<Duration>,
+}
+
+/// HTTP/1 读缓冲区的最小值，hyper 不接受更小的值
+const HTTP1_MIN_BUF_SIZE: usize = 8192;
+/// HTTP/2 流控窗口的最大值
+const HTTP2_MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+/// HTTP/2 连接前言
+const HTTP2_PREFACE: &[u8; 24] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+impl ProtocolParam {
+    /// 检查参数是否有效
+    ///
+    /// # 返回
+    /// - `Ok(())`: 参数有效
+    /// - `Err(std::io::Error)`: `max_header_size` 小于 8192 或窗口大小超过 2^31-1
+    pub fn validate(&self) -> Result<(), std::io::Error> {
+        if let Some(size) = self.http1.max_header_size.filter(|size| *size < HTTP1_MIN_BUF_SIZE) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("http1 max_header_size {size} is smaller than {HTTP1_MIN_BUF_SIZE}"),
+            ));
+        }
+        for (name, size) in [
+            ("initial_stream_window_size", self.http2.initial_stream_window_size),
+            ("initial_connection_window_size", self.http2.initial_connection_window_size),
+        ] {
+            if let Some(size) = size.filter(|size| *size > HTTP2_MAX_WINDOW_SIZE) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("http2 {name} {size} is larger than {HTTP2_MAX_WINDOW_SIZE}"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 创建应用了这些参数的 hyper 连接构建器
+    ///
+    /// 调用前应先通过 [`ProtocolParam::validate`] 检查参数，否则 hyper 可能 panic
+    ///
+    /// # 参数
+    /// - `header_read`: HTTP/1 请求头读取超时，为 None 时不限制
+    fn builder(&self, header_read: Option<Duration>) -> hyper_util::server::conn::auto::Builder<TokioExecutor> {
+        let mut builder = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+        builder = match self.versions {
+            HttpVersions::Auto => builder,
+            HttpVersions::Http1Only => builder.http1_only(),
+            HttpVersions::Http2Only => builder.http2_only(),
+        };
+        let mut http1 = builder.http1();
+        // 设置定时器后 hyper 默认的请求头读取超时为 30 秒，总是显式设置
+        http1
+            .timer(TokioTimer::new())
+            .header_read_timeout(header_read)
+            .keep_alive(self.http1.keep_alive)
+            .half_close(self.http1.half_close);
+        if let Some(max) = self.http1.max_headers {
+            http1.max_headers(max);
+        }
+        if let Some(max) = self.http1.max_header_size {
+            http1.max_buf_size(max);
+        }
+        let mut http2 = builder.http2();
+        // keep-alive PING 依赖定时器
+        http2
+            .timer(TokioTimer::new())
+            .max_concurrent_streams(self.http2.max_concurrent_streams)
+            .initial_stream_window_size(self.http2.initial_stream_window_size)
+            .initial_connection_window_size(self.http2.initial_connection_window_size)
+            .adaptive_window(self.http2.adaptive_window)
+            .keep_alive_interval(self.http2.keep_alive_interval);
+        if let Some(timeout) = self.http2.keep_alive_timeout {
+            http2.keep_alive_timeout(timeout);
+        }
+        builder
+    }
+
+    /// TLS 握手时通过 ALPN 声明的协议
+    fn alpn_protocols(&self) -> Vec<Vec<u8>> {
+        match self.versions {
+            HttpVersions::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+            HttpVersions::Http1Only => vec![b"http/1.1".to_vec()],
+            HttpVersions::Http2Only => vec![b"h2".to_vec()],
+        }
+    }
+}
+
+/// 热重启参数
+///
+/// # 字段
+/// - `ready_timeout`: 等待新进程就绪的超时时间，超时后终止新进程，旧进程继续提供服务
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy)]
+pub struct HotRestartParam {
+    pub ready_timeout: Duration,
+}
+
+#[cfg(unix)]
+impl Default for HotRestartParam {
+    fn default() -> Self {
+        Self {
+            ready_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// HTTP/3 参数
+///
+/// 启用后在每个 HTTPS TCP 监听器的同一 UDP 端口上提供 HTTP/3 服务，
+/// 使用同一份证书 (包括定时刷新)、路由、拦截器和关闭信号
+///
+/// # 字段
+/// - `alt_svc_max_age`: HTTPS 响应的 `Alt-Svc` 头中 `ma` 的值，即客户端记住 HTTP/3 服务的时长
+#[cfg(feature = "http3")]
+#[derive(Debug, Clone, Copy)]
+pub struct Http3Param {
+    pub alt_svc_max_age: Duration,
+}
+
+#[cfg(feature = "http3")]
+impl Default for Http3Param {
+    fn default() -> Self {
+        Self {
+            alt_svc_max_age: Duration::from_secs(60 * 60 * 24),
+        }
+    }
+}
+
 /// 请求拦截结果
 ///
 /// 用于控制请求的处理流程
 ///
 /// # 变体
 /// - `Return(Response)`: 直接返回响应，不继续处理
-/// - `Drop`: 丢弃请求，不返回响应
+/// - `Drop`: 丢弃请求，不写出任何响应。HTTP/1 连接以 TCP RST 关闭，HTTP/2 和 HTTP/3 重置该请求流，
+///   日志级别由 [`Server::with_drop_log_level`] 配置
 /// - `Continue(Request)`: 继续处理请求
 /// - `Error(T)`: 返回错误响应
 pub enum InterceptResult<T: IntoResponse> {
     Return(Response),
     Drop,
-    Continue(Request<Incoming>),
+    Continue(Request),
     Error(T),
 }
 
+/// 响应阶段的请求上下文
+///
+/// 在调用拦截器之前从请求中复制，供 [`ReqInterceptor::on_response`] 使用
+///
+/// # 字段
+/// - `method`: 请求方法
+/// - `uri`: 请求 URI
+/// - `version`: HTTP 版本
+/// - `headers`: 拦截器修改之前的请求头
+/// - `connection`: 连接信息
+/// - `received_at`: 开始处理请求的时间
+#[derive(Debug, Clone)]
+pub struct ResponseContext {
+    pub method: hyper::Method,
+    pub uri: hyper::Uri,
+    pub version: hyper::Version,
+    pub headers: hyper::HeaderMap,
+    pub connection: ConnectionInfo,
+    pub received_at: std::time::Instant,
+}
+
+impl ResponseContext {
+    fn new(request: &Request, connection: ConnectionInfo) -> Self {
+        Self {
+            method: request.method().clone(),
+            uri: request.uri().clone(),
+            version: request.version(),
+            headers: request.headers().clone(),
+            connection,
+            received_at: std::time::Instant::now(),
+        }
+    }
+
+    /// 从开始处理请求到现在经过的时间
+    pub fn elapsed(&self) -> Duration {
+        self.received_at.elapsed()
+    }
+}
+
+/// 拦截器丢弃请求时 [`handle`] 返回的内部错误
+#[derive(Debug)]
+struct InterceptorDrop;
+
+impl std::fmt::Display for InterceptorDrop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("request dropped by interceptor")
+    }
+}
+
+impl std::error::Error for InterceptorDrop {}
+
+impl InterceptorDrop {
+    /// 判断错误是否由拦截器丢弃请求产生
+    fn is(err: &std::io::Error) -> bool {
+        err.get_ref().is_some_and(|err| err.is::<InterceptorDrop>())
+    }
+}
+
 /// 请求拦截器 trait
 ///
 /// 实现此 trait 可以在请求到达路由处理器之前进行拦截和处理
@@ -126,13 +640,62 @@ pub enum InterceptResult<T: IntoResponse> {
 ///
 /// # 方法
 /// - `intercept`: 拦截请求的方法
+/// - `on_response`: 处理响应的方法，默认原样返回响应
+///
+/// # 响应阶段
+/// 请求没有被丢弃时，`on_response` 在响应写出之前调用，包括路由处理器生成的响应
+/// 和拦截器 `Return`、`Error` 生成的响应，可以用来添加响应头、按客户端统计状态码和耗时，
+/// 或者改写 5xx 响应体。`ctx` 中保存了原始请求的方法、URI、请求头和 [`ConnectionInfo`]。
+/// HSTS 和 `Alt-Svc` 头在 `on_response` 之后添加，且不会覆盖 `on_response` 设置的同名头
+///
+/// # 对端地址
+/// `ip` 参数为 TCP 连接的对端地址。Unix domain socket 连接没有对端地址，
+/// 此时 `ip` 为 [`PeerAddr::UNSPECIFIED_SOCKET_ADDR`]，
+/// 可以从请求的 extensions 中获取 `ConnectInfo<PeerAddr>` 得到完整的对端描述，
+/// 或获取 [`ConnectionInfo`] 得到本地地址、TLS 信息 (SNI、ALPN、客户端证书等) 和连接 ID
+///
+/// # 请求体
+/// 请求体统一为 [`axum::body::Body`]，HTTP/1、HTTP/2 和 HTTP/3 请求使用同一个拦截器。
+/// 0.1 版本中请求体为 `hyper::body::Incoming`，从 0.1 升级时把拦截器签名中的 `Request<Incoming>` 改为 `Request`，
+/// 详见 README 中的升级说明
+///
+/// # 组合
+/// 元组 `(A, B, ...)` (最多 8 个) 也实现了此 trait，按顺序依次调用每个拦截器，
+/// 后一个拦截器收到前一个拦截器 `Continue` 传出的请求。第一个 `Return`、`Drop` 或 `Error` 会直接返回，
+/// 不再调用后续拦截器，各拦截器的错误统一包装为 [`ChainError`]。元组可以嵌套。
+/// 响应阶段按相反的顺序调用每个拦截器的 `on_response`，包括短路时没有调用 `intercept` 的拦截器
+///
+/// ```no_run
+/// # use axum_bootstrap::{ReqInterceptor, InterceptResult};
+/// # use axum::extract::Request;
+/// # use std::net::SocketAddr;
+/// # #[derive(Clone)]
+/// # struct IpFilter;
+/// # impl ReqInterceptor for IpFilter {
+/// #     type Error = axum_bootstrap::error::AppError;
+/// #     async fn intercept(&self, req: Request, _ip: SocketAddr) -> InterceptResult<Self::Error> { InterceptResult::Continue(req) }
+/// # }
+/// # #[derive(Clone)]
+/// # struct Auth;
+/// # impl ReqInterceptor for Auth {
+/// #     type Error = axum_bootstrap::error::AppError;
+/// #     async fn intercept(&self, req: Request, _ip: SocketAddr) -> InterceptResult<Self::Error> { InterceptResult::Continue(req) }
+/// # }
+/// use axum::Router;
+/// use axum_bootstrap::{generate_shutdown_receiver, new_server};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let server = new_server(8080, Router::new(), generate_shutdown_receiver()).with_interceptor((IpFilter, Auth));
+///     server.run().await.unwrap();
+/// }
+/// ```
 ///
 /// # 示例
 ///
 /// ```no_run
 /// use axum_bootstrap::{ReqInterceptor, InterceptResult};
 /// use axum::extract::Request;
-/// use hyper::body::Incoming;
 /// use std::net::SocketAddr;
 ///
 /// #[derive(Clone)]
@@ -141,7 +704,7 @@ pub enum InterceptResult<T: IntoResponse> {
 /// impl ReqInterceptor for MyInterceptor {
 ///     type Error = axum_bootstrap::error::AppError;
 ///
-///     async fn intercept(&self, req: Request<Incoming>, ip: SocketAddr) -> InterceptResult<Self::Error> {
+///     async fn intercept(&self, req: Request, ip: SocketAddr) -> InterceptResult<Self::Error> {
 ///         // 自定义拦截逻辑
 ///         InterceptResult::Continue(req)
 ///     }
@@ -149,9 +712,89 @@ pub enum InterceptResult<T: IntoResponse> {
 /// ```
 pub trait ReqInterceptor: Send {
     type Error: IntoResponse + Send + Sync + 'static;
-    fn intercept(&self, req: Request<Incoming>, ip: SocketAddr) -> impl std::future::Future<Output = InterceptResult<Self::Error>> + Send;
+    fn intercept(&self, req: Request, ip: SocketAddr) -> impl std::future::Future<Output = InterceptResult<Self::Error>> + Send;
+
+    fn on_response(&self, response: Response, ctx: &ResponseContext) -> impl std::future::Future<Output = Response> + Send {
+        let _ = ctx;
+        std::future::ready(response)
+    }
+}
+
+/// 拦截器链的错误类型
+///
+/// 保存链中某个拦截器返回的错误，生成响应时使用该错误自身的 `IntoResponse` 实现
+pub struct ChainError(Box<dyn FnOnce() -> Response + Send + Sync>);
+
+impl ChainError {
+    /// 包装拦截器返回的错误
+    ///
+    /// # 参数
+    /// - `err`: 拦截器返回的错误
+    pub fn new<E>(err: E) -> Self
+    where
+        E: IntoResponse + Send + Sync + 'static,
+    {
+        Self(Box::new(move || err.into_response()))
+    }
+}
+
+impl IntoResponse for ChainError {
+    fn into_response(self) -> Response {
+        (self.0)()
+    }
+}
+
+/// 为元组实现拦截器链
+///
+/// 按元组顺序调用拦截器，遇到非 `Continue` 的结果时短路返回，错误统一包装为 [`ChainError`]
+macro_rules! impl_interceptor_chain {
+    ($($interceptor:ident),+) => {
+        impl<$($interceptor),+> ReqInterceptor for ($($interceptor,)+)
+        where
+            $($interceptor: ReqInterceptor + Sync,)+
+        {
+            type Error = ChainError;
+
+            #[allow(non_snake_case)]
+            async fn intercept(&self, req: Request, ip: SocketAddr) -> InterceptResult<Self::Error> {
+                let ($($interceptor,)+) = self;
+                $(
+                    let req = match $interceptor.intercept(req, ip).await {
+                        InterceptResult::Continue(req) => req,
+                        InterceptResult::Return(res) => return InterceptResult::Return(res),
+                        InterceptResult::Drop => return InterceptResult::Drop,
+                        InterceptResult::Error(err) => return InterceptResult::Error(ChainError::new(err)),
+                    };
+                )+
+                InterceptResult::Continue(req)
+            }
+
+            #[allow(non_snake_case)]
+            async fn on_response(&self, response: Response, ctx: &ResponseContext) -> Response {
+                let ($($interceptor,)+) = self;
+                impl_interceptor_chain!(@rev response, ctx, [$($interceptor)+] []);
+                response
+            }
+        }
+    };
+    (@rev $response:ident, $ctx:ident, [$first:ident $($rest:ident)*] [$($reversed:ident)*]) => {
+        impl_interceptor_chain!(@rev $response, $ctx, [$($rest)*] [$first $($reversed)*])
+    };
+    (@rev $response:ident, $ctx:ident, [] [$($reversed:ident)*]) => {
+        $(
+            let $response = $reversed.on_response($response, $ctx).await;
+        )*
+    };
 }
 
+impl_interceptor_chain!(A, B);
+impl_interceptor_chain!(A, B, C);
+impl_interceptor_chain!(A, B, C, D);
+impl_interceptor_chain!(A, B, C, D, E);
+impl_interceptor_chain!(A, B, C, D, E, F);
+impl_interceptor_chain!(A, B, C, D, E, F, G);
+impl_interceptor_chain!(A, B, C, D, E, F, G, H);
+
 /// 空实现的请求拦截器
 ///
 /// 默认不执行任何拦截操作，直接继续处理请求
@@ -161,7 +804,7 @@ pub struct DummyInterceptor;
 impl ReqInterceptor for DummyInterceptor {
     type Error = error::AppError;
 
-    async fn intercept(&self, req: Request<Incoming>, _ip: SocketAddr) -> InterceptResult<Self::Error> {
+    async fn intercept(&self, req: Request, _ip: SocketAddr) -> InterceptResult<Self::Error> {
         InterceptResult::Continue(req)
     }
 }
@@ -172,13 +815,15 @@ pub type DefaultServer = Server<DummyInterceptor>;
 /// 创建默认服务器实例
 ///
 /// # 参数
-/// - `port`: 监听端口
+/// - `port`: 监听端口，绑定到 `[::]:port` (IPv4/IPv6 双栈)
 /// - `router`: Axum 路由
 /// - `shutdown_rx`: 关闭信号接收器
 ///
 /// # 返回
 /// 返回配置好的服务器实例，默认不启用 TLS，空闲超时为 120 秒
 ///
+/// 如需绑定到指定网卡或同时监听多个地址，使用 [`Server::with_addrs`]
+///
 /// # 示例
 ///
 /// ```no_run
@@ -195,11 +840,35 @@ pub type DefaultServer = Server<DummyInterceptor>;
 /// ```
 pub fn new_server(port: u16, router: Router, shutdown_rx: broadcast::Receiver<()>) -> Server {
     Server {
-        port,
+        addrs: vec![SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 0], port))],
+        #[cfg(unix)]
+        unix_sockets: Vec::new(),
+        #[cfg(unix)]
+        systemd_socket_activation: false,
+        #[cfg(unix)]
+        hot_restart: None,
+        backlog: io::DEFAULT_BACKLOG,
+        #[cfg(unix)]
+        reuse_port_acceptors: 1,
+        proxy_protocol: HashMap::new(),
+        connection_limit: None,
+        connection_stats: ConnectionStats::default(),
+        connection_hook: None,
+        reload_hooks: Vec::new(),
+        #[cfg(unix)]
+        sighup_reload: false,
         tls_param: None, // 默认不启用 TLS
+        http_param: None,
+        hsts: None,
+        protocol: ProtocolParam::default(),
+        #[cfg(feature = "http3")]
+        http3: None,
         router,
+        sni_certificates: Vec::new(),
         interceptor: None,
+        drop_log_level: log::LevelFilter::Debug,
         idle_timeout: Duration::from_secs(120),
+        timeouts: TimeoutParam::default(),
         shutdown_rx,
     }
 }
@@ -225,125 +894,1367 @@ where
         R: ReqInterceptor + Clone + Send + Sync + 'static,
     {
         Server::<R> {
-            port: self.port,
+            addrs: self.addrs,
+            #[cfg(unix)]
+            unix_sockets: self.unix_sockets,
+            #[cfg(unix)]
+            systemd_socket_activation: self.systemd_socket_activation,
+            #[cfg(unix)]
+            hot_restart: self.hot_restart,
+            backlog: self.backlog,
+            #[cfg(unix)]
+            reuse_port_acceptors: self.reuse_port_acceptors,
+            proxy_protocol: self.proxy_protocol,
+            connection_limit: self.connection_limit,
+            connection_stats: self.connection_stats,
+            connection_hook: self.connection_hook,
+            reload_hooks: self.reload_hooks,
+            #[cfg(unix)]
+            sighup_reload: self.sighup_reload,
             tls_param: self.tls_param,
+            http_param: self.http_param,
+            hsts: self.hsts,
+            protocol: self.protocol,
+            #[cfg(feature = "http3")]
+            http3: self.http3,
             router: self.router,
+            sni_certificates: self.sni_certificates,
             interceptor: Some(interceptor),
+            drop_log_level: self.drop_log_level,
             idle_timeout: self.idle_timeout, // 保持相同的空闲超时
+            timeouts: self.timeouts,
             shutdown_rx: self.shutdown_rx,
         }
     }
 
-    /// 设置 TLS 参数
+    /// 设置拦截器丢弃请求时的日志级别
+    ///
+    /// 拦截器返回 [`InterceptResult::Drop`] 时不写出响应，只按该级别记录一条日志，
+    /// 适合低成本地屏蔽扫描器和恶意客户端
     ///
     /// # 参数
-    /// - `tls_param`: TLS 配置参数，为 None 时禁用 TLS
+    /// - `level`: 日志级别，`LevelFilter::Off` 时不记录，默认为 `Debug`
     ///
     /// # 返回
-    /// 返回配置了 TLS 的服务器实例
-    pub fn with_tls_param(mut self, tls_param: Option<TlsParam>) -> Self {
-        self.tls_param = tls_param;
+    /// 返回配置了日志级别的服务器实例
+    pub fn with_drop_log_level(mut self, level: log::LevelFilter) -> Self {
+        self.drop_log_level = level;
+        self
+    }
+
+    /// 设置监听地址列表
+    ///
+    /// 替换 `new_server` 时指定的端口，每个地址创建一个监听器，
+    /// 所有监听器共享同一个路由、拦截器和关闭信号
+    ///
+    /// # 参数
+    /// - `addrs`: 监听地址列表，例如 `127.0.0.1:9000`、`10.0.0.5:443`、`[::]:8080`
+    ///
+    /// # 返回
+    /// 返回配置了监听地址的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::Router;
+    /// use axum_bootstrap::{new_server, generate_shutdown_receiver};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let server = new_server(0, Router::new(), generate_shutdown_receiver())
+    ///         .with_addrs(vec!["127.0.0.1:9000".parse().unwrap(), "[::1]:9000".parse().unwrap()]);
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    pub fn with_addrs(mut self, addrs: Vec<SocketAddr>) -> Self {
+        self.addrs = addrs;
+        self
+    }
+
+    /// 添加 Unix domain socket 监听
+    ///
+    /// 与 TCP 监听地址同时生效；如只需要监听 Unix domain socket，
+    /// 可以通过 `with_addrs(vec![])` 清空 TCP 监听地址
+    ///
+    /// # 参数
+    /// - `param`: Unix domain socket 监听参数
+    ///
+    /// # 返回
+    /// 返回添加了 Unix domain socket 监听的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::Router;
+    /// use axum_bootstrap::{UnixSocketParam, new_server, generate_shutdown_receiver};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let server = new_server(0, Router::new(), generate_shutdown_receiver())
+    ///         .with_addrs(vec![])
+    ///         .with_unix_socket(UnixSocketParam {
+    ///             path: "/run/app/app.sock".into(),
+    ///             mode: Some(0o660),
+    ///             proxy_protocol: None,
+    ///         });
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    #[cfg(unix)]
+    pub fn with_unix_socket(mut self, param: UnixSocketParam) -> Self {
+        self.unix_sockets.push(param);
+        self
+    }
+
+    /// 设置 TCP 监听队列大小 (backlog)
+    ///
+    /// 实际生效值受系统参数 (例如 Linux 的 `net.core.somaxconn`) 限制，
+    /// 使用 systemd socket activation 或热重启继承的监听器时不生效
+    ///
+    /// # 参数
+    /// - `backlog`: 监听队列大小，默认 [`util::io::DEFAULT_BACKLOG`]
+    ///
+    /// # 返回
+    /// 返回配置了监听队列大小的服务器实例
+    pub fn with_backlog(mut self, backlog: u32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// 启用 `SO_REUSEPORT` 多 acceptor 模式
+    ///
+    /// 为每个 TCP 监听地址 (包括 [`HttpParam`] 的地址) 创建 `acceptors` 个 `SO_REUSEPORT` 监听器，
+    /// 每个监听器运行独立的 accept 循环 (包括 TLS 握手)，由内核把新连接分发到各个监听器，
+    /// 避免单个 accept 循环在高连接速率下成为瓶颈。所有监听器共享同一个优雅关闭句柄
+    ///
+    /// 使用 systemd socket activation 继承的监听器时不生效；热重启时所有监听器都会交给新进程
+    ///
+    /// # 参数
+    /// - `acceptors`: 每个地址的监听器数量，为 0 或 1 时不启用，通常设置为 CPU 核数
+    ///
+    /// # 返回
+    /// 返回配置了多 acceptor 模式的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::Router;
+    /// use axum_bootstrap::{new_server, generate_shutdown_receiver};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let acceptors = std::thread::available_parallelism().map_or(1, |n| n.get());
+    ///     let server = new_server(8080, Router::new(), generate_shutdown_receiver())
+    ///         .with_reuse_port(acceptors)
+    ///         .with_backlog(4096);
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    #[cfg(unix)]
+    pub fn with_reuse_port(mut self, acceptors: usize) -> Self {
+        self.reuse_port_acceptors = acceptors;
         self
     }
 
-    /// 设置连接空闲超时时间
+    /// 为 TCP 监听地址启用 PROXY protocol
+    ///
+    /// 启用后，该地址上的连接在 TLS 握手和 HTTP 解析之前先读取 PROXY protocol v1/v2 头，
+    /// 头部中的源地址会替换连接的对端地址，包括拦截器的 `ip` 参数、`ConnectInfo` 和日志。
+    /// 头部必须在 [`TimeoutParam`] 的 `proxy_header` 时间内到达，格式错误或 `Required` 模式下缺少头部时关闭连接
+    ///
+    /// 地址需要与 `addrs` 或 [`HttpParam`] 中的地址一致；使用继承的监听器时按监听器的本地地址匹配。
+    /// Unix domain socket 通过 [`UnixSocketParam`] 的 `proxy_protocol` 字段配置
+    ///
+    /// # 参数
+    /// - `addr`: TCP 监听地址
+    /// - `mode`: PROXY protocol 模式
+    ///
+    /// # 返回
+    /// 返回配置了 PROXY protocol 的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::Router;
+    /// use axum_bootstrap::{new_server, generate_shutdown_receiver, util::proxy_protocol::ProxyProtocolMode};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // 只接受来自负载均衡器的连接
+    ///     let server = new_server(8080, Router::new(), generate_shutdown_receiver())
+    ///         .with_proxy_protocol("[::]:8080".parse().unwrap(), ProxyProtocolMode::Required);
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    pub fn with_proxy_protocol(mut self, addr: SocketAddr, mode: ProxyProtocolMode) -> Self {
+        self.proxy_protocol.insert(addr, mode);
+        self
+    }
+
+    /// 设置连接数限制
+    ///
+    /// 全局上限在 accept 时检查，单 IP 上限在读取 PROXY protocol 头之后、TLS 握手之前检查，
+    /// 限额在连接任务结束时释放。被拒绝的连接会记录日志并计入 [`ConnectionStats`]
+    ///
+    /// # 参数
+    /// - `connection_limit`: 连接数限制参数，为 None 时不限制
+    ///
+    /// # 返回
+    /// 返回配置了连接数限制的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::Router;
+    /// use axum_bootstrap::{
+    ///     connection::{ConnectionLimitParam, LimitAction},
+    ///     generate_shutdown_receiver, new_server,
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let server = new_server(8080, Router::new(), generate_shutdown_receiver()).with_connection_limit(Some(ConnectionLimitParam {
+    ///         max_connections: Some(10_000),
+    ///         max_connections_per_ip: Some(100),
+    ///         action: LimitAction::Backpressure,
+    ///     }));
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    pub fn with_connection_limit(mut self, connection_limit: Option<ConnectionLimitParam>) -> Self {
+        self.connection_limit = connection_limit;
+        self
+    }
+
+    /// 设置连接级别的钩子
+    ///
+    /// 钩子的 `on_connect` 在 accept 之后、TLS 握手之前调用，可以直接丢弃连接；
+    /// 被接受的连接关闭时调用 `on_disconnect`，提供连接时长和读写字节数。详见 [`ConnectionHook`]
+    ///
+    /// # 参数
+    /// - `hook`: 连接级别的钩子
+    ///
+    /// # 返回
+    /// 返回配置了连接钩子的服务器实例
+    pub fn with_connection_hook<H: ConnectionHook>(mut self, hook: H) -> Self {
+        self.connection_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// 添加重新加载钩子
+    ///
+    /// 收到 `SIGHUP` (需要 [`Server::with_sighup_reload`]) 或调用 [`ServerHandle::reload`] 时，
+    /// 按添加顺序调用所有钩子，然后重新读取 TLS 证书。详见 [`reload`] 模块
+    ///
+    /// # 参数
+    /// - `hook`: 重新加载钩子
+    ///
+    /// # 返回
+    /// 返回添加了重新加载钩子的服务器实例
+    pub fn with_reload_hook<H: ReloadHook>(mut self, hook: H) -> Self {
+        self.reload_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// 收到 `SIGHUP` 时重新加载
+    ///
+    /// 重新读取 TLS 证书并调用所有重新加载钩子，已有连接不受影响。
+    /// 启用后 `SIGHUP` 不再终止进程，证书续期工具 (如 certbot、cert-manager) 可以通过 `kill -HUP` 通知服务器
+    ///
+    /// # 参数
+    /// - `enable`: 是否启用，默认不启用
+    ///
+    /// # 返回
+    /// 返回配置了 `SIGHUP` 重新加载的服务器实例
+    #[cfg(unix)]
+    pub fn with_sighup_reload(mut self, enable: bool) -> Self {
+        self.sighup_reload = enable;
+        self
+    }
+
+    /// 获取连接统计
+    ///
+    /// 返回的统计与服务器共享计数，可以在 `run` 之前获取并在运行期间读取
+    ///
+    /// # 返回
+    /// 连接统计
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.connection_stats.clone()
+    }
+
+    /// 启用 systemd socket activation
+    ///
+    /// 启用后，如果当前进程由 systemd 通过 socket activation 启动 (`LISTEN_PID` 为当前进程)，
+    /// 则使用 systemd 传递的监听 socket 代替 `addrs` 和 Unix domain socket 配置，
+    /// 从而在非 root 用户下使用特权端口，并且重启期间新连接会在 systemd 持有的 socket 上排队；
+    /// 否则仍然绑定配置的地址。
+    ///
+    /// 名称 (`FileDescriptorName=`) 为 `http` 的 socket 在配置了 [`HttpParam`] 时按明文 HTTP 处理
+    /// (处理请求或重定向到 HTTPS)，其它 socket 按 TLS 配置处理 HTTP 或 HTTPS
+    ///
+    /// # 参数
+    /// - `enable`: 是否启用
+    ///
+    /// # 返回
+    /// 返回配置了 socket activation 的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```ini
+    /// # app.socket
+    /// [Socket]
+    /// ListenStream=443
+    /// FileDescriptorName=https
+    ///
+    /// # app-http.socket
+    /// [Socket]
+    /// ListenStream=80
+    /// FileDescriptorName=http
+    /// Service=app.service
+    /// ```
+    #[cfg(unix)]
+    pub fn with_systemd_socket_activation(mut self, enable: bool) -> Self {
+        self.systemd_socket_activation = enable;
+        self
+    }
+
+    /// 启用热重启
+    ///
+    /// 启用后，进程收到 SIGUSR2 时以相同的参数重新执行当前程序 (通常是已经替换的新版本二进制)，
+    /// 程序路径在服务器启动时记录，所以可以用 rename 的方式替换二进制，
+    /// 并把所有监听 socket 交给新进程。新进程使用继承的 socket 完成启动并通知就绪后，
+    /// 旧进程停止 accept，按照正常关闭流程等待已有连接结束后 `run` 返回；
+    /// 新进程启动失败或超时未就绪时，旧进程终止新进程并继续提供服务。
+    /// 整个过程中监听 socket 始终可用，新连接不会被拒绝
+    ///
+    /// 新进程需要同样启用热重启才能使用继承的 socket。
+    /// 由 systemd 管理时，旧进程 (服务的主进程) 退出会导致 systemd 停止整个服务，
+    /// 此时推荐使用 [`Server::with_systemd_socket_activation`] 配合普通重启
+    ///
+    /// # 参数
+    /// - `hot_restart`: 热重启参数，为 None 时不响应 SIGUSR2
+    ///
+    /// # 返回
+    /// 返回配置了热重启的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::Router;
+    /// use axum_bootstrap::{HotRestartParam, new_server, generate_shutdown_receiver};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // 替换二进制后执行 `kill -USR2 <pid>` 即可升级
+    ///     let server = new_server(8080, Router::new(), generate_shutdown_receiver()).with_hot_restart(Some(HotRestartParam::default()));
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    #[cfg(unix)]
+    pub fn with_hot_restart(mut self, hot_restart: Option<HotRestartParam>) -> Self {
+        self.hot_restart = hot_restart;
+        self
+    }
+
+    /// 设置 TLS 参数
+    ///
+    /// # 参数
+    /// - `tls_param`: TLS 配置参数，为 None 时禁用 TLS
+    ///
+    /// # 返回
+    /// 返回配置了 TLS 的服务器实例
+    pub fn with_tls_param(mut self, tls_param: Option<TlsParam>) -> Self {
+        self.tls_param = tls_param;
+        self
+    }
+
+    /// 设置虚拟主机
+    ///
+    /// 按请求的主机名或 SNI 把请求分派到各虚拟主机的路由，替换 [`new_server`] 传入的路由。
+    /// 虚拟主机的独立证书在 TLS 握手时按 SNI 选择，需要同时通过 [`Server::with_tls_param`] 启用 TLS，
+    /// 没有匹配的 SNI 时使用服务器的证书
+    ///
+    /// # 参数
+    /// - `hosts`: 虚拟主机映射，见 [`vhost::VirtualHosts`]
+    ///
+    /// # 返回
+    /// 返回配置了虚拟主机的服务器实例
+    pub fn with_virtual_hosts(mut self, hosts: vhost::VirtualHosts) -> Self {
+        let (router, sni_certificates) = hosts.into_parts();
+        self.router = router;
+        self.sni_certificates = sni_certificates;
+        self
+    }
+
+    /// 设置明文 HTTP 监听参数
+    ///
+    /// 启用 TLS 时额外监听明文 HTTP 地址，处理请求或重定向到 HTTPS
+    ///
+    /// # 参数
+    /// - `http_param`: 明文 HTTP 监听参数，为 None 时不额外监听
+    ///
+    /// # 返回
+    /// 返回配置了明文 HTTP 监听的服务器实例
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::Router;
+    /// use axum_bootstrap::{HttpParam, HttpRedirectParam, TlsParam, new_server, generate_shutdown_receiver};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let server = new_server(443, Router::new(), generate_shutdown_receiver())
+    ///         .with_tls_param(Some(TlsParam {
+    ///             tls: true,
+    ///             cert: "cert.pem".to_string(),
+    ///             key: "privkey.pem".to_string(),
+    ///         }))
+    ///         .with_http_param(Some(HttpParam {
+    ///             addrs: vec!["[::]:80".parse().unwrap()],
+    ///             redirect: Some(HttpRedirectParam::default()),
+    ///         }));
+    ///     server.run().await.unwrap();
+    /// }
+    /// ```
+    pub fn with_http_param(mut self, http_param: Option<HttpParam>) -> Self {
+        self.http_param = http_param;
+        self
+    }
+
+    /// 设置 HSTS 参数
+    ///
+    /// # 参数
+    /// - `hsts`: HSTS 参数，为 None 时不附加 `Strict-Transport-Security` 头
+    ///
+    /// # 返回
+    /// 返回配置了 HSTS 的服务器实例
+    pub fn with_hsts(mut self, hsts: Option<HstsParam>) -> Self {
+        self.hsts = hsts;
+        self
+    }
+
+    /// 设置 HTTP 协议参数
+    ///
+    /// 可以限制 HTTP 版本并调整 HTTP/1 和 HTTP/2 的连接参数，参数在 `run` 时检查
+    ///
+    /// # 参数
+    /// - `protocol`: HTTP 协议参数
+    ///
+    /// # 返回
+    /// 返回配置了协议参数的服务器实例
+    pub fn with_protocol(mut self, protocol: ProtocolParam) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// 启用 HTTP/3
+    ///
+    /// 需要同时启用 TLS，QUIC 端点绑定在每个 HTTPS TCP 监听地址对应的 UDP 端口上，
+    /// HTTPS 响应附加 `Alt-Svc` 头声明 HTTP/3 服务。QUIC 端点不参与 systemd socket activation 和热重启交接，
+    /// 热重启时新进程通过 `SO_REUSEPORT` 绑定同一 UDP 端口，旧进程的 HTTP/3 连接可能被中断
+    ///
+    /// # 参数
+    /// - `http3`: HTTP/3 参数，为 None 时不启用
+    ///
+    /// # 返回
+    /// 返回配置了 HTTP/3 的服务器实例
+    #[cfg(feature = "http3")]
+    pub fn with_http3(mut self, http3: Option<Http3Param>) -> Self {
+        self.http3 = http3;
+        self
+    }
+
+    /// 设置连接空闲超时时间
+    ///
+    /// 连接上没有任何读写的时间超过该值后关闭连接，包括 keep-alive 连接等待下一个请求的时间
+    ///
+    /// # 参数
+    /// - `timeout`: 超时时长
+    ///
+    /// # 返回
+    /// 返回配置了超时的服务器实例
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// 设置 TLS 握手、请求头读取和连接存活时间的超时参数
+    ///
+    /// # 参数
+    /// - `timeouts`: 超时参数，参见 [`TimeoutParam`]
+    ///
+    /// # 返回
+    /// 返回配置了超时参数的服务器实例
+    pub fn with_timeouts(mut self, timeouts: TimeoutParam) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// 启动服务器
+    ///
+    /// 绑定所有监听地址，根据 TLS 配置启动 HTTP 或 HTTPS 服务器，并监听关闭信号。
+    /// 每个监听器运行独立的 accept 循环，收到关闭信号后统一进行优雅关闭
+    ///
+    /// 同时配置了 TLS 和 [`HttpParam`] 时，`addrs` 监听 HTTPS，`HttpParam.addrs` 监听明文 HTTP
+    /// (处理请求或重定向到 HTTPS)，所有监听器共享同一个关闭信号
+    ///
+    /// 启用热重启时，监听 socket 交给新进程后同样停止 accept 并优雅关闭，然后返回 `Ok(())`
+    ///
+    /// accept 出错时，单个连接的错误 (如 `ECONNABORTED`) 被忽略；资源耗尽 (如 `EMFILE`、`ENFILE`)
+    /// 时指数退避后重试，警告日志限速输出；监听 socket 不可用等致命错误会停止所有监听器，
+    /// 等待已有连接优雅关闭后返回该错误
+    ///
+    /// 如需获取实际绑定的地址或在程序中关闭服务器，使用 [`Server::spawn`]
+    ///
+    /// # 返回
+    /// - `Ok(())`: 服务器成功启动并正常关闭
+    /// - `Err(std::io::Error)`: 启动或运行过程中出现 I/O 错误
+    ///
+    /// # 错误
+    /// - HTTP 协议参数无效
+    /// - 没有配置监听地址
+    /// - 配置了重定向到 HTTPS 或 HTTP/3 但没有启用 TLS
+    /// - 端口绑定失败
+    /// - TLS 证书加载失败
+    /// - 致命的 accept 错误
+    pub async fn run(self) -> Result<(), std::io::Error> {
+        // 没有 ServerHandle 时发送端直接丢弃，只响应 shutdown_rx
+        let (_, handle_rx) = watch::channel(None);
+        let (_, _, serve) = self.start(handle_rx).await?;
+        serve.await
+    }
+
+    /// 在后台任务中启动服务器
+    ///
+    /// 绑定所有监听器并加载 TLS 配置后立即返回，服务器在新的 tokio 任务中运行，
+    /// 适合集成测试和嵌入到其他程序中。`shutdown_rx` 仍然有效，两种关闭方式任选其一
+    ///
+    /// # 返回
+    /// - `Ok(ServerHandle)`: 服务器句柄，可获取绑定地址、连接数并关闭服务器
+    /// - `Err(std::io::Error)`: 绑定或 TLS 配置加载失败，错误与 [`Server::run`] 的启动错误相同
+    ///
+    /// # 示例
+    ///
+    /// ```no_run
+    /// use axum::{Router, routing::get};
+    /// use axum_bootstrap::new_server;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let (_shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+    ///     let handle = new_server(0, Router::new().route("/", get(|| async { "ok" })), shutdown_rx)
+    ///         .with_addrs(vec!["127.0.0.1:0".parse().unwrap()])
+    ///         .spawn()
+    ///         .await
+    ///         .unwrap();
+    ///     let addr = handle.local_addrs()[0];
+    ///     println!("listening on {addr}, {} connections", handle.open_connections());
+    ///     handle.shutdown();
+    ///     handle.await.unwrap();
+    /// }
+    /// ```
+    pub async fn spawn(self) -> Result<ServerHandle, std::io::Error> {
+        let (shutdown_tx, handle_rx) = watch::channel(None);
+        let stats = self.connection_stats();
+        let (local_addrs, reloader, serve) = self.start(handle_rx).await?;
+        Ok(ServerHandle {
+            local_addrs,
+            shutdown_tx,
+            stats,
+            reloader,
+            task: tokio::spawn(serve),
+        })
+    }
+
+    /// 绑定监听器并加载 TLS 配置，启用 HTTP/3 时同时绑定 QUIC 端点
+    ///
+    /// # 参数
+    /// - `handle_rx`: [`ServerHandle`] 发出的关闭请求，值为优雅关闭的等待时间
+    ///
+    /// # 返回
+    /// - `Ok((Vec<SocketAddr>, Arc<Reloader>, impl Future))`: 实际绑定的 TCP 地址、重新加载器和运行服务器的 future
+    /// - `Err(std::io::Error)`: 绑定、TLS 配置加载或注册 `SIGHUP` 失败
+    async fn start(
+        self, handle_rx: watch::Receiver<Option<Duration>>,
+    ) -> Result<(Vec<SocketAddr>, Arc<Reloader>, impl Future<Output = Result<(), std::io::Error>> + Send + 'static), std::io::Error> {
+        self.protocol.validate()?;
+        let tls_param = self.tls_param.clone().filter(|param| param.tls);
+        let use_tls = tls_param.is_some();
+        let (listeners, inherited) = self.bind(use_tls).await?;
+        let mut local_addrs = Vec::new();
+        for bound in &listeners {
+            log::info!("listening on {bound}");
+            // SO_REUSEPORT 的多个监听器共享同一个地址
+            if let Listener::Tcp(listener) = &bound.listener {
+                match listener.local_addr() {
+                    Ok(addr) if !local_addrs.contains(&addr) => local_addrs.push(addr),
+                    Ok(_) => {}
+                    Err(e) => warn!("get local addr of {bound} error: {e}"),
+                }
+            }
+        }
+        let tls = match tls_param {
+            Some(tls_param) => Some((load_tls_config(&tls_param, &self.sni_certificates, &self.protocol)?, tls_param)),
+            None => None,
+        };
+        #[cfg(unix)]
+        let hangup = match self.sighup_reload {
+            true => Some(tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?),
+            false => None,
+        };
+        #[cfg(feature = "http3")]
+        let h3_endpoints = match (&self.http3, &tls) {
+            (Some(_), Some((config, _))) => {
+                let addrs: Vec<_> = listeners
+                    .iter()
+                    .filter(|bound| bound.role == ListenerRole::Https)
+                    .filter_map(|bound| match &bound.listener {
+                        Listener::Tcp(listener) => listener.local_addr().ok(),
+                        #[cfg(unix)]
+                        Listener::Unix(..) => None,
+                    })
+                    .collect();
+                let endpoints = http3::bind(&addrs, config, self.idle_timeout)?;
+                for endpoint in &endpoints {
+                    log::info!("listening on {}, serve http3", endpoint.local_addr()?);
+                }
+                endpoints
+            }
+            (Some(_), None) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "http3 requires tls")),
+            (None, _) => Vec::new(),
+        };
+        let settings = RuntimeSettings::new(self.idle_timeout);
+        let (tls, tls_reload) = match tls {
+            Some((config, param)) => {
+                let (config_tx, _) = broadcast::channel::<Arc<ServerConfig>>(1);
+                let tls_reload = TlsReload {
+                    param,
+                    sni_certificates: self.sni_certificates.clone(),
+                    protocol: self.protocol,
+                    config_tx: config_tx.clone(),
+                };
+                (Some((config, config_tx)), Some(tls_reload))
+            }
+            None => (None, None),
+        };
+        let reloader = Arc::new(Reloader::new(self.reload_hooks.clone(), settings.clone(), tls_reload));
+        let prepared = Prepared {
+            listeners,
+            inherited,
+            tls,
+            settings,
+            reloader: reloader.clone(),
+            #[cfg(unix)]
+            hangup,
+            #[cfg(feature = "http3")]
+            h3_endpoints,
+        };
+        Ok((local_addrs, reloader, self.serve(prepared, handle_rx)))
+    }
+
+    /// 运行所有监听器直到关闭
+    ///
+    /// # 参数
+    /// - `prepared`: 已绑定的监听器和加载好的 TLS 配置
+    /// - `handle_rx`: [`ServerHandle`] 发出的关闭请求
+    ///
+    /// # 返回
+    /// - `Ok(())`: 正常关闭
+    /// - `Err(std::io::Error)`: 运行过程中出现错误
+    async fn serve(self, prepared: Prepared, handle_rx: watch::Receiver<Option<Duration>>) -> Result<(), std::io::Error> {
+        let Prepared {
+            listeners,
+            inherited,
+            tls,
+            settings,
+            reloader,
+            #[cfg(unix)]
+            hangup,
+            #[cfg(feature = "http3")]
+            h3_endpoints,
+        } = prepared;
+        let (shutdown_signal_tx, shutdown) = ShutdownSignal::new();
+        let ctx = Arc::new(ServeContext {
+            app: self.router.clone(),
+            server: self.protocol.builder(self.timeouts.header_read),
+            versions: self.protocol.versions,
+            interceptor: self.interceptor.clone(),
+            drop_log_level: self.drop_log_level,
+            settings,
+            timeouts: self.timeouts,
+            hsts: self.hsts.as_ref().map(HstsParam::header_value),
+            limiter: ConnectionLimiter::new(self.connection_limit, self.connection_stats.clone()),
+            connection_hook: self.connection_hook.clone(),
+            shutdown,
+        });
+        let graceful: hyper_util::server::graceful::GracefulShutdown = hyper_util::server::graceful::GracefulShutdown::new();
+        // 关闭信号和热重启共用的停止信号，所有 accept 循环订阅同一个发送器
+        let (stop_tx, _) = broadcast::channel::<()>(1);
+        reload::spawn(
+            reloader,
+            #[cfg(unix)]
+            hangup,
+            stop_tx.subscribe(),
+        );
+        #[cfg(unix)]
+        let hot_restart = match self.hot_restart {
+            Some(param) => {
+                let http_role = self.http_role(tls.is_some())?;
+                let named: Vec<_> = listeners
+                    .iter()
+                    .map(|bound| {
+                        let name = if Some(bound.role) == http_role {
+                            HOT_RESTART_HTTP_NAME
+                        } else {
+                            HOT_RESTART_MAIN_NAME
+                        };
+                        (&bound.listener, name)
+                    })
+                    .collect();
+                Some(hot_restart::spawn(&named, param.ready_timeout, stop_tx.clone())?)
+            }
+            None => None,
+        };
+        #[cfg(unix)]
+        if hot_restart.is_some() {
+            hot_restart::notify_ready();
+        }
+        // 启用 HTTP/3 时 HTTPS 响应通过 Alt-Svc 声明同一端口的 HTTP/3 服务
+        #[cfg(feature = "http3")]
+        let alt_svc = |bound: &BoundListener| match (&bound.listener, self.http3) {
+            (Listener::Tcp(listener), Some(param)) => listener.local_addr().ok().map(|addr| http3::alt_svc(addr.port(), param.alt_svc_max_age)),
+            _ => None,
+        };
+        #[cfg(not(feature = "http3"))]
+        let alt_svc = |_: &BoundListener| None;
+        let serve = futures_util::future::try_join_all(listeners.into_iter().map(|bound| match (&bound.role, &tls) {
+            (ListenerRole::Https, Some((config, config_tx))) => {
+                let alt_svc = alt_svc(&bound);
+                Either::Left(serve_tls(&ctx, &graceful, bound, TlsAcceptor::new(config.clone()), alt_svc, config_tx.subscribe(), stop_tx.subscribe()))
+            }
+            _ => Either::Right(serve_plantext(&ctx, &graceful, bound, stop_tx.subscribe())),
+        }));
+        #[cfg(feature = "http3")]
+        let serve = futures_util::future::try_join(
+            serve,
+            futures_util::future::try_join_all(h3_endpoints.iter().filter_map(|endpoint| {
+                let (_, config_tx) = tls.as_ref()?;
+                let alt_svc = match (endpoint.local_addr(), self.http3) {
+                    (Ok(addr), Some(param)) => Some(http3::alt_svc(addr.port(), param.alt_svc_max_age)),
+                    _ => None,
+                };
+                Some(http3::serve(&ctx, &graceful, endpoint.clone(), alt_svc, config_tx.subscribe(), stop_tx.subscribe()))
+            })),
+        );
+        let mut shutdown_rx = self.shutdown_rx;
+        let shutdown_forwarder = {
+            let mut handle_rx = handle_rx.clone();
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = shutdown_rx.recv() => {}
+                    Ok(_) = handle_rx.wait_for(Option::is_some) => {}
+                }
+                let _ = stop_tx.send(());
+            })
+        };
+        let result = serve.await;
+        shutdown_forwarder.abort();
+        // 通知升级后的连接关闭，它们不受优雅关闭管理
+        shutdown_signal_tx.send_replace(true);
+        // 停止热重启任务，关闭复制的监听 socket
+        #[cfg(unix)]
+        let handed_over = match hot_restart {
+            Some(task) => {
+                task.abort();
+                matches!(task.await, Ok(true))
+            }
+            None => false,
+        };
+        let graceful_timeout = handle_rx.borrow().unwrap_or(GRACEFUL_SHUTDOWN_TIMEOUT);
+        match tokio::time::timeout(graceful_timeout, graceful.shutdown()).await {
+            Ok(_) => info!("Gracefully shutdown!"),
+            Err(_) => info!("Waited {graceful_timeout:?} for graceful shutdown, aborting..."),
+        }
+        // 继承自 systemd 或已交给新进程的 Unix domain socket 文件不能删除
+        #[cfg(unix)]
+        for param in self.unix_sockets.iter().filter(|_| !inherited && !handed_over) {
+            if let Err(e) = std::fs::remove_file(&param.path) {
+                warn!("remove unix socket {} error: {e}", param.path.display());
+            }
+        }
+        result.map(|_| ())
+    }
+
+    /// 绑定所有监听器
+    ///
+    /// 启用热重启且当前进程由旧进程热重启启动时，使用旧进程交接的监听 socket；
+    /// 启用 systemd socket activation 且当前进程收到了继承的监听 socket 时，直接使用继承的 socket
+    ///
+    /// # 参数
+    /// - `use_tls`: 是否启用 TLS，决定 `addrs` 和 Unix domain socket 监听器的用途
+    ///
+    /// # 返回
+    /// - `Ok((Vec<BoundListener>, bool))`: 监听器及其配置，以及监听器是否继承自 systemd
+    /// - `Err(std::io::Error)`: 没有配置任何监听、配置冲突或绑定失败
+    async fn bind(&self, use_tls: bool) -> Result<(Vec<BoundListener>, bool), std::io::Error> {
+        let role = if use_tls { ListenerRole::Https } else { ListenerRole::Http };
+        let http_role = self.http_role(use_tls)?;
+        #[cfg(unix)]
+        if self.hot_restart.is_some() {
+            if let Some(inherited) = hot_restart::inherited_listeners()? {
+                info!("use {} listeners from hot restart", inherited.len());
+                return Ok((self.adopt(inherited, HOT_RESTART_HTTP_NAME, role, http_role)?, false));
+            }
+        }
+        #[cfg(unix)]
+        if self.systemd_socket_activation {
+            match io::systemd_listeners()? {
+                Some(inherited) => {
+                    info!("use {} listeners from systemd socket activation", inherited.len());
+                    return Ok((self.adopt(inherited, SYSTEMD_HTTP_FD_NAME, role, http_role)?, true));
+                }
+                None => info!("not started by systemd socket activation, bind configured addresses"),
+            }
+        }
+        let mut listeners = Vec::with_capacity(self.addrs.len());
+        for addr in &self.addrs {
+            let proxy_protocol = self.proxy_protocol.get(addr).copied();
+            listeners.extend(self.bind_tcp(*addr).await?.into_iter().map(|listener| BoundListener {
+                listener,
+                role,
+                proxy_protocol,
+            }));
+        }
+        #[cfg(unix)]
+        for param in &self.unix_sockets {
+            let listener = io::create_unix_listener(&param.path, param.mode)?;
+            listeners.push(BoundListener {
+                listener: Listener::Unix(listener, param.path.clone()),
+                role,
+                proxy_protocol: param.proxy_protocol,
+            });
+        }
+        if let (Some(http_param), Some(http_role)) = (&self.http_param, http_role) {
+            for addr in &http_param.addrs {
+                let proxy_protocol = self.proxy_protocol.get(addr).copied();
+                listeners.extend(self.bind_tcp(*addr).await?.into_iter().map(|listener| BoundListener {
+                    listener,
+                    role: http_role,
+                    proxy_protocol,
+                }));
+            }
+        }
+        if listeners.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "no listen address configured"));
+        }
+        Ok((listeners, false))
+    }
+
+    /// 使用继承的监听器
+    ///
+    /// 名称为 `http_name` 的监听器在配置了 [`HttpParam`] 时按明文 HTTP 处理，
+    /// PROXY protocol 模式按监听器的本地地址匹配配置
+    ///
+    /// # 参数
+    /// - `inherited`: 继承的监听器及其名称
+    /// - `http_name`: 明文 HTTP 监听器的名称
+    /// - `role`: 其它监听器的用途
+    /// - `http_role`: 明文 HTTP 监听器的用途
+    ///
+    /// # 返回
+    /// - `Ok(Vec<BoundListener>)`: 监听器及其配置
+    /// - `Err(std::io::Error)`: 没有继承任何监听器
+    #[cfg(unix)]
+    fn adopt(
+        &self, inherited: Vec<(Listener, String)>, http_name: &str, role: ListenerRole, http_role: Option<ListenerRole>,
+    ) -> Result<Vec<BoundListener>, std::io::Error> {
+        if inherited.is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "no inherited listener"));
+        }
+        Ok(inherited
+            .into_iter()
+            .map(|(listener, name)| {
+                let proxy_protocol = match &listener {
+                    Listener::Tcp(tcp) => tcp.local_addr().ok().and_then(|addr| self.proxy_protocol.get(&addr).copied()),
+                    Listener::Unix(_, path) => self
+                        .unix_sockets
+                        .iter()
+                        .find(|param| &param.path == path)
+                        .and_then(|param| param.proxy_protocol),
+                };
+                let role = match http_role {
+                    Some(http_role) if name == http_name => http_role,
+                    _ => role,
+                };
+                BoundListener {
+                    listener,
+                    role,
+                    proxy_protocol,
+                }
+            })
+            .collect())
+    }
+
+    /// 绑定一个 TCP 监听地址
+    ///
+    /// 启用 `SO_REUSEPORT` 多 acceptor 模式时返回多个绑定到同一地址的监听器
+    ///
+    /// # 参数
+    /// - `addr`: 监听地址
+    ///
+    /// # 返回
+    /// - `Ok(Vec<Listener>)`: 绑定成功的监听器
+    /// - `Err(std::io::Error)`: 绑定失败
+    async fn bind_tcp(&self, addr: SocketAddr) -> Result<Vec<Listener>, std::io::Error> {
+        #[cfg(unix)]
+        if self.reuse_port_acceptors > 1 {
+            let listeners = io::create_reuse_port_listeners(addr, self.reuse_port_acceptors, self.backlog)?;
+            return Ok(listeners.into_iter().map(Listener::Tcp).collect());
+        }
+        Ok(vec![Listener::Tcp(create_listener_with_backlog(addr, self.backlog).await?)])
+    }
+
+    /// 明文 HTTP 监听器的用途
+    ///
+    /// # 参数
+    /// - `use_tls`: 是否启用 TLS
+    ///
+    /// # 返回
+    /// - `Ok(Some(ListenerRole))`: 配置了 [`HttpParam`] 时的用途
+    /// - `Ok(None)`: 没有配置 [`HttpParam`]
+    /// - `Err(std::io::Error)`: 配置了重定向到 HTTPS 但没有启用 TLS
+    fn http_role(&self, use_tls: bool) -> Result<Option<ListenerRole>, std::io::Error> {
+        match self.http_param.as_ref().map(|http_param| http_param.redirect) {
+            None => Ok(None),
+            Some(None) => Ok(Some(ListenerRole::Http)),
+            Some(Some(_)) if !use_tls => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "redirect to https requires tls")),
+            Some(Some(redirect)) => Ok(Some(ListenerRole::RedirectToHttps(redirect))),
+        }
+    }
+}
+
+/// 后台运行的服务器句柄
+///
+/// 由 [`Server::spawn`] 返回。句柄本身是一个 future，在服务器关闭后完成并返回运行结果；
+/// 丢弃句柄不会停止服务器
+///
+/// # 字段
+/// - `local_addrs`: 实际绑定的 TCP 地址
+/// - `shutdown_tx`: 关闭请求发送器，值为优雅关闭的等待时间
+/// - `stats`: 连接统计
+/// - `reloader`: 重新加载器
+/// - `task`: 运行服务器的任务
+pub struct ServerHandle {
+    local_addrs: Vec<SocketAddr>,
+    shutdown_tx: watch::Sender<Option<Duration>>,
+    stats: ConnectionStats,
+    reloader: Arc<Reloader>,
+    task: tokio::task::JoinHandle<Result<(), std::io::Error>>,
+}
+
+impl ServerHandle {
+    /// 实际绑定的 TCP 地址
+    ///
+    /// 端口为 0 时返回系统分配的端口，Unix domain socket 不包含在内
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
+    /// 当前打开的连接数
+    pub fn open_connections(&self) -> usize {
+        self.stats.open_connections()
+    }
+
+    /// 连接统计
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.stats.clone()
+    }
+
+    /// 重新加载
+    ///
+    /// 与收到 `SIGHUP` 时相同：调用所有重新加载钩子，然后重新读取 TLS 证书，已有连接不受影响
+    ///
+    /// # 返回
+    /// - `Ok(())`: 所有步骤都成功
+    /// - `Err(std::io::Error)`: 失败步骤的错误，这些步骤保留原来的配置，其余步骤照常生效
+    pub async fn reload(&self) -> Result<(), std::io::Error> {
+        self.reloader.reload().await
+    }
+
+    /// 停止 accept 并优雅关闭，最多等待 10 秒
+    ///
+    /// 只发出关闭请求，等待关闭完成需要 await 句柄
+    pub fn shutdown(&self) {
+        self.shutdown_with_timeout(GRACEFUL_SHUTDOWN_TIMEOUT);
+    }
+
+    /// 停止 accept 并优雅关闭，最多等待 `timeout`
+    ///
+    /// # 参数
+    /// - `timeout`: 等待已有连接关闭的最长时间，超时后不再等待
+    pub fn shutdown_with_timeout(&self, timeout: Duration) {
+        self.shutdown_tx.send_replace(Some(timeout));
+    }
+}
+
+#[cfg(test)]
+impl ServerHandle {
+    /// 关闭测试服务器，最多等待 1 秒，并等待服务器退出
+    pub(crate) async fn stop(self) {
+        self.shutdown_with_timeout(Duration::from_secs(1));
+        self.await.unwrap();
+    }
+}
+
+/// 在 `127.0.0.1` 的随机端口上后台启动测试服务器
+///
+/// # 返回
+/// 服务器句柄和实际绑定的地址
+#[cfg(test)]
+pub(crate) async fn spawn_loopback<I>(server: Server<I>) -> (ServerHandle, SocketAddr)
+where
+    I: ReqInterceptor + Clone + Send + Sync + 'static,
+{
+    let handle = server.with_addrs(vec![SocketAddr::from(([127, 0, 0, 1], 0))]).spawn().await.unwrap();
+    let addr = handle.local_addrs()[0];
+    (handle, addr)
+}
+
+impl Future for ServerHandle {
+    type Output = Result<(), std::io::Error>;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> std::task::Poll<Self::Output> {
+        std::pin::Pin::new(&mut self.task)
+            .poll(cx)
+            .map(|result| result.unwrap_or_else(|e| Err(std::io::Error::other(e))))
+    }
+}
+
+/// 已绑定的监听器和加载好的 TLS 配置
+///
+/// # 字段
+/// - `listeners`: 已绑定的监听器
+/// - `inherited`: 监听器是否继承自 systemd
+/// - `tls`: 初始 TLS 配置及新配置的广播发送器 (可选)
+/// - `settings`: 运行中可以修改的参数
+/// - `reloader`: 重新加载器
+/// - `hangup`: 已注册的 `SIGHUP` 信号 (可选，仅 Unix 平台)
+/// - `h3_endpoints`: HTTP/3 的 QUIC 端点 (需要 `http3` feature)
+struct Prepared {
+    listeners: Vec<BoundListener>,
+    inherited: bool,
+    tls: Option<(Arc<ServerConfig>, broadcast::Sender<Arc<ServerConfig>>)>,
+    settings: RuntimeSettings,
+    reloader: Arc<Reloader>,
+    #[cfg(unix)]
+    hangup: Option<tokio::signal::unix::Signal>,
+    #[cfg(feature = "http3")]
+    h3_endpoints: Vec<quinn::Endpoint>,
+}
+
+/// 监听器的用途
+///
+/// # 变体
+/// - `Http`: 使用路由处理明文 HTTP 请求
+/// - `Https`: 使用路由处理 HTTPS 请求
+/// - `RedirectToHttps`: 将明文 HTTP 请求重定向到 HTTPS，不经过拦截器和路由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListenerRole {
+    Http,
+    Https,
+    RedirectToHttps(HttpRedirectParam),
+}
+
+impl std::fmt::Display for ListenerRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenerRole::Http => write!(f, "http"),
+            ListenerRole::Https => write!(f, "https"),
+            ListenerRole::RedirectToHttps(_) => write!(f, "http redirect to https"),
+        }
+    }
+}
+
+/// 已绑定的监听器
+///
+/// # 字段
+/// - `listener`: 监听器
+/// - `role`: 监听器用途
+/// - `proxy_protocol`: PROXY protocol 模式，为 None 时不解析
+struct BoundListener {
+    listener: Listener,
+    role: ListenerRole,
+    proxy_protocol: Option<ProxyProtocolMode>,
+}
+
+impl std::fmt::Display for BoundListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, serve {}", self.listener, self.role)?;
+        match self.proxy_protocol {
+            Some(ProxyProtocolMode::Required) => write!(f, ", proxy protocol required"),
+            Some(ProxyProtocolMode::Optional) => write!(f, ", proxy protocol optional"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 连接处理上下文
+///
+/// 在所有监听器的 accept 循环和连接任务之间共享
+///
+/// # 字段
+/// - `app`: Axum 路由
+/// - `server`: Hyper 服务器构建器
+/// - `versions`: 允许的 HTTP 版本
+/// - `interceptor`: 可选的请求拦截器
+/// - `drop_log_level`: 拦截器丢弃请求时的日志级别
+/// - `settings`: 运行中可以修改的参数，包括连接空闲超时时间
+/// - `timeouts`: TLS 握手、请求头读取和连接存活时间的超时参数
+/// - `hsts`: HTTPS 响应附加的 `Strict-Transport-Security` 头 (可选)
+/// - `limiter`: 连接数限制器
+/// - `connection_hook`: 连接级别的钩子 (可选)
+/// - `shutdown`: 停止接受新连接后触发的关闭信号，插入每个请求的 extensions
+#[derive(Clone)]
+struct ServeContext<I> {
+    app: Router,
+    server: hyper_util::server::conn::auto::Builder<TokioExecutor>,
+    versions: HttpVersions,
+    interceptor: Option<I>,
+    drop_log_level: log::LevelFilter,
+    settings: RuntimeSettings,
+    timeouts: TimeoutParam,
+    hsts: Option<HeaderValue>,
+    limiter: ConnectionLimiter,
+    connection_hook: Option<Arc<dyn ConnectionHook>>,
+    shutdown: ShutdownSignal,
+}
+
+impl<I> ServeContext<I> {
+    /// 调用连接钩子决定是否接受连接
     ///
     /// # 参数
-    /// - `timeout`: 超时时长
+    /// - `peer_addr`: 对端描述
+    /// - `local_addr`: 本地地址
     ///
     /// # 返回
-    /// 返回配置了超时的服务器实例
-    pub fn with_timeout(mut self, timeout: Duration) -> Self {
-        self.idle_timeout = timeout;
-        self
+    /// 没有配置钩子或钩子接受连接时返回 true
+    fn accept_connection(&self, peer_addr: &PeerAddr, local_addr: Option<SocketAddr>) -> bool {
+        let Some(hook) = &self.connection_hook else {
+            return true;
+        };
+        match hook.on_connect(peer_addr, local_addr) {
+            ConnectAction::Accept => true,
+            ConnectAction::Drop => {
+                if let Some(level) = self.drop_log_level.to_level() {
+                    log::log!(level, "[connection hook]: dropped connection from {peer_addr}");
+                }
+                false
+            }
+        }
     }
 
-    /// 启动服务器
-    ///
-    /// 根据 TLS 配置启动 HTTP 或 HTTPS 服务器，并监听关闭信号
+    /// 调用连接钩子通知连接关闭
     ///
-    /// # 返回
-    /// - `Ok(())`: 服务器成功启动并正常关闭
-    /// - `Err(std::io::Error)`: 启动或运行过程中出现 I/O 错误
-    ///
-    /// # 错误
-    /// - 端口绑定失败
-    /// - TLS 证书加载失败
-    /// - 网络 I/O 错误
-    pub async fn run(mut self) -> Result<(), std::io::Error> {
-        let use_tls = match self.tls_param.clone() {
-            Some(config) => config.tls,
-            None => false,
-        };
-        log::info!("listening on port {}, use_tls: {}", self.port, use_tls);
-        let server: hyper_util::server::conn::auto::Builder<TokioExecutor> = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
-        let graceful: hyper_util::server::graceful::GracefulShutdown = hyper_util::server::graceful::GracefulShutdown::new();
-        match use_tls {
-            #[allow(clippy::expect_used)]
-            true => {
-                serve_tls(
-                    &self.router,
-                    server,
-                    graceful,
-                    self.port,
-                    self.tls_param.as_ref().expect("should be some"),
-                    self.interceptor.clone(),
-                    self.idle_timeout,
-                    &mut self.shutdown_rx,
-                )
-                .await?
-            }
-            false => {
-                serve_plantext(&self.router, server, graceful, self.port, self.interceptor.clone(), self.idle_timeout, &mut self.shutdown_rx).await?
-            }
+    /// # 参数
+    /// - `connection`: 连接信息
+    /// - `accepted_at`: accept 连接的时间
+    /// - `bytes_read`: 从连接读取的字节数
+    /// - `bytes_written`: 向连接写入的字节数
+    fn disconnect(&self, connection: ConnectionInfo, accepted_at: std::time::Instant, bytes_read: u64, bytes_written: u64) {
+        if let Some(hook) = &self.connection_hook {
+            hook.on_disconnect(&DisconnectInfo {
+                connection,
+                duration: accepted_at.elapsed(),
+                bytes_read,
+                bytes_written,
+            });
         }
-        Ok(())
     }
 }
 
 /// 处理单个 HTTP 请求
 ///
-/// 如果配置了拦截器，会先调用拦截器处理请求，否则直接路由到应用
+/// 如果配置了拦截器，会先调用拦截器处理请求，并在得到响应后调用拦截器的 `on_response`，否则直接路由到应用
 ///
 /// # 参数
 /// - `request`: HTTP 请求
-/// - `client_socket_addr`: 客户端地址
+/// - `info`: 连接信息，与其中的对端描述 (`ConnectInfo<PeerAddr>`) 一起插入请求 extensions
+/// - `role`: 接受该连接的监听器用途
 /// - `app`: Axum 应用实例
 /// - `interceptor`: 可选的请求拦截器
+/// - `hsts`: HTTPS 响应附加的 `Strict-Transport-Security` 头 (可选)
+/// - `alt_svc`: 响应附加的 `Alt-Svc` 头 (可选)
 ///
 /// # 返回
 /// - `Ok(Response)`: 成功生成的 HTTP 响应
 /// - `Err(std::io::Error)`: 处理过程中的 I/O 错误
 async fn handle<I>(
-    request: Request<Incoming>, client_socket_addr: SocketAddr, app: axum::middleware::AddExtension<Router, axum::extract::ConnectInfo<SocketAddr>>,
-    interceptor: Option<I>,
+    mut request: Request, info: ConnectionInfo, role: ListenerRole,
+    app: axum::middleware::AddExtension<Router, axum::extract::ConnectInfo<SocketAddr>>, interceptor: Option<I>, hsts: Option<HeaderValue>,
+    alt_svc: Option<HeaderValue>,
 ) -> std::result::Result<Response, std::io::Error>
 where
     I: ReqInterceptor + Clone + Send + Sync + 'static,
 {
-    if let Some(interceptor) = interceptor {
-        match interceptor.intercept(request, client_socket_addr).await {
-            InterceptResult::Return(res) => Ok(res),
-            InterceptResult::Drop => Err(std::io::Error::other("Request dropped by interceptor")),
+    if let ListenerRole::RedirectToHttps(param) = role {
+        return Ok(https_redirect(&request, param));
+    }
+    let client_socket_addr = info.peer_addr.socket_addr();
+    request.extensions_mut().insert(ConnectInfo(info.peer_addr.clone()));
+    let mut response = if let Some(interceptor) = interceptor {
+        let ctx = ResponseContext::new(&request, info.clone());
+        request.extensions_mut().insert(info);
+        let response = match interceptor.intercept(request, client_socket_addr).await {
+            InterceptResult::Return(res) => res,
+            InterceptResult::Drop => return Err(std::io::Error::other(InterceptorDrop)),
             InterceptResult::Continue(req) => app
                 .oneshot(req)
                 .await
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Interrupted, err)),
-            InterceptResult::Error(err) => {
-                let res = err.into_response();
-                Ok(res)
-            }
-        }
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Interrupted, err))?,
+            InterceptResult::Error(err) => err.into_response(),
+        };
+        interceptor.on_response(response, &ctx).await
     } else {
+        request.extensions_mut().insert(info);
         app.oneshot(request)
             .await
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Interrupted, err))
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Interrupted, err))?
+    };
+    if let (ListenerRole::Https, Some(hsts)) = (role, hsts) {
+        response.headers_mut().entry(header::STRICT_TRANSPORT_SECURITY).or_insert(hsts);
+    }
+    if let Some(alt_svc) = alt_svc {
+        response.headers_mut().entry(header::ALT_SVC).or_insert(alt_svc);
+    }
+    Ok(response)
+}
+
+/// 生成重定向到 HTTPS 的响应
+///
+/// # 参数
+/// - `request`: 明文 HTTP 请求
+/// - `param`: 重定向参数
+///
+/// # 返回
+/// - 重定向响应，`Location` 为同一 Host 和路径的 HTTPS 地址
+/// - 请求中没有 Host 信息时返回 400
+fn https_redirect(request: &Request, param: HttpRedirectParam) -> Response {
+    match https_redirect_location(request.headers(), request.uri(), param.https_port).and_then(|location| HeaderValue::try_from(location).ok()) {
+        Some(location) => (param.status, [(header::LOCATION, location)]).into_response(),
+        None => (hyper::StatusCode::BAD_REQUEST, "Missing Host information in request").into_response(),
     }
 }
 
+/// 计算重定向到 HTTPS 的目标地址
+///
+/// # 参数
+/// - `headers`: 请求头
+/// - `uri`: 请求 URI
+/// - `https_port`: HTTPS 端口，为 None 或 443 时不在地址中携带端口
+///
+/// # 返回
+/// - `Some(String)`: 重定向目标，例如 `https://example.com:8443/path?query`
+/// - `None`: 请求中没有 Host 信息
+fn https_redirect_location(headers: &hyper::HeaderMap, uri: &hyper::Uri, https_port: Option<u16>) -> Option<String> {
+    let host = util::extractor::request_host(headers, uri)?;
+    // 去掉明文 HTTP 的端口，IPv6 地址保留方括号
+    let hostname = match host.strip_prefix('[') {
+        Some(rest) => &host[..rest.find(']')? + 2],
+        None => host.rsplit_once(':').map_or(host, |(hostname, _)| hostname),
+    };
+    let path_and_query = uri.path_and_query().map_or("/", |path_and_query| path_and_query.as_str());
+    Some(match https_port {
+        Some(port) if port != 443 => format!("https://{hostname}:{port}{path_and_query}"),
+        _ => format!("https://{hostname}{path_and_query}"),
+    })
+}
+
 /// 处理单个连接
 ///
-/// 为每个连接创建超时包装器和 Hyper 服务，并在新的 tokio 任务中处理
+/// 调用连接钩子过滤连接 (启用 PROXY protocol 时在读取头部之后)，然后在新的 tokio 任务中
+/// 依次读取 PROXY protocol 头 (如果启用)、检查单 IP 连接数、完成 TLS 握手 (如果启用) 并处理 HTTP，
+/// 不会阻塞 accept 循环。被接受的连接关闭时通知连接钩子
+///
+/// # 参数
+/// - `accepted`: 刚接受的连接、对端描述和连接占用的限额。启用 PROXY protocol 时对端描述会被头部中的源地址替换，
+///   限额在连接任务结束时释放
+/// - `role`: 接受该连接的监听器用途
+/// - `proxy_protocol`: PROXY protocol 模式，为 None 时不解析
+/// - `tls`: TLS 接受器，为 None 时按明文处理
+/// - `alt_svc`: 响应附加的 `Alt-Svc` 头 (可选)
+/// - `ctx`: 连接处理上下文
+/// - `graceful`: 优雅关闭句柄
+fn handle_connection<I>(
+    accepted: (Stream, PeerAddr, ConnectionGuard), role: ListenerRole, proxy_protocol: Option<ProxyProtocolMode>, tls: Option<TlsAcceptor>,
+    alt_svc: Option<HeaderValue>, ctx: &Arc<ServeContext<I>>, graceful: &hyper_util::server::graceful::GracefulShutdown,
+) where
+    I: ReqInterceptor + Clone + Send + Sync + 'static,
+{
+    let ctx = ctx.clone();
+    // 在 accept 循环中取得 watcher，保证优雅关闭会等待尚未开始处理 HTTP 的连接；
+    // watcher 在处理 HTTP 时交给 hyper，另一个 watcher 由连接任务持有，优雅关闭同样等待 on_disconnect 回调
+    let watcher = graceful.watcher();
+    let task_watcher = graceful.watcher();
+    let (conn, peer_addr, mut guard) = accepted;
+    let accepted_at = std::time::Instant::now();
+    let local_addr = conn.local_addr();
+    let (conn, reset) = io::ResetOnDrop::new(conn);
+    if proxy_protocol.is_none() && !ctx.accept_connection(&peer_addr, local_addr) {
+        reset.store(true, std::sync::atomic::Ordering::Relaxed);
+        return;
+    }
+    tokio::spawn(async move {
+        let _task_watcher = task_watcher;
+        let (conn, bytes) = io::CountingIO::new(conn);
+        let (conn, peer_addr, local_addr) = match proxy_protocol {
+            Some(mode) => match tokio::time::timeout(ctx.timeouts.proxy_header, read_proxy_header(conn, mode)).await {
+                Ok(Ok((conn, header))) => match header.and_then(|header| header.source.map(|source| (source, header.destination))) {
+                    Some((source, destination)) => {
+                        log::debug!("proxy protocol: {} via {peer_addr}", util::format::SocketAddrFormat(&source));
+                        (conn, PeerAddr::Tcp(source), destination.or(local_addr))
+                    }
+                    None => (conn, peer_addr, local_addr),
+                },
+                Ok(Err(e)) => {
+                    info!("[proxy protocol]: {e} from {peer_addr}");
+                    return;
+                }
+                Err(_) => {
+                    info!("[proxy protocol]: no header in {:?} from {peer_addr}", ctx.timeouts.proxy_header);
+                    return;
+                }
+            },
+            None => (Rewind::new(conn, Vec::new()), peer_addr, local_addr),
+        };
+        if proxy_protocol.is_some() && !ctx.accept_connection(&peer_addr, local_addr) {
+            reset.store(true, std::sync::atomic::Ordering::Relaxed);
+            return;
+        }
+        let mut info = ConnectionInfo::new(local_addr, peer_addr);
+        if guard.acquire_ip(&info.peer_addr) {
+            match tls {
+                Some(acceptor) => {
+                    let mut conn = acceptor.accept(conn);
+                    match tokio::time::timeout(ctx.timeouts.tls_handshake, conn.handshake()).await {
+                        Ok(Ok(())) => {
+                            info.tls = conn.connection().map(|conn| Arc::new(TlsInfo::from_connection(conn)));
+                            serve_connection(conn, info.clone(), role, alt_svc, &ctx, watcher, reset).await
+                        }
+                        Ok(Err(e)) => warn!("[tls]: {e} from {}", info.peer_addr),
+                        Err(_) => info!("[tls handshake timeout]: not completed in {:?} from {}", ctx.timeouts.tls_handshake, info.peer_addr),
+                    }
+                }
+                None => serve_connection(conn, info.clone(), role, alt_svc, &ctx, watcher, reset).await,
+            }
+        }
+        ctx.disconnect(info, accepted_at, bytes.read(), bytes.written());
+    });
+}
+
+/// 在连接上处理 HTTP 请求
+///
+/// 为连接创建超时包装器和 Hyper 服务，直到连接关闭或超过最长存活时间
 ///
 /// # 类型参数
 /// - `C`: 连接类型，必须实现 AsyncRead + AsyncWrite
@@ -351,51 +2262,159 @@ where
 ///
 /// # 参数
 /// - `conn`: 网络连接
-/// - `client_socket_addr`: 客户端地址
-/// - `app`: Axum 路由
-/// - `server`: Hyper 服务器构建器
-/// - `interceptor`: 可选的请求拦截器
-/// - `graceful`: 优雅关闭句柄
-/// - `timeout`: 连接空闲超时时间
-async fn handle_connection<C, I>(
-    conn: C, client_socket_addr: std::net::SocketAddr, app: Router, server: hyper_util::server::conn::auto::Builder<TokioExecutor>,
-    interceptor: Option<I>, graceful: &hyper_util::server::graceful::GracefulShutdown, timeout: Duration,
+/// - `info`: 连接信息
+/// - `role`: 接受该连接的监听器用途
+/// - `alt_svc`: 响应附加的 `Alt-Svc` 头 (可选)
+/// - `ctx`: 连接处理上下文
+/// - `watcher`: 优雅关闭观察者
+/// - `reset`: 设置为 true 后连接以 TCP RST 关闭，拦截器丢弃 HTTP/1 请求时设置
+async fn serve_connection<C, I>(
+    conn: C, info: ConnectionInfo, role: ListenerRole, alt_svc: Option<HeaderValue>, ctx: &ServeContext<I>,
+    watcher: hyper_util::server::graceful::Watcher, reset: Arc<std::sync::atomic::AtomicBool>,
 ) where
     C: tokio::io::AsyncRead + tokio::io::AsyncWrite + 'static + Send + Sync,
     I: ReqInterceptor + Clone + Send + Sync + 'static,
 {
-    let timeout_io = Box::pin(io::TimeoutIO::new(conn, timeout));
+    let idle_timeout = ctx.settings.idle_timeout();
+    let timeout_io = Box::pin(io::TimeoutIO::new(conn, idle_timeout));
+    let idle_timed_out = timeout_io.timed_out();
+    let peer_addr = info.peer_addr.clone();
     use hyper::Request;
     use hyper_util::rt::TokioIo;
-    let stream = TokioIo::new(timeout_io);
-    let mut app = app.into_make_service_with_connect_info::<SocketAddr>();
-    let app: axum::middleware::AddExtension<Router, axum::extract::ConnectInfo<SocketAddr>> = unwrap_infallible(app.call(client_socket_addr).await);
+    let stream = match ctx.versions {
+        HttpVersions::Http1Only => match reject_http2_preface(timeout_io).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::debug!("[hyper]: {e} from {peer_addr}");
+                return;
+            }
+        },
+        _ => Rewind::new(timeout_io, Vec::new()),
+    };
+    let stream = TokioIo::new(stream);
+    let mut app = ctx.app.clone().into_make_service_with_connect_info::<SocketAddr>();
+    // Unix domain socket 连接没有对端地址，ConnectInfo<SocketAddr> 使用占位地址
+    let app: axum::middleware::AddExtension<Router, axum::extract::ConnectInfo<SocketAddr>> =
+        unwrap_infallible(app.call(peer_addr.socket_addr()).await);
     // https://github.com/tokio-rs/axum/blob/main/examples/serve-with-hyper/src/main.rs#L81
-    let hyper_service = hyper::service::service_fn(move |request: Request<hyper::body::Incoming>| {
-        handle(request, client_socket_addr, app.clone(), interceptor.clone())
+    let interceptor = ctx.interceptor.clone();
+    let hsts = ctx.hsts.clone();
+    let drop_log_level = ctx.drop_log_level;
+    let service_reset = reset.clone();
+    let shutdown = ctx.shutdown.clone();
+    let hyper_service = hyper::service::service_fn(move |mut request: Request<hyper::body::Incoming>| {
+        request.extensions_mut().insert(shutdown.clone());
+        let version = request.version();
+        let method = request.method().clone();
+        let uri = request.uri().clone();
+        let info = info.clone();
+        let reset = service_reset.clone();
+        let response =
+            handle(request.map(axum::body::Body::new), info.clone(), role, app.clone(), interceptor.clone(), hsts.clone(), alt_svc.clone());
+        async move {
+            let result = response.await;
+            if let Err(e) = &result {
+                if InterceptorDrop::is(e) {
+                    if let Some(level) = drop_log_level.to_level() {
+                        log::log!(level, "[interceptor]: dropped {method} {uri} from {}", info.peer_addr);
+                    }
+                    // HTTP/1 由 hyper 关闭连接，关闭时发送 RST；HTTP/2 由 hyper 重置该请求流，连接上的其他请求不受影响
+                    if version < hyper::Version::HTTP_2 {
+                        reset.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
+            }
+            result
+        }
     });
 
-    let conn = server.serve_connection_with_upgrades(stream, hyper_service);
-    let conn = graceful.watch(conn.into_owned());
+    // hyper-util 只在不支持 upgrade 时遵守 http2_only，HTTP/2 本身不需要 HTTP/1 upgrade
+    let serve = async {
+        match ctx.versions {
+            HttpVersions::Http2Only => watcher.watch(ctx.server.serve_connection(stream, hyper_service).into_owned()).await,
+            _ => {
+                watcher
+                    .watch(ctx.server.serve_connection_with_upgrades(stream, hyper_service).into_owned())
+                    .await
+            }
+        }
+    };
+    let result = match ctx.timeouts.max_lifetime {
+        Some(lifetime) => match tokio::time::timeout(lifetime, serve).await {
+            Ok(result) => result,
+            Err(_) => {
+                info!("[max lifetime]: closed after {lifetime:?} from {peer_addr}");
+                return;
+            }
+        },
+        None => serve.await,
+    };
+    match result {
+        // 拦截器丢弃请求，已经记录过日志
+        Err(_) if reset.load(std::sync::atomic::Ordering::Relaxed) => {}
+        Err(err) => handle_hyper_error(&peer_addr, err),
+        // 等待下一个请求时超时，hyper 按正常关闭处理
+        Ok(()) if idle_timed_out.load(std::sync::atomic::Ordering::Relaxed) => {
+            log::debug!("[idle timeout]: idle for {idle_timeout:?} from {peer_addr}")
+        }
+        Ok(()) => {}
+    }
+    log::debug!("dropped: {peer_addr}");
+}
 
-    tokio::spawn(async move {
-        if let Err(err) = conn.await {
-            handle_hyper_error(client_socket_addr, err);
+/// 拒绝 HTTP/2 prior knowledge 连接
+///
+/// hyper-util 在支持 upgrade 时忽略 `http1_only`，因此在交给 hyper 之前检查 HTTP/2 连接前言
+///
+/// # 参数
+/// - `conn`: 网络连接
+///
+/// # 返回
+/// - `Ok(Rewind<C>)`: 不是 HTTP/2 连接，预读的数据会在读取时重新返回
+/// - `Err(std::io::Error)`: 收到 HTTP/2 连接前言或读取失败
+async fn reject_http2_preface<C>(mut conn: C) -> Result<Rewind<C>, std::io::Error>
+where
+    C: tokio::io::AsyncRead + Unpin,
+{
+    use tokio::io::AsyncReadExt;
+    let mut buf = [0; HTTP2_PREFACE.len()];
+    let mut len = 0;
+    while len < buf.len() {
+        let n = conn.read(&mut buf[len..]).await?;
+        if n == 0 {
+            break;
         }
-        log::debug!("dropped: {client_socket_addr}");
-    });
+        len += n;
+        if !HTTP2_PREFACE.starts_with(&buf[..len]) {
+            break;
+        }
+    }
+    if buf[..len] == *HTTP2_PREFACE {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "http2 is disabled"));
+    }
+    Ok(Rewind::new(conn, buf[..len].to_vec()))
 }
 
 /// 处理 Hyper 错误并记录日志
 ///
-/// 根据错误类型输出不同级别的日志
+/// 根据错误类型输出不同级别的日志，请求头读取超时和空闲超时分别记录
 ///
 /// # 参数
-/// - `client_socket_addr`: 客户端地址
+/// - `peer_addr`: 对端描述
 /// - `http_err`: HTTP 错误
-fn handle_hyper_error(client_socket_addr: SocketAddr, http_err: DynError) {
+fn handle_hyper_error(peer_addr: &PeerAddr, http_err: DynError) {
     use std::error::Error;
+    // 超时错误可能被 hyper 或 h2 包装，沿着错误链查找
+    let timeout = std::iter::successors(Some(&*http_err as &(dyn Error + 'static)), |err| (*err).source())
+        .filter_map(|err| err.downcast_ref::<std::io::Error>())
+        .find(|io_err| io_err.kind() == std::io::ErrorKind::TimedOut);
+    if let Some(io_err) = timeout {
+        log::debug!("[idle timeout]: {io_err} from {peer_addr}");
+        return;
+    }
     match http_err.downcast_ref::<hyper::Error>() {
+        // HTTP/1 请求头读取超时，keep-alive 连接等待下一个请求超时也会走到这里，所以不作为警告
+        Some(hyper_err) if hyper_err.is_timeout() => log::debug!("[header read timeout]: {hyper_err} from {peer_addr}"),
         Some(hyper_err) => {
             let level = if hyper_err.is_user() { log::Level::Warn } else { log::Level::Debug };
             let source = hyper_err.source().unwrap_or(hyper_err);
@@ -404,44 +2423,47 @@ fn handle_hyper_error(client_socket_addr: SocketAddr, http_err: DynError) {
                 "[hyper {}]: {:?} from {}",
                 if hyper_err.is_user() { "user" } else { "system" },
                 source,
-                SocketAddrFormat(&client_socket_addr)
+                peer_addr
             );
         }
         None => match http_err.downcast_ref::<std::io::Error>() {
             Some(io_err) => {
-                warn!("[hyper io]: [{}] {} from {}", io_err.kind(), io_err, SocketAddrFormat(&client_socket_addr));
+                warn!("[hyper io]: [{}] {} from {}", io_err.kind(), io_err, peer_addr);
             }
             None => {
-                warn!("[hyper]: {} from {}", http_err, SocketAddrFormat(&client_socket_addr));
+                warn!("[hyper]: {} from {}", http_err, peer_addr);
             }
         },
     }
 }
 
-/// 启动纯文本 HTTP 服务器
+/// 运行纯文本 HTTP 监听器的 accept 循环
 ///
-/// 监听指定端口并处理 HTTP 连接，支持优雅关闭
+/// 处理单个监听器上的 HTTP 连接，收到关闭信号后停止 accept，
+/// 连接的优雅关闭由调用方通过共享的 `graceful` 统一等待
 ///
 /// # 参数
-/// - `app`: Axum 路由
-/// - `server`: Hyper 服务器构建器
+/// - `ctx`: 连接处理上下文
 /// - `graceful`: 优雅关闭句柄
-/// - `port`: 监听端口
-/// - `interceptor`: 可选的请求拦截器
-/// - `timeout`: 连接空闲超时时间
+/// - `bound`: 监听器及其配置
 /// - `shutdown_rx`: 关闭信号接收器
 ///
 /// # 返回
-/// - `Ok(())`: 服务器成功启动并正常关闭
-/// - `Err(std::io::Error)`: 启动或运行过程中出现错误
+/// - `Ok(())`: 收到关闭信号，accept 循环正常退出
+/// - `Err(std::io::Error)`: 致命的 accept 错误
 async fn serve_plantext<I>(
-    app: &Router, server: hyper_util::server::conn::auto::Builder<TokioExecutor>, graceful: hyper_util::server::graceful::GracefulShutdown,
-    port: u16, interceptor: Option<I>, timeout: Duration, shutdown_rx: &mut broadcast::Receiver<()>,
+    ctx: &Arc<ServeContext<I>>, graceful: &hyper_util::server::graceful::GracefulShutdown, bound: BoundListener,
+    mut shutdown_rx: broadcast::Receiver<()>,
 ) -> Result<(), std::io::Error>
 where
     I: ReqInterceptor + Clone + Send + Sync + 'static,
 {
-    let listener = create_dual_stack_listener(port).await?;
+    let BoundListener {
+        listener,
+        role,
+        proxy_protocol,
+    } = bound;
+    let mut backoff = AcceptBackoff::default();
     loop {
         tokio::select! {
             _ = shutdown_rx.recv() => {
@@ -449,75 +2471,83 @@ where
                 drop(listener);
                 break;
             }
-            conn = listener.accept() => {
+            conn = ctx.limiter.accept(&listener, &mut backoff) => {
                 match conn {
-                    Ok((conn, client_socket_addr)) => {
-                        handle_connection(conn,client_socket_addr, app.clone(), server.clone(),interceptor.clone(), &graceful, timeout).await;}
+                    Ok(Some(accepted)) => {
+                        handle_connection(accepted, role, proxy_protocol, None, None, ctx, graceful);
+                    }
+                    Ok(None) => {}
                     Err(e) => {
-                        warn!("accept error:{e}");
+                        error!("accept error on {listener}: {e}, stop accepting");
+                        return Err(e);
                     }
                 }
             }
         }
     }
-    match tokio::time::timeout(GRACEFUL_SHUTDOWN_TIMEOUT, graceful.shutdown()).await {
-        Ok(_) => info!("Gracefully shutdown!"),
-        Err(_) => info!("Waited {GRACEFUL_SHUTDOWN_TIMEOUT:?} for graceful shutdown, aborting..."),
-    }
     Ok(())
 }
 
-/// 启动 TLS HTTPS 服务器
+/// 加载 TLS 配置，并按允许的 HTTP 版本设置 ALPN
+///
+/// # 参数
+/// - `tls_param`: TLS 配置参数
+/// - `sni_certificates`: 虚拟主机的独立证书，不为空时按 SNI 选择证书
+/// - `protocol`: HTTP 协议参数
+///
+/// # 返回
+/// - `Ok(Arc<ServerConfig>)`: TLS 服务器配置
+/// - `Err(std::io::Error)`: 证书或私钥加载失败
+fn load_tls_config(
+    tls_param: &TlsParam, sni_certificates: &[(String, TlsParam)], protocol: &ProtocolParam,
+) -> Result<Arc<ServerConfig>, std::io::Error> {
+    let config = match sni_certificates {
+        [] => tls_config(&tls_param.key, &tls_param.cert)?,
+        _ => tls_config_with_resolver(Arc::new(vhost::SniResolver::load(tls_param, sni_certificates)?)),
+    };
+    if protocol.versions == HttpVersions::Auto {
+        return Ok(config);
+    }
+    let mut config = Arc::unwrap_or_clone(config);
+    config.alpn_protocols = protocol.alpn_protocols();
+    Ok(Arc::new(config))
+}
+
+/// 运行 TLS HTTPS 监听器的 accept 循环
 ///
-/// 监听指定端口并处理 HTTPS 连接，支持 TLS 证书动态更新和优雅关闭
+/// 处理单个监听器上的 HTTPS 连接，支持 TLS 证书动态更新，收到关闭信号后停止 accept
 ///
 /// # 参数
-/// - `app`: Axum 路由
-/// - `server`: Hyper 服务器构建器
+/// - `ctx`: 连接处理上下文
 /// - `graceful`: 优雅关闭句柄
-/// - `port`: 监听端口
-/// - `tls_param`: TLS 配置参数
-/// - `interceptor`: 可选的请求拦截器
-/// - `timeout`: 连接空闲超时时间
+/// - `bound`: 监听器及其配置
+/// - `acceptor`: 使用初始 TLS 配置的接受器
+/// - `alt_svc`: 响应附加的 `Alt-Svc` 头，启用 HTTP/3 时声明同一端口的 HTTP/3 服务 (可选)
+/// - `config_rx`: TLS 配置更新接收器
 /// - `shutdown_rx`: 关闭信号接收器
 ///
 /// # 返回
-/// - `Ok(())`: 服务器成功启动并正常关闭
-/// - `Err(std::io::Error)`: 启动或运行过程中出现错误
-///
-/// # 说明
-/// 服务器会在后台启动一个定时任务，每隔 REFRESH_INTERVAL (24小时) 刷新一次 TLS 配置
-#[allow(clippy::too_many_arguments)]
+/// - `Ok(())`: 收到关闭信号，accept 循环正常退出
+/// - `Err(std::io::Error)`: 致命的 accept 错误
 async fn serve_tls<I>(
-    app: &Router, server: hyper_util::server::conn::auto::Builder<TokioExecutor>, graceful: hyper_util::server::graceful::GracefulShutdown,
-    port: u16, tls_param: &TlsParam, interceptor: Option<I>, timeout: Duration, shutdown_rx: &mut broadcast::Receiver<()>,
+    ctx: &Arc<ServeContext<I>>, graceful: &hyper_util::server::graceful::GracefulShutdown, bound: BoundListener, mut acceptor: TlsAcceptor,
+    alt_svc: Option<HeaderValue>, mut config_rx: broadcast::Receiver<Arc<ServerConfig>>, mut shutdown_rx: broadcast::Receiver<()>,
 ) -> Result<(), std::io::Error>
 where
     I: ReqInterceptor + Clone + Send + Sync + 'static,
 {
-    let (tx, mut rx) = broadcast::channel::<Arc<ServerConfig>>(1);
-    let tls_param_clone = tls_param.clone();
-    tokio::spawn(async move {
-        info!("update tls config every {REFRESH_INTERVAL:?}");
-        loop {
-            time::sleep(REFRESH_INTERVAL).await;
-            if let Ok(new_acceptor) = tls_config(&tls_param_clone.key, &tls_param_clone.cert) {
-                info!("update tls config");
-                if let Err(e) = tx.send(new_acceptor) {
-                    warn!("send tls config error:{e}");
-                }
-            }
-        }
-    });
-    let mut acceptor: TlsAcceptor = TlsAcceptor::new(tls_config(&tls_param.key, &tls_param.cert)?, create_dual_stack_listener(port).await?);
+    let BoundListener {
+        listener, proxy_protocol, ..
+    } = bound;
+    let mut backoff = AcceptBackoff::default();
     loop {
         tokio::select! {
             _ = shutdown_rx.recv() => {
                 info!("start graceful shutdown!");
-                drop(acceptor);
+                drop(listener);
                 break;
             }
-            message = rx.recv() => {
+            message = config_rx.recv() => {
                 match message {
                     Ok(new_config) => {
                         acceptor.replace_config(new_config);
@@ -536,21 +2566,20 @@ where
                     }
                 }
             }
-            conn = acceptor.accept() => {
+            conn = ctx.limiter.accept(&listener, &mut backoff) => {
                 match conn {
-                    Ok((conn, client_socket_addr)) => {
-                        handle_connection(conn,client_socket_addr, app.clone(), server.clone(),interceptor.clone(), &graceful, timeout).await;}
+                    Ok(Some(accepted)) => {
+                        handle_connection(accepted, ListenerRole::Https, proxy_protocol, Some(acceptor.clone()), alt_svc.clone(), ctx, graceful);
+                    }
+                    Ok(None) => {}
                     Err(e) => {
-                        warn!("accept error:{e}");
+                        error!("accept error on {listener}: {e}, stop accepting");
+                        return Err(e);
                     }
                 }
             }
         }
     }
-    match tokio::time::timeout(GRACEFUL_SHUTDOWN_TIMEOUT, graceful.shutdown()).await {
-        Ok(_) => info!("Gracefully shutdown!"),
-        Err(_) => info!("Waited {GRACEFUL_SHUTDOWN_TIMEOUT:?} for graceful shutdown, aborting..."),
-    }
     Ok(())
 }
 
@@ -647,3 +2676,432 @@ fn unwrap_infallible<T>(result: Result<T, Infallible>) -> T {
         Err(err) => match err {},
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(uri: &str, host: Option<&str>, https_port: Option<u16>) -> Option<String> {
+        let mut builder = hyper::Request::builder().uri(uri);
+        if let Some(host) = host {
+            builder = builder.header(header::HOST, host);
+        }
+        let (parts, _) = builder.body(()).unwrap().into_parts();
+        https_redirect_location(&parts.headers, &parts.uri, https_port)
+    }
+
+    #[test]
+    fn test_https_redirect_location_strips_http_port() {
+        assert_eq!(location("/a/b?c=1", Some("example.com:80"), None).as_deref(), Some("https://example.com/a/b?c=1"));
+        assert_eq!(location("/", Some("example.com"), Some(443)).as_deref(), Some("https://example.com/"));
+    }
+
+    #[test]
+    fn test_https_redirect_location_with_port() {
+        assert_eq!(location("/x", Some("example.com:8080"), Some(8443)).as_deref(), Some("https://example.com:8443/x"));
+        assert_eq!(location("/x", Some("[::1]:8080"), Some(8443)).as_deref(), Some("https://[::1]:8443/x"));
+        assert_eq!(location("/x", Some("[::1]"), None).as_deref(), Some("https://[::1]/x"));
+    }
+
+    #[test]
+    fn test_https_redirect_location_without_host() {
+        assert_eq!(location("/x", None, None), None);
+        assert_eq!(location("http://example.com/x", None, None).as_deref(), Some("https://example.com/x"));
+    }
+
+    #[test]
+    fn test_protocol_param() {
+        let param = ProtocolParam::default();
+        assert!(param.validate().is_ok());
+        let builder = param.builder(None);
+        assert!(builder.is_http1_available() && builder.is_http2_available());
+
+        let param = ProtocolParam {
+            versions: HttpVersions::Http2Only,
+            ..Default::default()
+        };
+        let builder = param.builder(None);
+        assert!(!builder.is_http1_available() && builder.is_http2_available());
+        assert_eq!(param.alpn_protocols(), vec![b"h2".to_vec()]);
+
+        let mut param = ProtocolParam::default();
+        param.http1.max_header_size = Some(1024);
+        assert!(param.validate().is_err());
+        param.http1.max_header_size = Some(16 * 1024);
+        param.http2.initial_stream_window_size = Some(u32::MAX);
+        assert!(param.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reject_http2_preface() {
+        use tokio::io::AsyncReadExt;
+
+        assert!(reject_http2_preface(&HTTP2_PREFACE[..]).await.is_err());
+        let mut request = String::new();
+        reject_http2_preface(&b"GET / HTTP/1.1\r\n\r\n"[..])
+            .await
+            .unwrap()
+            .read_to_string(&mut request)
+            .await
+            .unwrap();
+        assert_eq!(request, "GET / HTTP/1.1\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_spawn_and_shutdown() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let router = Router::new().route("/", axum::routing::get(|| async { "ok" }));
+        let (handle, addr) = spawn_loopback(new_server(0, router, shutdown_rx)).await;
+        assert_ne!(addr.port(), 0);
+
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut buf = [0; 1024];
+        let n = client.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+        assert_eq!(handle.open_connections(), 1);
+
+        handle.stop().await;
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_proxy_header_timeout() {
+        use tokio::io::AsyncReadExt;
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let server = new_server(0, Router::new(), shutdown_rx)
+            .with_proxy_protocol(SocketAddr::from(([127, 0, 0, 1], 0)), ProxyProtocolMode::Required)
+            .with_timeouts(TimeoutParam {
+                proxy_header: Duration::from_millis(100),
+                ..Default::default()
+            });
+        let (handle, addr) = spawn_loopback(server).await;
+
+        // 空闲超时仍为 120 秒，没有发送 PROXY protocol 头的连接在 proxy_header 超时后关闭
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let closed = tokio::time::timeout(Duration::from_secs(5), client.read(&mut [0; 16])).await;
+        assert!(matches!(closed, Ok(Ok(0) | Err(_))), "{closed:?}");
+
+        handle.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_header_read_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let router = Router::new()
+            .route("/upload", axum::routing::post(|body: String| async move { body.len().to_string() }))
+            .route("/early", axum::routing::post(|| async { hyper::StatusCode::PAYLOAD_TOO_LARGE }));
+        let timeout = Duration::from_millis(200);
+        let server = new_server(0, router, shutdown_rx).with_timeouts(TimeoutParam {
+            header_read: Some(timeout),
+            ..Default::default()
+        });
+        let (handle, addr) = spawn_loopback(server).await;
+        // 读取响应直到包含指定数量的状态行，响应体为空或数字
+        async fn read_responses(client: &mut tokio::net::TcpStream, count: usize) -> String {
+            let mut received = String::new();
+            while received.matches("HTTP/1.1 ").count() < count || !(received.ends_with("\r\n\r\n") || received.ends_with(char::is_numeric)) {
+                let mut buf = [0; 1024];
+                let n = client.read(&mut buf).await.unwrap();
+                assert!(n > 0, "connection closed after {received:?}");
+                received.push_str(std::str::from_utf8(&buf[..n]).unwrap());
+            }
+            received
+        }
+        // 每次发送两个字节，间隔为超时时间的四分之一
+        async fn trickle(client: &mut tokio::net::TcpStream, data: &[u8], timeout: Duration) {
+            for chunk in data.chunks(2) {
+                if client.write_all(chunk).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(timeout / 4).await;
+            }
+        }
+
+        // 缓慢上传请求体不受请求头读取超时限制
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 16\r\n\r\n")
+            .await
+            .unwrap();
+        trickle(&mut client, b"0123456789abcdef", timeout).await;
+        assert!(read_responses(&mut client, 1).await.starts_with("HTTP/1.1 200"));
+
+        // 流水线请求都会得到响应
+        client
+            .write_all(b"POST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 1\r\n\r\naPOST /upload HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\nbc")
+            .await
+            .unwrap();
+        let responses = read_responses(&mut client, 2).await;
+        assert_eq!(responses.matches("HTTP/1.1 200").count(), 2, "{responses}");
+
+        // 上传过程中提前返回响应；hyper 不再读取剩余的请求体而是关闭连接，剩余的请求体不会被当作新的请求头
+        client
+            .write_all(b"POST /early HTTP/1.1\r\nHost: localhost\r\nContent-Length: 16\r\n\r\n01")
+            .await
+            .unwrap();
+        assert!(read_responses(&mut client, 1).await.starts_with("HTTP/1.1 413"));
+        trickle(&mut client, b"23456789abcdef", timeout).await;
+        let mut buf = [0; 1024];
+        assert!(matches!(client.read(&mut buf).await, Ok(0) | Err(_)));
+
+        // 逐字节发送请求头的连接在超时后关闭
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let started = std::time::Instant::now();
+        tokio::select! {
+            _ = trickle(&mut client, b"GET / HTTP/1.1\r\nHost: localhost\r\nX-Padding: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n", timeout) => {}
+            _ = tokio::time::sleep(timeout * 3) => {}
+        }
+        let closed = tokio::time::timeout(Duration::from_secs(5), client.read(&mut [0; 1024])).await;
+        assert!(matches!(closed, Ok(Ok(0) | Err(_))), "{closed:?}");
+        assert!(started.elapsed() < timeout * 5);
+
+        handle.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_reload() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let router = Router::new().route("/", axum::routing::get(|| async { "ok" }));
+        let reload_hook = |settings: &RuntimeSettings| -> std::io::Result<()> {
+            settings.set_idle_timeout(Duration::from_millis(200));
+            Ok(())
+        };
+        let server = new_server(0, router, shutdown_rx)
+            .with_timeout(Duration::from_secs(60))
+            .with_reload_hook(reload_hook);
+        let (handle, addr) = spawn_loopback(server).await;
+        let connect = || async move {
+            let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+            client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+            let mut buf = [0; 1024];
+            let n = client.read(&mut buf).await.unwrap();
+            assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+            client
+        };
+        let mut old = connect().await;
+        handle.reload().await.unwrap();
+
+        // 新的空闲超时只对之后建立的连接生效
+        let mut new = connect().await;
+        let mut buf = [0; 1024];
+        let n = tokio::time::timeout(Duration::from_secs(2), new.read(&mut buf)).await.unwrap().unwrap();
+        assert_eq!(n, 0);
+        assert!(tokio::time::timeout(Duration::from_millis(300), old.read(&mut buf)).await.is_err());
+
+        handle.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_connection_info() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let router = Router::new().route(
+            "/",
+            axum::routing::get(|info: ConnectionInfo| async move { format!("{} {:?} {}", info.id, info.local_addr, info.is_tls()) }),
+        );
+        let (handle, addr) = spawn_loopback(new_server(0, router, shutdown_rx)).await;
+
+        let mut bodies = Vec::new();
+        for _ in 0..2 {
+            let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+            client
+                .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            bodies.push(response.split("\r\n\r\n").nth(1).unwrap().to_string());
+        }
+        let ids: Vec<u64> = bodies.iter().map(|body| body.split(' ').next().unwrap().parse().unwrap()).collect();
+        assert_ne!(ids[0], ids[1]);
+        assert!(bodies[0].ends_with(&format!("Some({addr}) false")));
+
+        handle.stop().await;
+    }
+
+    #[derive(Clone)]
+    struct DropInterceptor;
+
+    impl ReqInterceptor for DropInterceptor {
+        type Error = String;
+
+        async fn intercept(&self, req: Request, _ip: SocketAddr) -> InterceptResult<Self::Error> {
+            if req.uri().path() == "/drop" {
+                InterceptResult::Drop
+            } else {
+                InterceptResult::Continue(req)
+            }
+        }
+    }
+
+    /// 记录调用顺序并给请求追加标记的拦截器
+    #[derive(Clone)]
+    struct TagInterceptor(&'static str, Arc<std::sync::Mutex<Vec<&'static str>>>);
+
+    impl ReqInterceptor for TagInterceptor {
+        type Error = (hyper::StatusCode, &'static str);
+
+        async fn intercept(&self, mut req: Request, _ip: SocketAddr) -> InterceptResult<Self::Error> {
+            self.1.lock().unwrap().push(self.0);
+            let tags = req
+                .headers()
+                .get("x-tags")
+                .map_or(String::new(), |tags| format!("{},", tags.to_str().unwrap()));
+            req.headers_mut()
+                .insert("x-tags", HeaderValue::try_from(format!("{tags}{}", self.0)).unwrap());
+            match req.uri().path().strip_prefix("/error/") {
+                Some(stage) if stage == self.0 => InterceptResult::Error((hyper::StatusCode::FORBIDDEN, self.0)),
+                _ => InterceptResult::Continue(req),
+            }
+        }
+
+        async fn on_response(&self, mut response: Response, ctx: &ResponseContext) -> Response {
+            let tags = response
+                .headers()
+                .get("x-response-tags")
+                .map_or(String::new(), |tags| format!("{},", tags.to_str().unwrap()));
+            response
+                .headers_mut()
+                .insert("x-response-tags", HeaderValue::try_from(format!("{tags}{}:{}", self.0, ctx.uri.path())).unwrap());
+            response
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_chain() {
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let tag = |name| TagInterceptor(name, calls.clone());
+        let chain = (tag("a"), (tag("b"), DropInterceptor), tag("c"));
+        let ip = PeerAddr::UNSPECIFIED_SOCKET_ADDR;
+        let request = |path: &str| hyper::Request::builder().uri(path).body(axum::body::Body::empty()).unwrap();
+
+        // 后一个拦截器收到前一个拦截器传出的请求
+        let InterceptResult::Continue(req) = chain.intercept(request("/"), ip).await else {
+            panic!("expect continue");
+        };
+        assert_eq!(req.headers()["x-tags"], "a,b,c");
+        assert_eq!(*calls.lock().unwrap(), ["a", "b", "c"]);
+
+        // 第一个非 Continue 的结果短路返回
+        calls.lock().unwrap().clear();
+        assert!(matches!(chain.intercept(request("/drop"), ip).await, InterceptResult::Drop));
+        assert_eq!(*calls.lock().unwrap(), ["a", "b"]);
+
+        calls.lock().unwrap().clear();
+        let InterceptResult::Error(err) = chain.intercept(request("/error/b"), ip).await else {
+            panic!("expect error");
+        };
+        assert_eq!(err.into_response().status(), hyper::StatusCode::FORBIDDEN);
+        assert_eq!(*calls.lock().unwrap(), ["a", "b"]);
+
+        // 响应阶段按相反的顺序调用
+        let ctx = ResponseContext::new(&request("/path"), ConnectionInfo::new(None, PeerAddr::Tcp(ip)));
+        let response = chain.on_response(Response::default(), &ctx).await;
+        assert_eq!(response.headers()["x-response-tags"], "c:/path,b:/path,a:/path");
+    }
+
+    struct RecordingHook {
+        block: std::sync::atomic::AtomicBool,
+        disconnected: std::sync::Mutex<Vec<DisconnectInfo>>,
+    }
+
+    impl ConnectionHook for Arc<RecordingHook> {
+        fn on_connect(&self, _peer_addr: &PeerAddr, _local_addr: Option<SocketAddr>) -> ConnectAction {
+            match self.block.load(std::sync::atomic::Ordering::Relaxed) {
+                true => ConnectAction::Drop,
+                false => ConnectAction::Accept,
+            }
+        }
+
+        fn on_disconnect(&self, info: &DisconnectInfo) {
+            self.disconnected.lock().unwrap().push(info.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_hook() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let router = Router::new().route("/", axum::routing::get(|| async { "ok" }));
+        let hook = Arc::new(RecordingHook {
+            block: true.into(),
+            disconnected: Default::default(),
+        });
+        let (handle, addr) = spawn_loopback(new_server(0, router, shutdown_rx).with_connection_hook(hook.clone())).await;
+        let request = b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+
+        // 被丢弃的连接以 RST 关闭，不调用 on_disconnect
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut response = Vec::new();
+        assert_eq!(client.read_to_end(&mut response).await.unwrap_err().kind(), std::io::ErrorKind::ConnectionReset);
+
+        hook.block.store(false, std::sync::atomic::Ordering::Relaxed);
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client.write_all(request).await.unwrap();
+        client.read_to_end(&mut response).await.unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 200"));
+
+        handle.stop().await;
+        let disconnected = hook.disconnected.lock().unwrap();
+        assert_eq!(disconnected.len(), 1);
+        assert_eq!(disconnected[0].connection.local_addr, Some(addr));
+        assert_eq!(disconnected[0].bytes_read, request.len() as u64);
+        assert_eq!(disconnected[0].bytes_written, response.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_on_response() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let router = Router::new().route("/", axum::routing::get(|| async { "ok" }));
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (handle, addr) = spawn_loopback(new_server(0, router, shutdown_rx).with_interceptor(TagInterceptor("a", calls))).await;
+
+        for (path, status) in [("/", "200"), ("/error/a", "403")] {
+            let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+            client
+                .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with(&format!("HTTP/1.1 {status}")));
+            assert!(response.contains(&format!("x-response-tags: a:{path}\r\n")));
+        }
+
+        handle.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_drop_resets_connection() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let router = Router::new().route("/", axum::routing::get(|| async { "ok" }));
+        let (handle, addr) = spawn_loopback(new_server(0, router, shutdown_rx).with_interceptor(DropInterceptor)).await;
+
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut buf = [0; 1024];
+        let n = client.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"HTTP/1.1 200"));
+
+        // 不写出任何响应，直接以 RST 关闭连接
+        client.write_all(b"GET /drop HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let err = client.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::ConnectionReset);
+
+        handle.stop().await;
+    }
+}
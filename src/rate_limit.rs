@@ -0,0 +1,670 @@
+//! # 限流模块
+//!
+//! 基于 GCRA (Generic Cell Rate Algorithm) 的内存限流，不依赖外部存储
+//!
+//! # 主要组件
+//! - `Quota`: 限流配额，周期内的请求数和突发上限
+//! - `RateLimitKey`: 限流键，决定按什么维度计数 (客户端 IP、请求头、JWT 用户名、路由或自定义)
+//! - `RateLimitRule`: 限流键和配额组成的规则
+//! - `RateLimiter`: 按规则限流的请求拦截器 (`ReqInterceptor`)
+//! - `RateLimitLayer`: 同样规则的 tower layer，可以放在路由或 JWT 中间件之后
+//!
+//! # 响应
+//! 超过配额的请求返回 `429 Too Many Requests`，并附带以下响应头:
+//! - `Retry-After`: 多少秒之后可以重试
+//! - `RateLimit-Limit`: 突发上限
+//! - `RateLimit-Remaining`: 剩余的请求数，被限流时为 0
+//! - `RateLimit-Reset`: 多少秒之后配额完全恢复
+//!
+//! # 存储
+//! 每条规则的计数按键的哈希分片保存在内存中，每个分片单独加锁。配额已经完全恢复的键等价于不存在，
+//! 在 tokio 运行时中创建限流器时，后台任务每隔 60 秒清理一次这些空闲的键，限流器被 drop 后任务随之退出。
+//! 每条规则最多保存 [`RateLimiter::with_max_keys`] 个键 (默认 100000)，平均分配到各个分片；
+//! 分片已满时先清理空闲的键，仍然已满时淘汰配额最接近完全恢复的键，
+//! 所以随机的请求头或路径不会让内存无限增长
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::BuildHasher,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::{HeaderName, HeaderValue, StatusCode, header, request::Parts},
+    response::{IntoResponse, Response},
+};
+use futures_util::future::Either;
+use log::debug;
+
+use crate::{InterceptResult, ReqInterceptor, util::io::PeerAddr};
+
+/// 分片数量
+const SHARDS: usize = 32;
+
+/// 清理空闲键的间隔
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 每条规则默认最多保存的键数量
+const DEFAULT_MAX_KEYS: usize = 100_000;
+
+/// 限流配额
+///
+/// 平均每 `period / requests` 允许一个请求，最多允许 `burst` 个请求同时到达
+///
+/// # 示例
+///
+/// ```
+/// use std::time::Duration;
+/// use axum_bootstrap::rate_limit::Quota;
+///
+/// // 每秒 10 个请求，允许 20 个请求的突发
+/// let quota = Quota::per_second(10).with_burst(20);
+/// // 每 5 分钟 100 个请求
+/// let quota = Quota::new(100, Duration::from_secs(300));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    interval: Duration,
+    burst: u32,
+}
+
+impl Quota {
+    /// 创建周期内允许指定请求数的配额，突发上限等于请求数
+    ///
+    /// # 参数
+    /// - `requests`: 周期内的请求数，为 0 时按 1 处理
+    /// - `period`: 周期
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1);
+        Self {
+            interval: period / requests,
+            burst: requests,
+        }
+    }
+
+    /// 每秒允许指定请求数的配额
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// 每分钟允许指定请求数的配额
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// 每小时允许指定请求数的配额
+    pub fn per_hour(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60 * 60))
+    }
+
+    /// 设置突发上限
+    ///
+    /// # 参数
+    /// - `burst`: 最多同时到达的请求数，为 0 时按 1 处理
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// 突发上限
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// 平均每个请求的间隔
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// 自定义限流键函数
+pub type KeyFn = Arc<dyn Fn(&Parts) -> Option<String> + Send + Sync>;
+
+/// 限流键
+///
+/// 无法从请求中获取键时 (例如缺少请求头、没有登录、Unix domain socket 连接)，该规则不限流
+///
+/// # 变体
+/// - `ClientIp`: 客户端 IP。拦截器使用 `intercept` 的 `ip` 参数，tower layer 使用请求 extensions 中的
+///   `ConnectInfo<PeerAddr>` 或 `ConnectInfo<SocketAddr>`，IPv4 映射的 IPv6 地址按 IPv4 计数
+/// - `Header(HeaderName)`: 请求头的值，例如 API key
+/// - `JwtUsername(Arc<JwtConfig>)`: JWT 中的用户名 (需要 `jwt` feature)。优先使用 JWT 中间件存入 extensions 的
+///   [`Claims`](crate::jwt::Claims)，没有时使用配置解码 Cookie 中的 token
+/// - `Route`: 匹配的路由，例如 `/users/{id}`。拦截器在路由之前调用，此时使用请求路径
+/// - `Custom(..)`: 自定义函数，根据请求的方法、URI、请求头和 extensions 计算键
+#[derive(Clone)]
+pub enum RateLimitKey {
+    ClientIp,
+    Header(HeaderName),
+    #[cfg(feature = "jwt")]
+    JwtUsername(Arc<crate::jwt::JwtConfig>),
+    Route,
+    Custom(KeyFn),
+}
+
+impl RateLimitKey {
+    /// 使用自定义函数计算键
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// 从请求中计算键
+    ///
+    /// # 参数
+    /// - `parts`: 请求头部
+    /// - `ip`: 客户端 IP，为 None 时从 extensions 中获取
+    fn extract(&self, parts: &Parts, ip: Option<IpAddr>) -> Option<String> {
+        match self {
+            RateLimitKey::ClientIp => ip.or_else(|| client_ip(parts)).map(|ip| ip.to_canonical().to_string()),
+            RateLimitKey::Header(name) => parts
+                .headers
+                .get(name)
+                .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned()),
+            #[cfg(feature = "jwt")]
+            RateLimitKey::JwtUsername(config) => match parts.extensions.get::<crate::jwt::Claims>() {
+                Some(claims) => Some(claims.payload.username.clone()),
+                None => crate::jwt::token_from_headers(&parts.headers)
+                    .and_then(|token| crate::jwt::Claims::<crate::jwt::ClaimsPayload>::decode(&token, config).ok())
+                    .map(|claims| claims.payload.username),
+            },
+            RateLimitKey::Route => Some(match parts.extensions.get::<MatchedPath>() {
+                Some(path) => path.as_str().to_string(),
+                None => parts.uri.path().to_string(),
+            }),
+            RateLimitKey::Custom(f) => f(parts),
+        }
+    }
+}
+
+impl fmt::Debug for RateLimitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitKey::ClientIp => f.write_str("ClientIp"),
+            RateLimitKey::Header(name) => f.debug_tuple("Header").field(name).finish(),
+            #[cfg(feature = "jwt")]
+            RateLimitKey::JwtUsername(_) => f.write_str("JwtUsername"),
+            RateLimitKey::Route => f.write_str("Route"),
+            RateLimitKey::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// 从请求 extensions 中获取客户端 IP
+fn client_ip(parts: &Parts) -> Option<IpAddr> {
+    match parts.extensions.get::<ConnectInfo<PeerAddr>>() {
+        Some(ConnectInfo(PeerAddr::Tcp(addr))) => Some(addr.ip()),
+        Some(_) => None,
+        None => parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip()),
+    }
+}
+
+/// 限流规则
+///
+/// # 字段
+/// - `key`: 限流键
+/// - `quota`: 每个键的配额
+#[derive(Debug, Clone)]
+pub struct RateLimitRule {
+    pub key: RateLimitKey,
+    pub quota: Quota,
+}
+
+/// 限流判断结果
+///
+/// # 变体
+/// - `Allowed`: 允许请求
+/// - `Limited`: 超过配额，`retry_after` 之后可以重试，`reset` 之后配额完全恢复
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Decision {
+    Allowed,
+    Limited { limit: u32, retry_after: Duration, reset: Duration },
+}
+
+/// 一条规则的计数分片
+struct Shard {
+    /// 每个键的理论到达时间 (TAT)
+    buckets: HashMap<String, Instant>,
+}
+
+impl Shard {
+    /// 计算键消耗一个请求的配额之后的 TAT，不修改计数
+    ///
+    /// # 返回
+    /// - `Ok(Instant)`: 允许请求，提交时写入的新 TAT
+    /// - `Err(Decision)`: 超过配额
+    fn check(&self, key: &str, quota: Quota, now: Instant) -> Result<Instant, Decision> {
+        let Quota { interval, burst } = quota;
+        let tolerance = interval * burst;
+        let tat = self.buckets.get(key).copied().unwrap_or(now).max(now);
+        let new_tat = tat + interval;
+        let ahead = new_tat - now;
+        if ahead > tolerance {
+            return Err(Decision::Limited {
+                limit: burst,
+                retry_after: ahead - tolerance,
+                reset: tat - now,
+            });
+        }
+        Ok(new_tat)
+    }
+
+    /// 写入键的新 TAT
+    ///
+    /// 新键使分片超过上限时，先清理配额已经完全恢复的键，仍然超过上限时淘汰 TAT 最早的键，
+    /// 即配额最接近完全恢复、淘汰后影响最小的键
+    fn commit(&mut self, key: String, tat: Instant, max_keys: usize, now: Instant) {
+        if self.buckets.len() >= max_keys && !self.buckets.contains_key(&key) {
+            self.sweep(now);
+            if self.buckets.len() >= max_keys {
+                if let Some(oldest) = self.buckets.iter().min_by_key(|(_, tat)| **tat).map(|(key, _)| key.clone()) {
+                    self.buckets.remove(&oldest);
+                }
+            }
+        }
+        self.buckets.insert(key, tat);
+    }
+
+    /// 清理配额已经完全恢复的键
+    fn sweep(&mut self, now: Instant) {
+        self.buckets.retain(|_, tat| *tat > now);
+    }
+}
+
+/// 一条规则的计数
+struct Store {
+    quota: Quota,
+    /// 每个分片最多保存的键数量
+    max_keys: usize,
+    hasher: std::collections::hash_map::RandomState,
+    shards: Vec<Mutex<Shard>>,
+}
+
+impl Store {
+    /// 创建计数
+    ///
+    /// # 参数
+    /// - `quota`: 每个键的配额
+    /// - `max_keys`: 最多保存的键数量，平均分配到各个分片
+    fn new(quota: Quota, max_keys: usize) -> Self {
+        Self {
+            quota,
+            max_keys: max_keys.div_ceil(SHARDS).max(1),
+            hasher: Default::default(),
+            shards: (0..SHARDS).map(|_| Mutex::new(Shard { buckets: HashMap::new() })).collect(),
+        }
+    }
+
+    /// 锁定键所在的分片
+    fn lock(&self, key: &str) -> std::sync::MutexGuard<'_, Shard> {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        self.shards[index].lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 为键消耗一个请求的配额
+    #[cfg(test)]
+    fn acquire(&self, key: &str, now: Instant) -> Decision {
+        let mut shard = self.lock(key);
+        match shard.check(key, self.quota, now) {
+            Ok(tat) => {
+                shard.commit(key.to_string(), tat, self.max_keys, now);
+                Decision::Allowed
+            }
+            Err(limited) => limited,
+        }
+    }
+
+    /// 清理所有分片中配额已经完全恢复的键
+    fn sweep(&self, now: Instant) {
+        for shard in &self.shards {
+            shard.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).sweep(now);
+        }
+    }
+
+    /// 当前保存的键数量
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).buckets.len())
+            .sum()
+    }
+}
+
+/// 限流器
+///
+/// 可以克隆，所有克隆共享同一份计数。请求需要满足所有规则，先检查所有规则，全部允许时才消耗每条规则的配额，
+/// 被拒绝的请求不消耗任何配额。既可以作为请求拦截器，也可以通过 [`RateLimiter::layer`] 作为 tower layer
+///
+/// # 示例
+///
+/// ```no_run
+/// use axum::{Router, http::HeaderName, routing::get};
+/// use axum_bootstrap::{
+///     generate_shutdown_receiver, new_server,
+///     rate_limit::{Quota, RateLimitKey, RateLimitRule, RateLimiter},
+/// };
+///
+/// #[tokio::main]
+/// async fn main() {
+///     // 在路由之前按客户端 IP 限流
+///     let limiter = RateLimiter::new([RateLimitRule {
+///         key: RateLimitKey::ClientIp,
+///         quota: Quota::per_second(10).with_burst(20),
+///     }]);
+///     // 在路由之后按 API key 和匹配的路由限流
+///     let api_limiter = RateLimiter::new([
+///         RateLimitRule {
+///             key: RateLimitKey::Header(HeaderName::from_static("x-api-key")),
+///             quota: Quota::per_minute(600),
+///         },
+///         RateLimitRule {
+///             key: RateLimitKey::Route,
+///             quota: Quota::per_second(1000),
+///         },
+///     ]);
+///     let router = Router::new().route("/users/{id}", get(|| async { "ok" })).layer(api_limiter.layer());
+///     let server = new_server(8080, router, generate_shutdown_receiver()).with_interceptor(limiter);
+///     server.run().await.unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    rules: Arc<Vec<(RateLimitRule, Store)>>,
+}
+
+impl RateLimiter {
+    /// 创建限流器
+    ///
+    /// # 参数
+    /// - `rules`: 限流规则，每条规则单独计数
+    pub fn new(rules: impl IntoIterator<Item = RateLimitRule>) -> Self {
+        Self::with_rules(rules, DEFAULT_MAX_KEYS)
+    }
+
+    /// 设置每条规则最多保存的键数量
+    ///
+    /// 超过上限时淘汰配额最接近完全恢复的键，被淘汰的键重新获得完整的配额。
+    /// 会清空已有的计数，应在克隆和开始使用之前调用
+    ///
+    /// # 参数
+    /// - `max_keys`: 每条规则最多保存的键数量，默认 100000
+    pub fn with_max_keys(self, max_keys: usize) -> Self {
+        Self::with_rules(self.rules().cloned().collect::<Vec<_>>(), max_keys)
+    }
+
+    fn with_rules(rules: impl IntoIterator<Item = RateLimitRule>, max_keys: usize) -> Self {
+        let rules = Arc::new(
+            rules
+                .into_iter()
+                .map(|rule| {
+                    let store = Store::new(rule.quota, max_keys);
+                    (rule, store)
+                })
+                .collect::<Vec<_>>(),
+        );
+        spawn_sweeper(&rules);
+        Self { rules }
+    }
+
+    /// 限流规则
+    pub fn rules(&self) -> impl Iterator<Item = &RateLimitRule> {
+        self.rules.iter().map(|(rule, _)| rule)
+    }
+
+    /// 创建共享计数的 tower layer
+    pub fn layer(&self) -> RateLimitLayer {
+        RateLimitLayer { limiter: self.clone() }
+    }
+
+    /// 检查请求是否超过配额
+    ///
+    /// # 参数
+    /// - `parts`: 请求头部
+    /// - `ip`: 客户端 IP，为 None 时从 extensions 中获取
+    fn check(&self, parts: &Parts, ip: Option<IpAddr>) -> Decision {
+        // 先计算所有键，避免持有分片锁时解码 JWT 或调用自定义函数
+        let keys: Vec<_> = self
+            .rules
+            .iter()
+            .filter_map(|(rule, store)| rule.key.extract(parts, ip).map(|key| (rule, store, key)))
+            .collect();
+        let now = Instant::now();
+        // 按规则顺序锁定每条规则中键所在的分片，所有请求的加锁顺序相同，不会死锁。
+        // 全部允许后再写入新的 TAT，检查和写入之间其它请求无法修改这些键
+        let mut allowed = Vec::with_capacity(keys.len());
+        for (rule, store, key) in keys {
+            let shard = store.lock(&key);
+            match shard.check(&key, store.quota, now) {
+                Ok(tat) => allowed.push((shard, store.max_keys, key, tat)),
+                Err(limited) => {
+                    debug!("[rate limit]: {} {} limited by {:?} key {key}", parts.method, parts.uri, rule.key);
+                    return limited;
+                }
+            }
+        }
+        for (mut shard, max_keys, key, tat) in allowed {
+            shard.commit(key, tat, max_keys, now);
+        }
+        Decision::Allowed
+    }
+}
+
+/// 启动定时清理空闲键的后台任务
+///
+/// 任务只持有规则的弱引用，限流器的所有克隆都被 drop 后退出。不在 tokio 运行时中时不启动，
+/// 此时只在分片已满时清理
+fn spawn_sweeper(rules: &Arc<Vec<(RateLimitRule, Store)>>) {
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        debug!("[rate limit]: not in a tokio runtime, idle keys are only evicted when a shard is full");
+        return;
+    };
+    let rules = Arc::downgrade(rules);
+    runtime.spawn(async move {
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + SWEEP_INTERVAL, SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(rules) = rules.upgrade() else {
+                break;
+            };
+            let now = Instant::now();
+            for (_, store) in rules.iter() {
+                store.sweep(now);
+            }
+        }
+    });
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter").field("rules", &self.rules().collect::<Vec<_>>()).finish()
+    }
+}
+
+/// 生成 429 响应
+fn too_many_requests(limit: u32, retry_after: Duration, reset: Duration) -> Response {
+    let seconds = |duration: Duration| HeaderValue::from(duration.as_secs() + u64::from(duration.subsec_nanos() > 0));
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [
+            (header::RETRY_AFTER, seconds(retry_after)),
+            (HeaderName::from_static("ratelimit-limit"), HeaderValue::from(limit)),
+            (HeaderName::from_static("ratelimit-remaining"), HeaderValue::from(0)),
+            (HeaderName::from_static("ratelimit-reset"), seconds(reset)),
+        ],
+        "Too Many Requests",
+    )
+        .into_response()
+}
+
+impl ReqInterceptor for RateLimiter {
+    type Error = StatusCode;
+
+    async fn intercept(&self, req: Request, ip: SocketAddr) -> InterceptResult<Self::Error> {
+        let (parts, body) = req.into_parts();
+        let ip = match parts.extensions.get::<ConnectInfo<PeerAddr>>() {
+            Some(ConnectInfo(PeerAddr::Unix(_))) => None,
+            _ => Some(ip.ip()),
+        };
+        match self.check(&parts, ip) {
+            Decision::Allowed => InterceptResult::Continue(Request::from_parts(parts, body)),
+            Decision::Limited { limit, retry_after, reset } => InterceptResult::Return(too_many_requests(limit, retry_after, reset)),
+        }
+    }
+}
+
+/// 限流 tower layer
+///
+/// 由 [`RateLimiter::layer`] 创建，与限流器共享计数
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+}
+
+impl<S> tower::Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+/// 限流 tower service
+#[derive(Debug, Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S, B> tower::Service<axum::http::Request<B>> for RateLimitService<S>
+where
+    S: tower::Service<axum::http::Request<B>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<std::future::Ready<Result<Response, S::Error>>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: axum::http::Request<B>) -> Self::Future {
+        let (parts, body) = req.into_parts();
+        match self.limiter.check(&parts, None) {
+            Decision::Allowed => Either::Right(self.inner.call(axum::http::Request::from_parts(parts, body))),
+            Decision::Limited { limit, retry_after, reset } => Either::Left(std::future::ready(Ok(too_many_requests(limit, retry_after, reset)))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcra() {
+        let store = Store::new(Quota::per_second(10).with_burst(3), DEFAULT_MAX_KEYS);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(store.acquire("a", now), Decision::Allowed);
+        }
+        assert_eq!(
+            store.acquire("a", now),
+            Decision::Limited {
+                limit: 3,
+                retry_after: Duration::from_millis(100),
+                reset: Duration::from_millis(300),
+            }
+        );
+        // 其它键不受影响
+        assert_eq!(store.acquire("b", now), Decision::Allowed);
+        // 每 100ms 恢复一个请求
+        assert_eq!(store.acquire("a", now + Duration::from_millis(100)), Decision::Allowed);
+        assert!(matches!(store.acquire("a", now + Duration::from_millis(150)), Decision::Limited { .. }));
+
+        // 配额完全恢复的键在定时清理时被删除
+        let later = now + SWEEP_INTERVAL;
+        store.sweep(now + Duration::from_millis(200));
+        assert_eq!(store.len(), 1);
+        store.sweep(later);
+        assert_eq!(store.len(), 0);
+        for i in 0..1000 {
+            assert_eq!(store.acquire(&format!("key-{i}"), later), Decision::Allowed);
+        }
+        assert_eq!(store.len(), 1000);
+    }
+
+    #[test]
+    fn test_max_keys() {
+        let store = Store::new(Quota::per_hour(3), SHARDS * 4);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(store.acquire("heavy", now), Decision::Allowed);
+        }
+        // 随机的键不会让计数无限增长，配额消耗最多的键不会被淘汰
+        for i in 0..10_000 {
+            assert_eq!(store.acquire(&format!("random-{i}"), now), Decision::Allowed);
+        }
+        assert!(store.len() <= SHARDS * 4);
+        assert!(matches!(store.acquire("heavy", now), Decision::Limited { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_keys() {
+        use tower::{Layer, ServiceExt};
+
+        let limiter = RateLimiter::new([
+            RateLimitRule {
+                key: RateLimitKey::ClientIp,
+                quota: Quota::per_minute(2),
+            },
+            RateLimitRule {
+                key: RateLimitKey::Header(HeaderName::from_static("x-api-key")),
+                quota: Quota::per_minute(1),
+            },
+        ]);
+        let request = |key: Option<&str>| {
+            let mut builder = axum::http::Request::builder().uri("/");
+            if let Some(key) = key {
+                builder = builder.header("x-api-key", key);
+            }
+            builder.body(axum::body::Body::empty()).unwrap()
+        };
+        let ip = SocketAddr::from(([127, 0, 0, 1], 1234));
+
+        assert!(matches!(limiter.intercept(request(Some("k1")), ip).await, InterceptResult::Continue(_)));
+        // 同一个 API key 超过配额
+        let InterceptResult::Return(response) = limiter.intercept(request(Some("k1")), ip).await else {
+            panic!("expect too many requests");
+        };
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["ratelimit-limit"], "1");
+        assert_eq!(response.headers()["retry-after"], "60");
+        // 被 API key 规则拒绝的请求不消耗 IP 的配额，没有 API key 的请求只按 IP 限流
+        assert!(matches!(limiter.intercept(request(None), ip).await, InterceptResult::Continue(_)));
+        assert!(matches!(limiter.intercept(request(None), ip).await, InterceptResult::Return(_)));
+        let other_ip = SocketAddr::from(([127, 0, 0, 2], 1234));
+        assert!(matches!(limiter.intercept(request(None), other_ip).await, InterceptResult::Continue(_)));
+
+        // layer 与拦截器共享计数，客户端 IP 从 extensions 中获取
+        let service = limiter
+            .layer()
+            .layer(tower::service_fn(|_req: Request| async { Ok::<_, std::convert::Infallible>(Response::default()) }));
+        let mut req = request(None);
+        req.extensions_mut().insert(ConnectInfo(PeerAddr::Tcp(other_ip)));
+        assert_eq!(service.clone().oneshot(req).await.unwrap().status(), StatusCode::OK);
+        let mut req = request(None);
+        req.extensions_mut().insert(ConnectInfo(PeerAddr::Tcp(other_ip)));
+        assert_eq!(service.oneshot(req).await.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
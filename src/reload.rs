@@ -0,0 +1,296 @@
+//! # 重新加载模块
+//!
+//! 服务器运行中重新加载 TLS 证书和部分配置，不中断已有连接
+//!
+//! # 触发方式
+//! - 启用 [`Server::with_sighup_reload`](crate::Server::with_sighup_reload) 后收到 `SIGHUP` (仅 Unix 平台)，
+//!   例如证书续期后执行 `kill -HUP <pid>`
+//! - 调用 [`ServerHandle::reload`](crate::ServerHandle::reload)
+//!
+//! # 重新加载的内容
+//! 先依次调用所有重新加载钩子 (`ReloadHook`)，再重新读取 TLS 证书 (包括虚拟主机的独立证书)。
+//! 任一步骤失败时以 error 级别记录日志并保留该部分原来的配置，其余步骤照常进行。
+//! 新配置只对之后建立的连接生效
+//!
+//! 每 24 小时的证书定时刷新与之独立，只重新读取证书，每次重新加载后重新计时
+
+use std::{
+    io,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use log::{error, info, warn};
+use rustls::ServerConfig;
+use tokio::{sync::broadcast, time};
+
+use crate::{ProtocolParam, REFRESH_INTERVAL, TlsParam, load_tls_config};
+
+/// 运行中可以修改的服务器参数
+///
+/// 可以克隆，所有克隆共享同一份参数。修改只对之后建立的连接生效，
+/// HTTP/3 的空闲超时在下次重新加载证书时生效
+#[derive(Debug, Clone)]
+pub struct RuntimeSettings {
+    idle_timeout_nanos: Arc<AtomicU64>,
+}
+
+impl RuntimeSettings {
+    pub(crate) fn new(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout_nanos: Arc::new(AtomicU64::new(nanos(idle_timeout))),
+        }
+    }
+
+    /// 连接空闲超时时间
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_nanos(self.idle_timeout_nanos.load(Ordering::Relaxed))
+    }
+
+    /// 修改连接空闲超时时间
+    pub fn set_idle_timeout(&self, timeout: Duration) {
+        let old = Duration::from_nanos(self.idle_timeout_nanos.swap(nanos(timeout), Ordering::Relaxed));
+        if old != timeout {
+            info!("[reload]: idle timeout {old:?} -> {timeout:?}");
+        }
+    }
+}
+
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// 重新加载钩子
+///
+/// 收到重新加载请求时调用，适合重新读取配置文件、IP 列表等。返回错误时应保留原来的配置。
+/// 也为 `Fn(&RuntimeSettings) -> io::Result<()>` 闭包实现
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use axum::Router;
+/// use axum_bootstrap::{generate_shutdown_receiver, new_server, reload::RuntimeSettings};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let server = new_server(8080, Router::new(), generate_shutdown_receiver())
+///         .with_sighup_reload(true)
+///         .with_reload_hook(|settings: &RuntimeSettings| -> std::io::Result<()> {
+///             let secs: u64 = std::fs::read_to_string("/etc/app/idle_timeout")?.trim().parse().map_err(std::io::Error::other)?;
+///             settings.set_idle_timeout(Duration::from_secs(secs));
+///             Ok(())
+///         });
+///     server.run().await.unwrap();
+/// }
+/// ```
+pub trait ReloadHook: Send + Sync + 'static {
+    /// 重新加载
+    ///
+    /// # 参数
+    /// - `settings`: 运行中可以修改的服务器参数
+    fn reload(&self, settings: &RuntimeSettings) -> io::Result<()>;
+
+    /// 日志中使用的名称，默认为类型名
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<F> ReloadHook for F
+where
+    F: Fn(&RuntimeSettings) -> io::Result<()> + Send + Sync + 'static,
+{
+    fn reload(&self, settings: &RuntimeSettings) -> io::Result<()> {
+        self(settings)
+    }
+
+    fn name(&self) -> &str {
+        "reload hook"
+    }
+}
+
+/// 重新加载 TLS 证书所需的参数
+///
+/// # 字段
+/// - `param`: TLS 配置参数
+/// - `sni_certificates`: 虚拟主机的独立证书
+/// - `protocol`: HTTP 协议参数，决定 ALPN 中声明的协议
+/// - `config_tx`: 新 TLS 配置的广播发送器，所有 TLS 监听器订阅
+pub(crate) struct TlsReload {
+    pub(crate) param: TlsParam,
+    pub(crate) sni_certificates: Vec<(String, TlsParam)>,
+    pub(crate) protocol: ProtocolParam,
+    pub(crate) config_tx: broadcast::Sender<Arc<ServerConfig>>,
+}
+
+impl TlsReload {
+    /// 重新读取证书并广播给所有 TLS 监听器
+    fn reload(&self) -> io::Result<()> {
+        let config = load_tls_config(&self.param, &self.sni_certificates, &self.protocol)?;
+        if let Err(e) = self.config_tx.send(config) {
+            warn!("send tls config error:{e}");
+        }
+        Ok(())
+    }
+}
+
+/// 重新加载器
+///
+/// # 字段
+/// - `hooks`: 重新加载钩子
+/// - `settings`: 运行中可以修改的服务器参数
+/// - `tls`: 重新加载 TLS 证书所需的参数，没有启用 TLS 时为 None
+/// - `lock`: 保证同一时间只有一次重新加载
+pub(crate) struct Reloader {
+    hooks: Vec<Arc<dyn ReloadHook>>,
+    settings: RuntimeSettings,
+    tls: Option<TlsReload>,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl Reloader {
+    pub(crate) fn new(hooks: Vec<Arc<dyn ReloadHook>>, settings: RuntimeSettings, tls: Option<TlsReload>) -> Self {
+        Self {
+            hooks,
+            settings,
+            tls,
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// 调用所有重新加载钩子并重新读取 TLS 证书
+    ///
+    /// # 返回
+    /// - `Ok(())`: 所有步骤都成功
+    /// - `Err(io::Error)`: 失败步骤的错误，这些步骤保留原来的配置
+    pub(crate) async fn reload(&self) -> io::Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut errors = Vec::new();
+        for hook in &self.hooks {
+            if let Err(e) = hook.reload(&self.settings) {
+                error!("[reload]: {} failed, keep the old config: {e}", hook.name());
+                errors.push(format!("{}: {e}", hook.name()));
+            }
+        }
+        if let Err(e) = self.reload_tls() {
+            errors.push(format!("tls: {e}"));
+        }
+        match errors.is_empty() {
+            true => {
+                info!("[reload]: done");
+                Ok(())
+            }
+            false => Err(io::Error::other(errors.join("; "))),
+        }
+    }
+
+    /// 重新读取 TLS 证书，没有启用 TLS 时什么也不做
+    fn reload_tls(&self) -> io::Result<()> {
+        let Some(tls) = &self.tls else {
+            return Ok(());
+        };
+        match tls.reload() {
+            Ok(()) => {
+                info!("[reload]: tls config reloaded");
+                Ok(())
+            }
+            Err(e) => {
+                error!("[reload]: load tls config failed, keep the old certificate: {e}");
+                Err(e)
+            }
+        }
+    }
+}
+
+/// 启动重新加载任务
+///
+/// 每隔 REFRESH_INTERVAL (24小时) 重新读取证书，收到 `SIGHUP` 时完整地重新加载，
+/// 收到停止信号后退出
+///
+/// # 参数
+/// - `reloader`: 重新加载器
+/// - `hangup`: 已注册的 `SIGHUP` 信号，为 None 时不响应
+/// - `stop_rx`: 停止信号接收器
+pub(crate) fn spawn(reloader: Arc<Reloader>, #[cfg(unix)] hangup: Option<tokio::signal::unix::Signal>, mut stop_rx: broadcast::Receiver<()>) {
+    #[cfg(unix)]
+    let mut hangup = hangup;
+    tokio::spawn(async move {
+        if reloader.tls.is_some() {
+            info!("update tls config every {REFRESH_INTERVAL:?}");
+        }
+        loop {
+            #[cfg(unix)]
+            let hangup = recv_hangup(&mut hangup);
+            #[cfg(not(unix))]
+            let hangup = std::future::pending::<()>();
+            tokio::select! {
+                _ = time::sleep(REFRESH_INTERVAL), if reloader.tls.is_some() => {
+                    let _ = reloader.reload_tls();
+                }
+                _ = hangup => {
+                    info!("receive SIGHUP, reload");
+                    let _ = reloader.reload().await;
+                }
+                _ = stop_rx.recv() => break,
+            }
+        }
+    });
+}
+
+/// 等待 `SIGHUP`，没有注册时一直等待
+#[cfg(unix)]
+async fn recv_hangup(hangup: &mut Option<tokio::signal::unix::Signal>) {
+    if let Some(signal) = hangup {
+        if signal.recv().await.is_some() {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reloader() {
+        use std::sync::atomic::AtomicUsize;
+
+        let settings = RuntimeSettings::new(Duration::from_secs(120));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let hooks: Vec<Arc<dyn ReloadHook>> = vec![
+            Arc::new(move |settings: &RuntimeSettings| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                settings.set_idle_timeout(Duration::from_secs(30));
+                Ok(())
+            }),
+            Arc::new(|_: &RuntimeSettings| Err(io::Error::other("bad config"))),
+        ];
+        let (config_tx, mut config_rx) = broadcast::channel(1);
+        let tls = TlsReload {
+            param: TlsParam {
+                tls: true,
+                cert: "/nonexistent/cert.pem".to_string(),
+                key: "/nonexistent/privkey.pem".to_string(),
+            },
+            sni_certificates: Vec::new(),
+            protocol: ProtocolParam::default(),
+            config_tx,
+        };
+        let reloader = Reloader::new(hooks, settings.clone(), Some(tls));
+
+        // 失败的步骤不影响其它步骤，错误中包含所有失败的步骤
+        let err = reloader.reload().await.unwrap_err().to_string();
+        assert!(err.starts_with("reload hook: bad config; tls: "), "{err}");
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(settings.idle_timeout(), Duration::from_secs(30));
+        // 证书加载失败时不发送新配置
+        assert!(config_rx.try_recv().is_err());
+    }
+}
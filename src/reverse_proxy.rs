@@ -0,0 +1,542 @@
+//! # 反向代理模块
+//!
+//! 把路由下的请求转发到上游 HTTP 服务
+//!
+//! # 主要组件
+//! - `Upstream`: 上游地址和该上游的响应超时时间
+//! - `ReverseProxy`: 反向代理服务，实现了 `tower::Service`，可以通过 `Router::nest_service` 挂载到路径下，
+//!   在多个上游之间轮询
+//!
+//! # 转发规则
+//! - 去掉 hop-by-hop 请求头和响应头 (`Connection`、`Keep-Alive`、`Transfer-Encoding`、`Upgrade` 等，
+//!   以及 `Connection` 头中列出的头)
+//! - 追加 `X-Forwarded-For` 和 `Forwarded`，设置 `X-Forwarded-Proto` 和 `X-Forwarded-Host`
+//! - 请求体和响应体都以流的方式转发，不会缓存在内存中
+//! - 带 `Upgrade` 头的 HTTP/1.1 请求 (如 WebSocket) 在上游返回 101 后双向转发升级后的连接，
+//!   服务器停止接受新连接时升级后的连接随之关闭
+//! - 上游使用 HTTP/1.1 明文连接，不论客户端使用哪个 HTTP 版本
+
+use std::{
+    convert::Infallible,
+    io,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    response::{IntoResponse, Response},
+};
+use futures_util::future::BoxFuture;
+use hyper::{
+    HeaderMap, StatusCode, Uri, Version,
+    header::{self, HeaderName, HeaderValue},
+};
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::{TokioExecutor, TokioIo},
+};
+use log::{debug, warn};
+use tower::Service;
+
+use crate::{
+    connection::{ConnectionInfo, ShutdownSignal},
+    util::extractor::request_host,
+};
+
+/// 默认的上游响应超时时间
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 除 `Connection` 头中列出的头之外，总是需要去掉的 hop-by-hop 头
+const HOP_BY_HOP_HEADERS: [HeaderName; 8] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    HeaderName::from_static("proxy-connection"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+];
+
+/// `X-Forwarded-For` 请求头
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+/// `X-Forwarded-Proto` 请求头
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+/// `X-Forwarded-Host` 请求头
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+
+/// 上游服务
+///
+/// 从字符串解析时为 `http://host:port` 形式的地址，可以带路径前缀，例如 `http://127.0.0.1:8080/v1`，
+/// 转发时请求路径拼接在路径前缀之后
+///
+/// # 示例
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use axum_bootstrap::reverse_proxy::Upstream;
+///
+/// let upstream: Upstream = "http://127.0.0.1:8080/v1".parse().unwrap();
+/// let upstream = upstream.with_timeout(Duration::from_secs(5));
+/// assert_eq!(upstream.uri(), "http://127.0.0.1:8080/v1");
+/// assert!("https://example.com".parse::<Upstream>().is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Upstream {
+    uri: Uri,
+    timeout: Duration,
+}
+
+impl Upstream {
+    /// 上游地址
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// 设置等待上游响应头的超时时间
+    ///
+    /// # 参数
+    /// - `timeout`: 包括建立连接、发送请求头和接收响应头的总时间，不包括转发响应体的时间，默认 30 秒。
+    ///   超时返回 504
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 生成转发请求的 URI
+    ///
+    /// # 参数
+    /// - `uri`: 客户端请求的 URI，通过 `nest_service` 挂载时已去掉挂载路径
+    fn request_uri(&self, uri: &Uri) -> Result<Uri, hyper::http::Error> {
+        let prefix = self.uri.path().trim_end_matches('/');
+        let path_and_query = uri.path_and_query().map_or("/", |path_and_query| path_and_query.as_str());
+        Uri::builder()
+            .scheme("http")
+            .authority(self.uri.authority().map_or("", |authority| authority.as_str()))
+            .path_and_query(format!("{prefix}{path_and_query}"))
+            .build()
+    }
+}
+
+impl FromStr for Upstream {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid upstream {s}: {msg}"));
+        let uri: Uri = s.parse().map_err(|_| invalid("malformed uri"))?;
+        if uri.scheme_str() != Some("http") {
+            return Err(invalid("only http upstreams are supported"));
+        }
+        if uri.authority().is_none() {
+            return Err(invalid("missing host"));
+        }
+        if uri.query().is_some() {
+            return Err(invalid("query is not allowed"));
+        }
+        Ok(Self {
+            uri,
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+}
+
+/// 反向代理服务
+///
+/// 可以克隆，所有克隆共享同一个连接池和轮询位置。通过 [`axum::Router::nest_service`] 挂载时，
+/// 转发的路径不包含挂载路径；通过 [`axum::Router::route_service`] 挂载时转发完整路径
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use axum::Router;
+/// use axum_bootstrap::{generate_shutdown_receiver, new_server, reverse_proxy::ReverseProxy};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let proxy = ReverseProxy::new([
+///         "http://10.0.0.1:8080".parse().unwrap(),
+///         "http://10.0.0.2:8080".parse::<axum_bootstrap::reverse_proxy::Upstream>().unwrap().with_timeout(Duration::from_secs(5)),
+///     ]);
+///     // /api/users 转发到 http://10.0.0.x:8080/users
+///     let router = Router::new().nest_service("/api", proxy);
+///     new_server(8080, router, generate_shutdown_receiver()).run().await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReverseProxy {
+    upstreams: Arc<[Upstream]>,
+    next: Arc<AtomicUsize>,
+    client: Client<HttpConnector, Body>,
+    preserve_host: bool,
+}
+
+impl ReverseProxy {
+    /// 创建反向代理
+    ///
+    /// # 参数
+    /// - `upstreams`: 上游服务，按轮询的方式选择。为空时所有请求返回 502
+    pub fn new(upstreams: impl IntoIterator<Item = Upstream>) -> Self {
+        let mut connector = HttpConnector::new();
+        connector.set_nodelay(true);
+        Self {
+            upstreams: upstreams.into_iter().collect(),
+            next: Arc::default(),
+            client: Client::builder(TokioExecutor::new()).build(connector),
+            preserve_host: false,
+        }
+    }
+
+    /// 设置是否保留客户端请求的 `Host` 头
+    ///
+    /// # 参数
+    /// - `preserve_host`: 为 true 时转发客户端请求的 `Host`，默认为 false，使用上游地址作为 `Host`
+    pub fn with_preserve_host(mut self, preserve_host: bool) -> Self {
+        self.preserve_host = preserve_host;
+        self
+    }
+
+    /// 按轮询顺序选择下一个上游
+    fn next_upstream(&self) -> Option<&Upstream> {
+        match self.upstreams.len() {
+            0 => None,
+            len => self.upstreams.get(self.next.fetch_add(1, Ordering::Relaxed) % len),
+        }
+    }
+
+    /// 转发请求并返回上游的响应
+    async fn forward(self, mut req: Request) -> Response {
+        let Some(upstream) = self.next_upstream() else {
+            warn!("[reverse proxy]: no upstream for {} {}", req.method(), req.uri());
+            return StatusCode::BAD_GATEWAY.into_response();
+        };
+        let upgrade = upgrade_protocol(&req).map(|protocol| (protocol, hyper::upgrade::on(&mut req)));
+        let shutdown = req.extensions().get::<ShutdownSignal>().cloned();
+        let (mut parts, body) = req.into_parts();
+        let uri = match upstream.request_uri(&parts.uri) {
+            Ok(uri) => uri,
+            Err(e) => {
+                warn!("[reverse proxy]: build uri for {} error: {e}", parts.uri);
+                return StatusCode::BAD_GATEWAY.into_response();
+            }
+        };
+        let host = request_host(&parts.headers, &parts.uri).and_then(|host| HeaderValue::from_str(host).ok());
+        remove_hop_by_hop_headers(&mut parts.headers);
+        if let Some((protocol, _)) = &upgrade {
+            parts.headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+            parts.headers.insert(header::UPGRADE, protocol.clone());
+        }
+        add_forwarded_headers(&mut parts, host.as_ref());
+        match host.filter(|_| self.preserve_host) {
+            Some(host) => parts.headers.insert(header::HOST, host),
+            None => parts.headers.remove(header::HOST),
+        };
+        let method = parts.method.clone();
+        parts.uri = uri;
+        parts.version = Version::HTTP_11;
+        let request = Request::from_parts(parts, body);
+        let mut response = match tokio::time::timeout(upstream.timeout, self.client.request(request)).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                warn!("[reverse proxy]: {method} {} error: {e}", upstream.uri);
+                return StatusCode::BAD_GATEWAY.into_response();
+            }
+            Err(_) => {
+                warn!("[reverse proxy]: {method} {} timeout after {:?}", upstream.uri, upstream.timeout);
+                return StatusCode::GATEWAY_TIMEOUT.into_response();
+            }
+        };
+        if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+            remove_hop_by_hop_headers(response.headers_mut());
+            return response.map(Body::new);
+        }
+        // 上游同意升级，响应中的 Connection 和 Upgrade 头原样返回给客户端
+        let Some((_, client_upgrade)) = upgrade else {
+            warn!("[reverse proxy]: unexpected 101 response from {}", upstream.uri);
+            return StatusCode::BAD_GATEWAY.into_response();
+        };
+        let upstream_upgrade = hyper::upgrade::on(&mut response);
+        tokio::spawn(async move {
+            let (client, upstream) = match tokio::try_join!(client_upgrade, upstream_upgrade) {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    warn!("[reverse proxy]: upgrade error: {e}");
+                    return;
+                }
+            };
+            let (mut client, mut upstream) = (TokioIo::new(client), TokioIo::new(upstream));
+            let shutdown = async {
+                match shutdown {
+                    Some(shutdown) => shutdown.wait().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                result = tokio::io::copy_bidirectional(&mut client, &mut upstream) => {
+                    if let Err(e) = result {
+                        debug!("[reverse proxy]: upgraded connection error: {e}");
+                    }
+                }
+                _ = shutdown => {}
+            }
+        });
+        response.map(Body::new)
+    }
+}
+
+impl Service<Request> for ReverseProxy {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let proxy = self.clone();
+        Box::pin(async move { Ok(proxy.forward(req).await) })
+    }
+}
+
+/// 获取 HTTP/1.1 升级请求的目标协议
+///
+/// 只有 `Connection` 头包含 `upgrade` 且带有 `Upgrade` 头的请求才是升级请求
+fn upgrade_protocol(req: &Request) -> Option<HeaderValue> {
+    let upgrade = req
+        .headers()
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|token| token.trim().eq_ignore_ascii_case("upgrade"));
+    match (req.version(), upgrade) {
+        (Version::HTTP_11, true) => req.headers().get(header::UPGRADE).cloned(),
+        _ => None,
+    }
+}
+
+/// 去掉 hop-by-hop 头，包括 `Connection` 头中列出的头
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let listed: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|token| HeaderName::from_str(token.trim()).ok())
+        .collect();
+    for name in listed.iter().chain(HOP_BY_HOP_HEADERS.iter()).chain([&header::UPGRADE]) {
+        headers.remove(name);
+    }
+}
+
+/// 添加 `X-Forwarded-For`、`Forwarded`、`X-Forwarded-Proto` 和 `X-Forwarded-Host` 头
+///
+/// 客户端地址取自 `ConnectInfo<SocketAddr>`，Unix domain socket 连接没有客户端地址，`Forwarded` 中记为 `unknown`；
+/// 连接使用 TLS 时协议为 `https`
+///
+/// # 参数
+/// - `parts`: 转发的请求
+/// - `host`: 客户端请求的 Host
+fn add_forwarded_headers(parts: &mut hyper::http::request::Parts, host: Option<&HeaderValue>) {
+    let client_ip = parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_canonical())
+        .filter(|ip| !ip.is_unspecified());
+    let tls = parts.extensions.get::<ConnectionInfo>().is_some_and(ConnectionInfo::is_tls);
+    let proto = if tls { "https" } else { "http" };
+    let headers = &mut parts.headers;
+    if let Some(ip) = client_ip {
+        let forwarded_for = match headers.get(X_FORWARDED_FOR).and_then(|value| value.to_str().ok()) {
+            Some(previous) => format!("{previous}, {ip}"),
+            None => ip.to_string(),
+        };
+        if let Ok(value) = HeaderValue::try_from(forwarded_for) {
+            headers.insert(X_FORWARDED_FOR, value);
+        }
+    }
+    // RFC 7239: IPv6 地址需要加方括号并用引号括起来
+    let mut forwarded = match client_ip {
+        Some(std::net::IpAddr::V6(ip)) => format!("for=\"[{ip}]\";proto={proto}"),
+        Some(ip) => format!("for={ip};proto={proto}"),
+        None => format!("for=unknown;proto={proto}"),
+    };
+    if let Some(host) = host.and_then(|host| host.to_str().ok()) {
+        forwarded.push_str(&format!(";host=\"{host}\""));
+    }
+    if let Some(previous) = headers.get(header::FORWARDED).and_then(|value| value.to_str().ok()) {
+        forwarded = format!("{previous}, {forwarded}");
+    }
+    if let Ok(value) = HeaderValue::try_from(forwarded) {
+        headers.insert(header::FORWARDED, value);
+    }
+    headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static(proto));
+    if let Some(host) = host {
+        headers.insert(X_FORWARDED_HOST, host.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    #[test]
+    fn test_forward_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONNECTION, HeaderValue::from_static("keep-alive, x-custom"));
+        headers.insert("x-custom", HeaderValue::from_static("1"));
+        headers.insert(header::TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        headers.insert(header::ACCEPT, HeaderValue::from_static("*/*"));
+        remove_hop_by_hop_headers(&mut headers);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.contains_key(header::ACCEPT));
+
+        let mut request = Request::builder()
+            .header(X_FORWARDED_FOR, "10.0.0.1")
+            .header(header::FORWARDED, "for=10.0.0.1")
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 1234))));
+        let (mut parts, _) = request.into_parts();
+        add_forwarded_headers(&mut parts, Some(&HeaderValue::from_static("example.com")));
+        assert_eq!(parts.headers[X_FORWARDED_FOR], "10.0.0.1, ::1");
+        assert_eq!(parts.headers[header::FORWARDED], "for=10.0.0.1, for=\"[::1]\";proto=http;host=\"example.com\"");
+        assert_eq!(parts.headers[X_FORWARDED_PROTO], "http");
+        assert_eq!(parts.headers[X_FORWARDED_HOST], "example.com");
+    }
+
+    #[tokio::test]
+    async fn test_reverse_proxy() {
+        use axum::routing::any;
+
+        let (_shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let mut upstreams = Vec::new();
+        let mut handles = Vec::new();
+        for name in ["a", "b"] {
+            let router = axum::Router::new()
+                .route(
+                    "/echo/{*path}",
+                    any(move |req: Request| async move {
+                        let head = {
+                            let header = |name: &str| req.headers().get(name).map_or("-", |value| value.to_str().unwrap()).to_string();
+                            format!(
+                                "{name} {} {} xff={} fwd={} proto={} host={} conn={}\n",
+                                req.method(),
+                                req.uri(),
+                                header("x-forwarded-for"),
+                                header("forwarded"),
+                                header("x-forwarded-proto"),
+                                header("host"),
+                                header("connection"),
+                            )
+                        };
+                        let body = axum::body::to_bytes(req.into_body(), usize::MAX).await.unwrap();
+                        format!("{head}{}", String::from_utf8_lossy(&body))
+                    }),
+                )
+                .route(
+                    "/slow",
+                    any(|| async {
+                        tokio::time::sleep(Duration::from_millis(500)).await;
+                        "slow"
+                    }),
+                )
+                .route(
+                    "/upgrade",
+                    any(|mut req: Request| async move {
+                        let on_upgrade = hyper::upgrade::on(&mut req);
+                        tokio::spawn(async move {
+                            let mut io = TokioIo::new(on_upgrade.await.unwrap());
+                            let mut buf = [0; 4];
+                            io.read_exact(&mut buf).await.unwrap();
+                            io.write_all(&buf).await.unwrap();
+                        });
+                        Response::builder()
+                            .status(StatusCode::SWITCHING_PROTOCOLS)
+                            .header(header::CONNECTION, "upgrade")
+                            .header(header::UPGRADE, "echo")
+                            .body(Body::empty())
+                            .unwrap()
+                    }),
+                );
+            let (handle, addr) = crate::spawn_loopback(crate::new_server(0, router, shutdown_rx.resubscribe())).await;
+            upstreams.push(format!("http://{addr}").parse::<Upstream>().unwrap());
+            handles.push(handle);
+        }
+        let slow = upstreams[0].clone().with_timeout(Duration::from_millis(100));
+        let router = axum::Router::new()
+            .nest_service("/api", ReverseProxy::new(upstreams))
+            .route_service("/slow", ReverseProxy::new([slow]));
+        let (handle, addr) = crate::spawn_loopback(crate::new_server(0, router, shutdown_rx)).await;
+        let request = |raw: String| async move {
+            let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+            client.write_all(raw.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        // 轮询上游，转发时去掉挂载路径，追加转发头
+        let mut names = Vec::new();
+        for _ in 0..2 {
+            let response =
+                request("GET /api/echo/x?q=1 HTTP/1.1\r\nHost: example.com\r\nX-Forwarded-For: 10.0.0.1\r\nConnection: close\r\n\r\n".into()).await;
+            assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+            let body = response.split("\r\n\r\n").nth(1).unwrap();
+            let upstream = &handles[names.len()].local_addrs()[0];
+            assert!(body.ends_with(&format!(
+                " GET /echo/x?q=1 xff=10.0.0.1, 127.0.0.1 fwd=for=127.0.0.1;proto=http;host=\"example.com\" proto=http host={upstream} conn=-\n"
+            )));
+            names.push(body[..1].to_string());
+        }
+        assert_eq!(names, ["a", "b"]);
+
+        // 请求体以流的方式转发
+        let response = request(
+            "POST /api/echo/body HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n".into(),
+        )
+        .await;
+        assert!(response.ends_with("\nhello world"), "{response}");
+
+        let response = request("GET /slow HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n".into()).await;
+        assert!(response.starts_with("HTTP/1.1 504"), "{response}");
+
+        // 升级后的连接双向转发
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"GET /api/upgrade HTTP/1.1\r\nHost: example.com\r\nConnection: Upgrade\r\nUpgrade: echo\r\n\r\n")
+            .await
+            .unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(client.read_u8().await.unwrap());
+        }
+        let head = String::from_utf8(head).unwrap().to_ascii_lowercase();
+        assert!(head.starts_with("http/1.1 101"), "{head}");
+        assert!(head.contains("upgrade: echo\r\n"));
+        client.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        handle.stop().await;
+        for handle in handles {
+            handle.stop().await;
+        }
+    }
+}
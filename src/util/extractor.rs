@@ -17,9 +17,12 @@
 
 use std::io;
 
-use axum::{extract::FromRequestParts, http::request::Parts};
+use axum::{
+    extract::FromRequestParts,
+    http::{HeaderMap, Uri, header, request::Parts},
+};
 
-use crate::error::AppError;
+use crate::{connection::ConnectionInfo, error::AppError};
 
 /// Host extractor
 ///
@@ -74,24 +77,56 @@ where
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        // 以Host header为优先
-        // HTTP1 要求必须传递 Host header
-        // HTTP2 中用于特殊情况的需求，例如反向代理指定 Host
-        if let Some(host) = parts.headers.get("host") {
-            if let Ok(host_str) = host.to_str() {
-                return Ok(Host(host_str.to_string()));
-            }
+        match request_host(&parts.headers, &parts.uri) {
+            Some(host) => Ok(Host(host.to_string())),
+            // 无法获取 Host 信息
+            None => Err(AppError::new(io::Error::new(io::ErrorKind::InvalidInput, "Missing Host information in request"))),
         }
+    }
+}
 
-        // HTTP/2 使用 :authority pseudo-header
-        // 在 Axum/Hyper 中，:authority 会被转换为 URI 的 authority 部分
-        if let Some(authority) = parts.uri.authority() {
-            return Ok(Host(authority.to_string()));
+/// 连接信息 extractor
+///
+/// 从请求的 extensions 中获取服务器为每个连接插入的 [`ConnectionInfo`]，
+/// 不是由本 crate 的服务器处理的请求 (例如直接调用 Router 的测试) 没有连接信息，返回 500 错误
+impl<S> FromRequestParts<S> for ConnectionInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<ConnectionInfo>() {
+            Some(info) => Ok(info.clone()),
+            None => Err(AppError::new(io::Error::other("Missing connection info in request"))),
         }
+    }
+}
 
-        // 无法获取 Host 信息
-        Err(AppError::new(io::Error::new(io::ErrorKind::InvalidInput, "Missing Host information in request")))
+/// 获取请求的 Host (可能包含端口)
+///
+/// 与 [`Host`] extractor 使用相同的规则，供服务器内部 (例如 HTTPS 重定向) 复用
+///
+/// # 参数
+/// - `headers`: 请求头
+/// - `uri`: 请求 URI
+///
+/// # 返回
+/// - `Some(&str)`: Host 信息
+/// - `None`: 请求中没有 Host 信息
+pub(crate) fn request_host<'a>(headers: &'a HeaderMap, uri: &'a Uri) -> Option<&'a str> {
+    // 以Host header为优先
+    // HTTP1 要求必须传递 Host header
+    // HTTP2 中用于特殊情况的需求，例如反向代理指定 Host
+    if let Some(host) = headers.get(header::HOST) {
+        if let Ok(host_str) = host.to_str() {
+            return Some(host_str);
+        }
     }
+
+    // HTTP/2 使用 :authority pseudo-header
+    // 在 Axum/Hyper 中，:authority 会被转换为 URI 的 authority 部分
+    uri.authority().map(|authority| authority.as_str())
 }
 
 #[cfg(test)]
@@ -99,6 +134,14 @@ mod tests {
     use super::*;
     use axum::http::Request;
 
+    #[tokio::test]
+    async fn test_missing_connection_info() {
+        let req = Request::builder().uri("/test").body(()).unwrap();
+
+        let (mut parts, _) = req.into_parts();
+        assert!(ConnectionInfo::from_request_parts(&mut parts, &()).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_host_from_header() {
         let req = Request::builder().uri("/test").header("host", "example.com:8080").body(()).unwrap();
@@ -3,55 +3,144 @@
 //! 提供网络 IO 相关的工具函数和类型
 //!
 //! # 主要组件
-//! - `create_dual_stack_listener`: 创建支持 IPv4/IPv6 双栈的监听器
+//! - `create_listener`: 创建绑定到任意地址的监听器，`[::]` 时为 IPv4/IPv6 双栈
+//! - `create_listener_with_backlog`: 创建指定监听队列大小的监听器
+//! - `create_reuse_port_listeners`: 创建多个 `SO_REUSEPORT` 监听器，由内核分发连接 (仅 Unix 平台)
+//! - `create_unix_listener`: 创建 Unix domain socket 监听器 (仅 Unix 平台)
+//! - `systemd_listeners`: 获取 systemd socket activation 传递的监听器 (仅 Unix 平台)
+//! - `Listener` / `Stream`: 统一 TCP 与 Unix domain socket 的监听器和连接
+//! - `PeerAddr`: 连接对端的描述
+//! - `ResetOnDrop`: 关闭时可以发送 TCP RST 的连接包装器
+//! - `CountingIO`: 统计读写字节数的 IO 包装器
 //! - `TimeoutIO`: 为 IO 流添加空闲超时检测
 
 use socket2::{Domain, Protocol, Socket, Type};
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
 
-/// 创建 IPv4/IPv6 双栈监听器
-///
-/// 创建一个同时支持 IPv4 和 IPv6 连接的 TCP 监听器
+use crate::util::format::SocketAddrFormat;
+
+/// 默认监听队列大小 (backlog)
+pub const DEFAULT_BACKLOG: u32 = 1024;
+
+/// 创建绑定到指定地址的 TCP 监听器
 ///
 /// # 参数
-/// - `port`: 监听端口
+/// - `addr`: 监听地址，例如 `127.0.0.1:9000`、`[::1]:443`
 ///
 /// # 返回
 /// - `Ok(TcpListener)`: 配置好的 TCP 监听器
 /// - `Err(io::Error)`: 创建或配置失败
 ///
 /// # 配置说明
-/// - 绑定到 `[::]` (所有 IPv6 地址)
-/// - 设置 `IPV6_V6ONLY=false`，支持 IPv4 映射
-/// - 监听队列大小 (backlog): 1024
+/// - 地址为 `[::]` 时设置 `IPV6_V6ONLY=false`，同时接受 IPv4 映射连接 (双栈)
+/// - 监听队列大小 (backlog): [`DEFAULT_BACKLOG`]
 /// - 非阻塞模式
 /// - 非 Windows 平台启用 `SO_REUSEADDR`
 ///
 /// # 示例
 ///
 /// ```no_run
-/// use axum_bootstrap::util::io::create_dual_stack_listener;
+/// use axum_bootstrap::util::io::create_listener;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let listener = create_listener("127.0.0.1:8080".parse().unwrap()).await.unwrap();
+/// }
+/// ```
+pub async fn create_listener(addr: SocketAddr) -> io::Result<TcpListener> {
+    create_listener_with_backlog(addr, DEFAULT_BACKLOG).await
+}
+
+/// 创建绑定到指定地址、指定监听队列大小的 TCP 监听器
+///
+/// # 参数
+/// - `addr`: 监听地址
+/// - `backlog`: 监听队列大小，实际生效值受系统参数 (例如 Linux 的 `net.core.somaxconn`) 限制
+///
+/// # 返回
+/// - `Ok(TcpListener)`: 配置好的 TCP 监听器
+/// - `Err(io::Error)`: 创建或配置失败
+pub async fn create_listener_with_backlog(addr: SocketAddr, backlog: u32) -> io::Result<TcpListener> {
+    let socket = tcp_socket(addr)?;
+    bind_tcp_socket(socket, addr, backlog)
+}
+
+/// 创建多个绑定到同一地址的 `SO_REUSEPORT` TCP 监听器
+///
+/// 每个监听器拥有独立的 accept 队列，内核按连接的四元组哈希把新连接分发到各个监听器，
+/// 配合每个监听器一个 accept 循环，可以避免单个 accept 循环成为高连接速率下的瓶颈
+///
+/// 端口为 0 时，第一个监听器绑定的随机端口会被其它监听器复用
+///
+/// # 参数
+/// - `addr`: 监听地址
+/// - `count`: 监听器数量，至少为 1
+/// - `backlog`: 每个监听器的监听队列大小
+///
+/// # 返回
+/// - `Ok(Vec<TcpListener>)`: 配置好的 TCP 监听器
+/// - `Err(io::Error)`: 创建或配置失败
+///
+/// # 注意
+/// 同一用户的其它进程也可以通过 `SO_REUSEPORT` 绑定同一地址并分走连接
+///
+/// # 示例
+///
+/// ```no_run
+/// use axum_bootstrap::util::io::{DEFAULT_BACKLOG, create_reuse_port_listeners};
 ///
 /// #[tokio::main]
 /// async fn main() {
-///     let listener = create_dual_stack_listener(8080).await.unwrap();
+///     let listeners = create_reuse_port_listeners("[::]:8080".parse().unwrap(), 4, DEFAULT_BACKLOG).unwrap();
+///     assert_eq!(listeners.len(), 4);
 /// }
 /// ```
-pub(crate) async fn create_dual_stack_listener(port: u16) -> io::Result<TcpListener> {
-    // 创建一个 IPv6 的 socket
-    let domain = Domain::IPV6;
+#[cfg(unix)]
+pub fn create_reuse_port_listeners(addr: SocketAddr, count: usize, backlog: u32) -> io::Result<Vec<TcpListener>> {
+    let mut addr = addr;
+    let mut listeners = Vec::with_capacity(count.max(1));
+    for _ in 0..count.max(1) {
+        let socket = tcp_socket(addr)?;
+        socket.set_reuse_port(true)?;
+        let listener = bind_tcp_socket(socket, addr, backlog)?;
+        addr = listener.local_addr()?;
+        listeners.push(listener);
+    }
+    Ok(listeners)
+}
+
+/// 创建 TCP socket 并设置通用选项
+///
+/// # 参数
+/// - `addr`: 监听地址，决定地址族和是否启用双栈
+fn tcp_socket(addr: SocketAddr) -> io::Result<Socket> {
+    let domain = Domain::for_address(addr);
     let socket = Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
 
     #[cfg(not(windows))]
     socket.set_reuse_address(true)?; // 设置 reuse_address 以支持快速重启
 
-    // 支持 IPv4 + IPv6 双栈
-    socket.set_only_v6(false)?;
+    // 绑定到 [::] 时支持 IPv4 + IPv6 双栈
+    if let SocketAddr::V6(v6) = addr {
+        if v6.ip().is_unspecified() {
+            socket.set_only_v6(false)?;
+        }
+    }
+    Ok(socket)
+}
 
+/// 绑定并监听 TCP socket，转换为 tokio 监听器
+///
+/// # 参数
+/// - `socket`: 已设置好选项的 socket
+/// - `addr`: 监听地址
+/// - `backlog`: 监听队列大小
+fn bind_tcp_socket(socket: Socket, addr: SocketAddr, backlog: u32) -> io::Result<TcpListener> {
     // 绑定 socket 到地址和端口
-    let addr = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 0], port));
     socket.bind(&addr.into())?;
-    socket.listen(1024)?; // 监听，1024 为 backlog 的大小
+    socket.listen(backlog.min(i32::MAX as u32) as i32)?;
 
     // 将 socket2::Socket 转换为 std::net::TcpListener
     let std_listener = std::net::TcpListener::from(socket);
@@ -60,11 +149,422 @@ pub(crate) async fn create_dual_stack_listener(port: u16) -> io::Result<TcpListe
     TcpListener::from_std(std_listener)
 }
 
+/// 创建 Unix domain socket 监听器
+///
+/// # 参数
+/// - `path`: socket 文件路径
+/// - `mode`: socket 文件权限 (例如 `0o660`)，为 None 时保持 umask 决定的默认权限
+///
+/// # 返回
+/// - `Ok(UnixListener)`: 配置好的监听器
+/// - `Err(io::Error)`: 创建或配置失败
+///
+/// # 配置说明
+/// - socket 文件已存在且无法连接 (上次进程退出时遗留) 时，先删除再绑定
+/// - socket 文件已存在且仍可连接时，返回 `AddrInUse` 错误
+/// - 路径存在但不是 socket 文件时，返回 `AlreadyExists` 错误，不会删除该文件
+/// - 配置了 `mode` 时，绑定后设置 socket 文件权限
+#[cfg(unix)]
+pub fn create_unix_listener(path: &std::path::Path, mode: Option<u32>) -> io::Result<UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => match std::os::unix::net::UnixStream::connect(path) {
+            Ok(_) => {
+                return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("unix socket {} is in use", path.display())));
+            }
+            Err(_) => {
+                log::info!("remove stale unix socket {}", path.display());
+                std::fs::remove_file(path)?;
+            }
+        },
+        Ok(_) => {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and is not a unix socket", path.display())));
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
+
+/// systemd socket activation 传递的第一个文件描述符
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: std::os::fd::RawFd = 3;
+
+/// 获取 systemd socket activation 传递的监听器
+///
+/// 按照 `sd_listen_fds(3)` 的协议读取 `LISTEN_PID`、`LISTEN_FDS` 和 `LISTEN_FDNAMES` 环境变量，
+/// 将从 3 开始的文件描述符转换为监听器
+///
+/// # 返回
+/// - `Ok(Some(Vec<(Listener, String)>))`: 继承的监听器及其名称 (`FileDescriptorName=`，未设置时为 `unknown`)
+/// - `Ok(None)`: 没有设置 `LISTEN_FDS`，或 `LISTEN_PID` 不是当前进程
+/// - `Err(io::Error)`: 环境变量格式错误，或文件描述符不是 TCP / Unix domain socket 的流式监听 socket
+///
+/// # 说明
+/// 继承的文件描述符会被设置为 `FD_CLOEXEC` 和非阻塞模式；环境变量不会被清除，
+/// 子进程会因为 `LISTEN_PID` 不匹配而忽略它们
+#[cfg(unix)]
+pub fn systemd_listeners() -> io::Result<Option<Vec<(Listener, String)>>> {
+    let var = |name: &str| std::env::var(name).ok();
+    listeners_from_fds(var("LISTEN_PID").as_deref(), var("LISTEN_FDS").as_deref(), var("LISTEN_FDNAMES").as_deref(), SD_LISTEN_FDS_START)
+}
+
+/// 按照 `sd_listen_fds(3)` 的协议，将继承的文件描述符转换为监听器
+///
+/// # 参数
+/// - `listen_pid`: `LISTEN_PID` 的值
+/// - `listen_fds`: `LISTEN_FDS` 的值
+/// - `listen_fdnames`: `LISTEN_FDNAMES` 的值，以 `:` 分隔
+/// - `start`: 第一个文件描述符
+#[cfg(unix)]
+fn listeners_from_fds(
+    listen_pid: Option<&str>, listen_fds: Option<&str>, listen_fdnames: Option<&str>, start: std::os::fd::RawFd,
+) -> io::Result<Option<Vec<(Listener, String)>>> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let Some(listen_fds) = listen_fds else {
+        return Ok(None);
+    };
+    if listen_pid.and_then(|pid| pid.trim().parse::<u32>().ok()) != Some(std::process::id()) {
+        return Ok(None);
+    }
+    let count: std::os::fd::RawFd = listen_fds
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid LISTEN_FDS: {listen_fds}")))?;
+    let names: Vec<&str> = listen_fdnames.map(|names| names.split(':').collect()).unwrap_or_default();
+    (0..count)
+        .map(|i| {
+            // SAFETY: LISTEN_PID 指向当前进程，按照 sd_listen_fds 协议，
+            // 从 start 开始的 LISTEN_FDS 个文件描述符由 systemd 传递给当前进程独占使用
+            let fd = unsafe { OwnedFd::from_raw_fd(start + i) };
+            let name = names.get(i as usize).copied().unwrap_or("unknown");
+            Ok((listener_from_fd(fd)?, name.to_string()))
+        })
+        .collect::<io::Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// 将已经处于监听状态的文件描述符转换为监听器
+///
+/// # 参数
+/// - `fd`: 监听 socket 的文件描述符
+///
+/// # 返回
+/// - `Ok(Listener)`: TCP 或 Unix domain socket 监听器
+/// - `Err(io::Error)`: 不是流式 socket，或地址族不受支持
+#[cfg(unix)]
+pub fn listener_from_fd(fd: std::os::fd::OwnedFd) -> io::Result<Listener> {
+    let socket = Socket::from(fd);
+    if socket.r#type()? != Type::STREAM {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "inherited fd is not a stream socket"));
+    }
+    socket.set_cloexec(true)?;
+    socket.set_nonblocking(true)?;
+    let local_addr = socket.local_addr()?;
+    if local_addr.as_socket().is_some() {
+        Ok(Listener::Tcp(TcpListener::from_std(std::net::TcpListener::from(socket))?))
+    } else if local_addr.is_unix() {
+        let path = local_addr.as_pathname().map(std::path::Path::to_path_buf).unwrap_or_default();
+        let listener = std::os::unix::net::UnixListener::from(std::os::fd::OwnedFd::from(socket));
+        Ok(Listener::Unix(UnixListener::from_std(listener)?, path))
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "inherited fd is neither a tcp nor a unix socket"))
+    }
+}
+
+/// 连接对端的描述
+///
+/// TCP 连接使用对端的 `SocketAddr`；Unix domain socket 连接没有对端地址，
+/// 使用监听的 socket 文件路径和对端进程凭证 (`SO_PEERCRED`) 描述
+///
+/// 该类型会以 `ConnectInfo<PeerAddr>` 的形式插入每个请求的 extensions，
+/// 拦截器和路由处理器都可以获取
+///
+/// # 变体
+/// - `Tcp(SocketAddr)`: TCP 连接的对端地址
+/// - `Unix(UnixPeerAddr)`: Unix domain socket 连接的对端信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(UnixPeerAddr),
+}
+
+/// Unix domain socket 连接的对端信息
+///
+/// # 字段
+/// - `path`: 接受该连接的 socket 文件路径
+/// - `pid`: 对端进程 ID (平台不支持时为 None)
+/// - `uid`: 对端进程用户 ID
+/// - `gid`: 对端进程组 ID
+#[cfg(unix)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnixPeerAddr {
+    pub path: std::path::PathBuf,
+    pub pid: Option<i32>,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl PeerAddr {
+    /// 没有对端 `SocketAddr` 时使用的占位地址 (`0.0.0.0:0`)
+    pub const UNSPECIFIED_SOCKET_ADDR: SocketAddr = SocketAddr::V4(std::net::SocketAddrV4::new(std::net::Ipv4Addr::UNSPECIFIED, 0));
+
+    /// 获取对端的 `SocketAddr`
+    ///
+    /// # 返回
+    /// - TCP 连接: 对端地址
+    /// - Unix domain socket 连接: [`PeerAddr::UNSPECIFIED_SOCKET_ADDR`]
+    pub fn socket_addr(&self) -> SocketAddr {
+        match self {
+            PeerAddr::Tcp(addr) => *addr,
+            #[cfg(unix)]
+            PeerAddr::Unix(_) => Self::UNSPECIFIED_SOCKET_ADDR,
+        }
+    }
+}
+
+impl std::fmt::Display for PeerAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeerAddr::Tcp(addr) => write!(f, "{}", SocketAddrFormat(addr)),
+            #[cfg(unix)]
+            PeerAddr::Unix(peer) => match peer.pid {
+                Some(pid) => write!(f, "unix:{} pid={} uid={}", peer.path.display(), pid, peer.uid),
+                None => write!(f, "unix:{} uid={}", peer.path.display(), peer.uid),
+            },
+        }
+    }
+}
+
+impl From<SocketAddr> for PeerAddr {
+    fn from(addr: SocketAddr) -> Self {
+        PeerAddr::Tcp(addr)
+    }
+}
+
+/// 监听器，统一 TCP 和 Unix domain socket
+///
+/// # 变体
+/// - `Tcp`: TCP 监听器
+/// - `Unix`: Unix domain socket 监听器，附带 socket 文件路径
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, std::path::PathBuf),
+}
+
+impl Listener {
+    /// 接受新连接
+    ///
+    /// # 返回
+    /// - `Ok((Stream, PeerAddr))`: 新连接和对端描述
+    /// - `Err(io::Error)`: 接受连接失败
+    pub async fn accept(&self) -> io::Result<(Stream, PeerAddr)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((Stream::Tcp(stream), PeerAddr::Tcp(addr)))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, path) => {
+                let (stream, _) = listener.accept().await?;
+                let cred = stream.peer_cred()?;
+                let peer = UnixPeerAddr {
+                    path: path.clone(),
+                    pid: cred.pid(),
+                    uid: cred.uid(),
+                    gid: cred.gid(),
+                };
+                Ok((Stream::Unix(stream), PeerAddr::Unix(peer)))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Listener::Tcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "{addr}"),
+                Err(_) => write!(f, "<unknown tcp>"),
+            },
+            #[cfg(unix)]
+            Listener::Unix(_, path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl From<TcpListener> for Listener {
+    fn from(listener: TcpListener) -> Self {
+        Listener::Tcp(listener)
+    }
+}
+
+#[cfg(unix)]
+impl std::os::fd::AsFd for Listener {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        match self {
+            Listener::Tcp(listener) => listener.as_fd(),
+            Listener::Unix(listener, _) => listener.as_fd(),
+        }
+    }
+}
+
+/// 网络连接，统一 TCP 和 Unix domain socket
+///
+/// # 变体
+/// - `Tcp`: TCP 连接
+/// - `Unix`: Unix domain socket 连接
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    /// 获取连接的本地地址
+    ///
+    /// # 返回
+    /// - TCP 连接: 本地地址，监听 `[::]` 时为客户端实际连接的地址
+    /// - Unix domain socket 连接: None
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Stream::Tcp(stream) => stream.local_addr().ok(),
+            #[cfg(unix)]
+            Stream::Unix(_) => None,
+        }
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut tokio::io::ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Stream::Tcp(stream) => stream.is_write_vectored(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.is_write_vectored(),
+        }
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[std::io::IoSlice<'_>]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+        }
+    }
+}
+
+/// 关闭时可以发送 TCP RST 的连接包装器
+///
+/// 标记设置为 true 后，连接被丢弃时通过 `SO_LINGER=0` 发送 RST 而不是 FIN，
+/// 对端无法区分这是服务器主动拒绝还是网络故障。Unix domain socket 没有 RST，直接关闭
+pub(crate) struct ResetOnDrop {
+    inner: Stream,
+    reset: Arc<AtomicBool>,
+}
+
+impl ResetOnDrop {
+    /// 创建新的 ResetOnDrop 包装器
+    ///
+    /// # 返回
+    /// 包装后的连接和控制关闭方式的标记
+    pub(crate) fn new(inner: Stream) -> (Self, Arc<AtomicBool>) {
+        let reset = Arc::new(AtomicBool::new(false));
+        (Self { inner, reset: reset.clone() }, reset)
+    }
+}
+
+impl Drop for ResetOnDrop {
+    fn drop(&mut self) {
+        if let Stream::Tcp(stream) = &self.inner {
+            if self.reset.load(Ordering::Relaxed) {
+                // 非阻塞 socket 设置 SO_LINGER=0 后 close 立即返回并发送 RST
+                let _ = socket2::SockRef::from(stream).set_linger(Some(Duration::ZERO));
+            }
+        }
+    }
+}
+
+impl AsyncRead for ResetOnDrop {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut tokio::io::ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ResetOnDrop {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[std::io::IoSlice<'_>]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+}
+
 use std::{
     future::Future,
     io,
     net::SocketAddr,
     pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
     task::{Context, Poll},
     time::Duration,
 };
@@ -88,6 +588,7 @@ pin_project! {
     /// - `inner`: 底层 IO 流
     /// - `timeout`: 超时时长
     /// - `idle_future`: 空闲计时器
+    /// - `timed_out`: 是否已经因空闲超时返回错误
     ///
     /// # 行为说明
     /// - 每次成功的读写操作都会重置计时器
@@ -103,7 +604,8 @@ pin_project! {
         inner: T,
         timeout:Duration,
         #[pin]
-        idle_future:Sleep
+        idle_future:Sleep,
+        timed_out: Arc<AtomicBool>,
     }
 }
 
@@ -136,9 +638,21 @@ where
             inner,
             timeout,
             idle_future: sleep(timeout),
+            timed_out: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// 获取空闲超时标记
+    ///
+    /// HTTP/1 连接在等待下一个请求时超时，hyper 会按连接正常关闭处理而不返回错误，
+    /// 把连接交给 hyper 之前取得该标记，连接结束后可以据此判断是否因空闲超时关闭
+    ///
+    /// # 返回
+    /// 已经因空闲超时返回过错误时为 true
+    pub fn timed_out(&self) -> Arc<AtomicBool> {
+        self.timed_out.clone()
+    }
+
     /// 设置新的超时时长
     ///
     /// # 参数
@@ -172,6 +686,7 @@ where
             // 读到内容或者读到 EOF 等等，重置计时
             idle_feature.reset(Instant::now() + *timeout);
         } else if idle_feature.poll(cx).is_ready() {
+            pro.timed_out.store(true, Ordering::Relaxed);
             // 没有读到内容，且已经 timeout，则返回错误
             return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, format!("read idle for {timeout:?}"))));
         }
@@ -198,6 +713,7 @@ where
         if write_poll.is_ready() {
             idle_feature.reset(Instant::now() + *timeout);
         } else if idle_feature.poll(cx).is_ready() {
+            pro.timed_out.store(true, Ordering::Relaxed);
             return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, format!("write idle for {timeout:?}"))));
         }
         write_poll
@@ -211,6 +727,7 @@ where
         if write_poll.is_ready() {
             idle_feature.reset(Instant::now() + *timeout);
         } else if idle_feature.poll(cx).is_ready() {
+            pro.timed_out.store(true, Ordering::Relaxed);
             return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, format!("write idle for {timeout:?}"))));
         }
         write_poll
@@ -224,6 +741,7 @@ where
         if write_poll.is_ready() {
             idle_feature.reset(Instant::now() + *timeout);
         } else if idle_feature.poll(cx).is_ready() {
+            pro.timed_out.store(true, Ordering::Relaxed);
             return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, format!("write idle for {timeout:?}"))));
         }
         write_poll
@@ -241,8 +759,202 @@ where
         if write_poll.is_ready() {
             idle_feature.reset(Instant::now() + *timeout);
         } else if idle_feature.poll(cx).is_ready() {
+            pro.timed_out.store(true, Ordering::Relaxed);
             return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, format!("write idle for {timeout:?}"))));
         }
         write_poll
     }
 }
+
+/// 请求头读取超时错误
+///
+/// HTTP/3 请求头读取超时时记录日志使用，HTTP/1 的请求头读取超时由 hyper 处理
+///
+/// # 字段
+/// - `0`: 请求头读取超时时间
+#[derive(Debug)]
+pub struct HeaderReadTimeout(pub Duration);
+
+impl std::fmt::Display for HeaderReadTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request head not received in {:?}", self.0)
+    }
+}
+
+impl std::error::Error for HeaderReadTimeout {}
+
+/// 连接读写的字节数
+///
+/// 由 [`CountingIO`] 更新，可以在连接关闭之后读取
+#[derive(Debug, Default)]
+pub struct ByteCount {
+    read: AtomicU64,
+    written: AtomicU64,
+}
+
+impl ByteCount {
+    /// 已读取的字节数
+    pub fn read(&self) -> u64 {
+        self.read.load(Ordering::Relaxed)
+    }
+
+    /// 已写入的字节数
+    pub fn written(&self) -> u64 {
+        self.written.load(Ordering::Relaxed)
+    }
+}
+
+pin_project! {
+    /// 统计读写字节数的 IO 包装器
+    ///
+    /// # 泛型参数
+    /// - `T`: 底层 IO 流类型，必须实现 `AsyncRead` 和 `AsyncWrite`
+    #[derive(Debug)]
+    pub struct CountingIO<T> {
+        #[pin]
+        inner: T,
+        count: Arc<ByteCount>,
+    }
+}
+
+impl<T> CountingIO<T> {
+    /// 创建新的 CountingIO 包装器
+    ///
+    /// # 返回
+    /// 包装后的 IO 流和共享的字节计数
+    pub fn new(inner: T) -> (Self, Arc<ByteCount>) {
+        let count = Arc::new(ByteCount::default());
+        (Self { inner, count: count.clone() }, count)
+    }
+}
+
+impl<T> AsyncRead for CountingIO<T>
+where
+    T: AsyncRead,
+{
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut tokio::io::ReadBuf<'_>) -> Poll<Result<(), std::io::Error>> {
+        let pro = self.project();
+        let filled = buf.filled().len();
+        let read_poll = pro.inner.poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = read_poll {
+            pro.count.read.fetch_add((buf.filled().len() - filled) as u64, Ordering::Relaxed);
+        }
+        read_poll
+    }
+}
+
+impl<T> AsyncWrite for CountingIO<T>
+where
+    T: AsyncWrite,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, std::io::Error>> {
+        let pro = self.project();
+        let write_poll = pro.inner.poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = write_poll {
+            pro.count.written.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        write_poll
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[std::io::IoSlice<'_>]) -> Poll<Result<usize, std::io::Error>> {
+        let pro = self.project();
+        let write_poll = pro.inner.poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(n)) = write_poll {
+            pro.count.written.fetch_add(n as u64, Ordering::Relaxed);
+        }
+        write_poll
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_listeners_from_fds() {
+        use std::os::fd::IntoRawFd;
+
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_listener.local_addr().unwrap();
+        let fd = std_listener.into_raw_fd();
+        let pid = std::process::id().to_string();
+
+        let listeners = listeners_from_fds(Some(&pid), Some("1"), Some("https"), fd).unwrap().unwrap();
+        assert_eq!(listeners.len(), 1);
+        let (listener, name) = &listeners[0];
+        assert_eq!(name, "https");
+        let Listener::Tcp(tcp) = listener else {
+            panic!("expect tcp listener");
+        };
+        assert_eq!(tcp.local_addr().unwrap(), addr);
+
+        let client = TcpStream::connect(addr).await.unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer, PeerAddr::Tcp(client.local_addr().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_reuse_port_listeners_share_port() {
+        let listeners = create_reuse_port_listeners("127.0.0.1:0".parse().unwrap(), 3, 16).unwrap();
+        assert_eq!(listeners.len(), 3);
+        let addr = listeners[0].local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+        assert!(listeners.iter().all(|listener| listener.local_addr().unwrap() == addr));
+
+        let _client = TcpStream::connect(addr).await.unwrap();
+        let accepted = futures_util::future::select_all(listeners.iter().map(|listener| Box::pin(listener.accept()))).await;
+        assert!(accepted.0.is_ok());
+    }
+
+    #[test]
+    fn test_listeners_from_fds_other_pid() {
+        assert!(listeners_from_fds(Some("1"), Some("1"), None, SD_LISTEN_FDS_START).unwrap().is_none());
+        assert!(listeners_from_fds(None, None, None, SD_LISTEN_FDS_START).unwrap().is_none());
+        let pid = std::process::id().to_string();
+        assert!(listeners_from_fds(Some(&pid), Some("x"), None, SD_LISTEN_FDS_START).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unix_listener_removes_stale_socket() {
+        let path = std::env::temp_dir().join(format!("axum-bootstrap-stale-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // 绑定后立即释放，遗留 socket 文件
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let listener = create_unix_listener(&path, Some(0o600)).unwrap();
+        let mode = std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(&path).unwrap().permissions());
+        assert_eq!(mode & 0o777, 0o600);
+
+        // 仍在监听的 socket 不会被删除
+        let err = create_unix_listener(&path, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unix_listener_refuses_regular_file() {
+        let path = std::env::temp_dir().join(format!("axum-bootstrap-regular-{}.sock", std::process::id()));
+        std::fs::write(&path, b"not a socket").unwrap();
+
+        let err = create_unix_listener(&path, None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(path.exists());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
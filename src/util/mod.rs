@@ -1,5 +1,6 @@
 pub mod extractor;
 pub(crate) mod format;
-pub(crate) mod io;
+pub mod io;
 pub mod json;
+pub mod proxy_protocol;
 pub(crate) mod tls;
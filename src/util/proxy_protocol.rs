@@ -0,0 +1,398 @@
+//! # PROXY protocol 模块
+//!
+//! 解析负载均衡器 (AWS NLB、HAProxy 等) 在连接开头发送的 PROXY protocol 头，获取真实的客户端地址
+//!
+//! # 主要组件
+//! - `ProxyProtocolMode`: 监听器的 PROXY protocol 模式 (必须 / 可选)
+//! - `ProxyHeader`: 解析出的源地址和目标地址
+//! - `read_proxy_header`: 从连接读取并解析 PROXY protocol 头
+//! - `Rewind`: 读取头部时多读的数据会通过该包装器重新交给 TLS / HTTP 处理
+//!
+//! # 支持的格式
+//! - v1 文本格式: `PROXY TCP4 192.0.2.1 192.0.2.2 56324 443\r\n`
+//! - v2 二进制格式: `PROXY` 命令的 TCP/UDP over IPv4/IPv6，`LOCAL` 命令 (健康检查) 不携带地址
+//!
+//! 参考: <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+
+/// v1 头部的前缀
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// v1 头部的最大长度 (包括 `\r\n`)
+const V1_MAX_LEN: usize = 107;
+
+/// v2 头部的签名
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// v2 头部固定部分的长度 (签名 + 版本/命令 + 地址族/协议 + 地址长度)
+const V2_FIXED_LEN: usize = 16;
+
+/// 监听器的 PROXY protocol 模式
+///
+/// # 变体
+/// - `Required`: 连接必须以 PROXY protocol 头开始，否则关闭连接
+/// - `Optional`: 有 PROXY protocol 头时使用其中的源地址，没有时按普通连接处理
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocolMode {
+    Required,
+    Optional,
+}
+
+/// 解析出的 PROXY protocol 头
+///
+/// # 字段
+/// - `version`: 协议版本，1 或 2
+/// - `source`: 真实的客户端地址，`UNKNOWN` / `LOCAL` / 非 IP 地址族时为 None
+/// - `destination`: 客户端连接的目标地址，`UNKNOWN` / `LOCAL` / 非 IP 地址族时为 None
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyHeader {
+    pub version: u8,
+    pub source: Option<SocketAddr>,
+    pub destination: Option<SocketAddr>,
+}
+
+/// 从连接读取并解析 PROXY protocol 头
+///
+/// # 参数
+/// - `stream`: 刚接受的连接
+/// - `mode`: PROXY protocol 模式
+///
+/// # 返回
+/// - `Ok((Rewind<S>, Some(ProxyHeader)))`: 解析成功，返回的连接从头部之后开始
+/// - `Ok((Rewind<S>, None))`: `Optional` 模式下没有 PROXY protocol 头，返回的连接从头开始
+/// - `Err(io::Error)`: 头部格式错误、`Required` 模式下没有头部，或连接在头部结束前关闭
+///
+/// # 示例
+///
+/// ```no_run
+/// use axum_bootstrap::util::proxy_protocol::{ProxyProtocolMode, read_proxy_header};
+/// use tokio::net::TcpStream;
+///
+/// async fn example(stream: TcpStream) -> std::io::Result<()> {
+///     let (stream, header) = read_proxy_header(stream, ProxyProtocolMode::Required).await?;
+///     if let Some(source) = header.and_then(|header| header.source) {
+///         println!("real client: {source}");
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn read_proxy_header<S>(mut stream: S, mode: ProxyProtocolMode) -> io::Result<(Rewind<S>, Option<ProxyHeader>)>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut buf = Vec::with_capacity(V1_MAX_LEN);
+    loop {
+        match parse(&buf)? {
+            Parsed::Header(header, len) => {
+                buf.drain(..len);
+                return Ok((Rewind::new(stream, buf), Some(header)));
+            }
+            Parsed::NotProxy => {
+                return match mode {
+                    ProxyProtocolMode::Required => Err(io::Error::new(io::ErrorKind::InvalidData, "missing proxy protocol header")),
+                    ProxyProtocolMode::Optional => Ok((Rewind::new(stream, buf), None)),
+                };
+            }
+            Parsed::Incomplete(need) => {
+                let start = buf.len();
+                buf.resize(start + need, 0);
+                let n = stream.read(&mut buf[start..]).await?;
+                buf.truncate(start + n);
+                if n == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed before proxy protocol header completed"));
+                }
+            }
+        }
+    }
+}
+
+/// 头部解析结果
+///
+/// # 变体
+/// - `Header`: 完整的头部及其长度
+/// - `NotProxy`: 不是 PROXY protocol 头
+/// - `Incomplete`: 数据不足，需要至少再读取的字节数
+#[derive(Debug, PartialEq, Eq)]
+enum Parsed {
+    Header(ProxyHeader, usize),
+    NotProxy,
+    Incomplete(usize),
+}
+
+/// 解析缓冲区开头的 PROXY protocol 头
+fn parse(buf: &[u8]) -> io::Result<Parsed> {
+    if is_prefix(buf, V2_SIGNATURE) {
+        if buf.len() < V2_FIXED_LEN {
+            return Ok(Parsed::Incomplete(V2_FIXED_LEN - buf.len()));
+        }
+        return parse_v2(buf);
+    }
+    if is_prefix(buf, V1_PREFIX) {
+        if buf.len() < V1_PREFIX.len() {
+            return Ok(Parsed::Incomplete(V1_PREFIX.len() - buf.len()));
+        }
+        return parse_v1(buf);
+    }
+    Ok(Parsed::NotProxy)
+}
+
+/// `buf` 与 `signature` 的公共部分是否一致 (`buf` 可能比 `signature` 短)
+fn is_prefix(buf: &[u8], signature: &[u8]) -> bool {
+    let len = buf.len().min(signature.len());
+    buf[..len] == signature[..len]
+}
+
+/// 解析 v1 文本头部
+fn parse_v1(buf: &[u8]) -> io::Result<Parsed> {
+    let Some(end) = buf.windows(2).position(|window| window == b"\r\n") else {
+        if buf.len() >= V1_MAX_LEN {
+            return Err(invalid("proxy protocol v1 header too long"));
+        }
+        return Ok(Parsed::Incomplete(V1_MAX_LEN - buf.len()));
+    };
+    let line = std::str::from_utf8(&buf[V1_PREFIX.len()..end]).map_err(|_| invalid("proxy protocol v1 header is not utf-8"))?;
+    let fields: Vec<&str> = line.split(' ').collect();
+    let (source, destination) = match fields.as_slice() {
+        ["UNKNOWN", ..] => (None, None),
+        [proto @ ("TCP4" | "TCP6"), src, dst, src_port, dst_port] => {
+            let ip = |s: &str| -> io::Result<IpAddr> {
+                let ip: IpAddr = s.parse().map_err(|_| invalid("invalid proxy protocol v1 address"))?;
+                match (*proto, ip) {
+                    ("TCP4", IpAddr::V4(_)) | ("TCP6", IpAddr::V6(_)) => Ok(ip),
+                    _ => Err(invalid("proxy protocol v1 address does not match protocol")),
+                }
+            };
+            let port = |s: &str| s.parse::<u16>().map_err(|_| invalid("invalid proxy protocol v1 port"));
+            (Some(SocketAddr::new(ip(src)?, port(src_port)?)), Some(SocketAddr::new(ip(dst)?, port(dst_port)?)))
+        }
+        _ => return Err(invalid("invalid proxy protocol v1 header")),
+    };
+    Ok(Parsed::Header(
+        ProxyHeader {
+            version: 1,
+            source,
+            destination,
+        },
+        end + 2,
+    ))
+}
+
+/// 解析 v2 二进制头部，`buf` 至少包含固定部分
+fn parse_v2(buf: &[u8]) -> io::Result<Parsed> {
+    let version_command = buf[12];
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported proxy protocol version"));
+    }
+    let len = V2_FIXED_LEN + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if buf.len() < len {
+        return Ok(Parsed::Incomplete(len - buf.len()));
+    }
+    let addresses = &buf[V2_FIXED_LEN..len];
+    let (source, destination) = match (version_command & 0x0f, buf[13] >> 4) {
+        // LOCAL: 负载均衡器自身发起的连接 (例如健康检查)，使用连接本身的地址
+        (0x0, _) => (None, None),
+        // PROXY over AF_INET
+        (0x1, 0x1) => {
+            if addresses.len() < 12 {
+                return Err(invalid("proxy protocol v2 ipv4 address block too short"));
+            }
+            let ip =
+                |offset: usize| IpAddr::V4(Ipv4Addr::new(addresses[offset], addresses[offset + 1], addresses[offset + 2], addresses[offset + 3]));
+            let port = |offset: usize| u16::from_be_bytes([addresses[offset], addresses[offset + 1]]);
+            (Some(SocketAddr::new(ip(0), port(8))), Some(SocketAddr::new(ip(4), port(10))))
+        }
+        // PROXY over AF_INET6
+        (0x1, 0x2) => {
+            if addresses.len() < 36 {
+                return Err(invalid("proxy protocol v2 ipv6 address block too short"));
+            }
+            let ip = |offset: usize| {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&addresses[offset..offset + 16]);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            let port = |offset: usize| u16::from_be_bytes([addresses[offset], addresses[offset + 1]]);
+            (Some(SocketAddr::new(ip(0), port(32))), Some(SocketAddr::new(ip(16), port(34))))
+        }
+        // PROXY over AF_UNSPEC / AF_UNIX: 没有可用的 IP 地址
+        (0x1, _) => (None, None),
+        _ => return Err(invalid("unsupported proxy protocol v2 command")),
+    };
+    Ok(Parsed::Header(
+        ProxyHeader {
+            version: 2,
+            source,
+            destination,
+        },
+        len,
+    ))
+}
+
+fn invalid(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 带有预读数据的连接
+///
+/// 读取时先返回预读的数据，再从底层连接读取；写入直接交给底层连接
+///
+/// # 泛型参数
+/// - `S`: 底层连接类型
+#[derive(Debug)]
+pub struct Rewind<S> {
+    prefix: Vec<u8>,
+    pos: usize,
+    inner: S,
+}
+
+impl<S> Rewind<S> {
+    /// 创建带有预读数据的连接
+    ///
+    /// # 参数
+    /// - `inner`: 底层连接
+    /// - `prefix`: 预读的数据
+    pub fn new(inner: S, prefix: Vec<u8>) -> Self {
+        Self { prefix, pos: 0, inner }
+    }
+
+    /// 获取底层连接的引用
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Rewind<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.pos < this.prefix.len() {
+            let n = buf.remaining().min(this.prefix.len() - this.pos);
+            buf.put_slice(&this.prefix[this.pos..this.pos + n]);
+            this.pos += n;
+            if this.pos == this.prefix.len() {
+                this.prefix = Vec::new();
+                this.pos = 0;
+            }
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Rewind<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[io::IoSlice<'_>]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write_vectored(cx, bufs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2_header(command: u8, family: u8, addresses: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(0x20 | command);
+        header.push(family);
+        header.extend_from_slice(&(addresses.len() as u16).to_be_bytes());
+        header.extend_from_slice(addresses);
+        header
+    }
+
+    #[test]
+    fn test_parse_v1() {
+        let header = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET";
+        let Parsed::Header(parsed, len) = parse(header).unwrap() else {
+            panic!("expect header");
+        };
+        assert_eq!(&header[len..], b"GET");
+        assert_eq!(parsed.source, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(parsed.destination, Some("198.51.100.1:443".parse().unwrap()));
+
+        let Parsed::Header(parsed, _) = parse(b"PROXY TCP6 2001:db8::1 2001:db8::2 1 2\r\n").unwrap() else {
+            panic!("expect header");
+        };
+        assert_eq!(parsed.source, Some("[2001:db8::1]:1".parse().unwrap()));
+
+        let Parsed::Header(parsed, _) = parse(b"PROXY UNKNOWN\r\n").unwrap() else {
+            panic!("expect header");
+        };
+        assert_eq!(parsed.source, None);
+
+        assert_eq!(parse(b"PROXY TCP4 192.0.2.1").unwrap(), Parsed::Incomplete(V1_MAX_LEN - 20));
+        assert_eq!(parse(b"PRO").unwrap(), Parsed::Incomplete(3));
+        assert!(parse(b"PROXY TCP4 2001:db8::1 192.0.2.1 1 2\r\n").is_err());
+        assert!(parse(b"PROXY TCP4 192.0.2.1 192.0.2.2 1\r\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let header = v2_header(0x1, 0x11, &[192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x01, 0xbb]);
+        let Parsed::Header(parsed, len) = parse(&header).unwrap() else {
+            panic!("expect header");
+        };
+        assert_eq!(len, header.len());
+        assert_eq!(parsed.version, 2);
+        assert_eq!(parsed.source, Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(parsed.destination, Some("198.51.100.1:443".parse().unwrap()));
+
+        let mut addresses = Ipv6Addr::LOCALHOST.octets().to_vec();
+        addresses.extend_from_slice(&Ipv6Addr::UNSPECIFIED.octets());
+        addresses.extend_from_slice(&[0, 80, 1, 187]);
+        let Parsed::Header(parsed, _) = parse(&v2_header(0x1, 0x21, &addresses)).unwrap() else {
+            panic!("expect header");
+        };
+        assert_eq!(parsed.source, Some("[::1]:80".parse().unwrap()));
+
+        let Parsed::Header(parsed, _) = parse(&v2_header(0x0, 0x00, &[])).unwrap() else {
+            panic!("expect header");
+        };
+        assert_eq!(parsed.source, None);
+
+        assert_eq!(parse(&header[..10]).unwrap(), Parsed::Incomplete(6));
+        assert_eq!(parse(&header[..20]).unwrap(), Parsed::Incomplete(8));
+        assert!(parse(&v2_header(0x1, 0x11, &[0; 4])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_proxy_header() {
+        let input: &[u8] = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET / HTTP/1.1\r\n";
+        let (mut stream, header) = read_proxy_header(input, ProxyProtocolMode::Required).await.unwrap();
+        assert_eq!(header.unwrap().source, Some("192.0.2.1:56324".parse().unwrap()));
+        let mut rest = String::new();
+        stream.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "GET / HTTP/1.1\r\n");
+
+        let input: &[u8] = b"GET / HTTP/1.1\r\n";
+        let (mut stream, header) = read_proxy_header(input, ProxyProtocolMode::Optional).await.unwrap();
+        assert!(header.is_none());
+        let mut rest = String::new();
+        stream.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "GET / HTTP/1.1\r\n");
+
+        let input: &[u8] = b"GET / HTTP/1.1\r\n";
+        assert!(read_proxy_header(input, ProxyProtocolMode::Required).await.is_err());
+    }
+}
@@ -4,7 +4,7 @@
 //!
 //! # 主要组件
 //! - `tls_config`: 从证书文件创建 TLS 配置
-//! - `TlsAcceptor`: TLS 连接接受器，支持动态配置更新
+//! - `TlsAcceptor`: 把已接受的连接包装为 TLS 流，支持动态配置更新
 //! - `TlsStream`: TLS 流处理，自动处理握手和数据传输
 //!
 //! # 支持的协议
@@ -12,7 +12,12 @@
 //! - HTTP/2 (h2)
 //! - HTTP/1.1
 
-use std::{io, net::SocketAddr, sync::Arc};
+use std::{io, sync::Arc};
+
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{server::ResolvesServerCert, sign::CertifiedKey};
+
+use crate::util::io::Stream;
 
 /// 从证书和私钥文件创建 TLS 服务器配置
 ///
@@ -33,14 +38,43 @@ use std::{io, net::SocketAddr, sync::Arc};
 /// ```no_run
 /// use axum_bootstrap::util::tls::tls_config;
 ///
-/// let config = tls_config(
-///     &"privkey.pem".to_string(),
-///     &"cert.pem".to_string()
-/// ).unwrap();
+/// let config = tls_config("privkey.pem", "cert.pem").unwrap();
 /// ```
-pub fn tls_config(key: &String, cert: &String) -> Result<Arc<ServerConfig>, std::io::Error> {
+pub fn tls_config(key: &str, cert: &str) -> Result<Arc<ServerConfig>, std::io::Error> {
+    let (certs, key) = load_pem(key, cert)?;
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(std::io::Error::other)?;
+    Ok(with_alpn(config))
+}
+
+/// 使用证书选择器创建 TLS 服务器配置，ALPN 与 [`tls_config`] 相同
+///
+/// # 参数
+/// - `resolver`: 按 ClientHello (例如 SNI) 选择证书的选择器
+pub(crate) fn tls_config_with_resolver(resolver: Arc<dyn ResolvesServerCert>) -> Arc<ServerConfig> {
+    // 安装默认加密提供者 (如果尚未设置)
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    with_alpn(ServerConfig::builder().with_no_client_auth().with_cert_resolver(resolver))
+}
+
+/// 从证书和私钥文件加载证书选择器使用的证书，私钥必须与证书匹配
+///
+/// # 参数
+/// - `key`: 私钥文件路径
+/// - `cert`: 证书文件路径（PEM 格式）
+pub(crate) fn certified_key(key: &str, cert: &str) -> Result<Arc<CertifiedKey>, std::io::Error> {
+    let (certs, key) = load_pem(key, cert)?;
+    let provider = rustls::crypto::CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::aws_lc_rs::default_provider()));
+    Ok(Arc::new(CertifiedKey::from_der(certs, key, &provider).map_err(std::io::Error::other)?))
+}
+
+/// 读取 PEM 格式的证书链和私钥
+fn load_pem(key: &str, cert: &str) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), std::io::Error> {
     use rustls_pki_types::pem::PemObject;
-    use rustls_pki_types::{CertificateDer, PrivateKeyDer};
 
     // 安装默认加密提供者 (如果尚未设置)
     let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
@@ -51,16 +85,16 @@ pub fn tls_config(key: &String, cert: &String) -> Result<Arc<ServerConfig>, std:
         .map_err(|_| io::Error::other("invalid cert pem"))?;
 
     let key = PrivateKeyDer::from_pem_file(key).map_err(|_| io::Error::other("failed to read private key"))?;
+    Ok((certs, key))
+}
 
-    let mut config = ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(certs, key)
-        .map_err(std::io::Error::other)?;
+/// 设置 ALPN 协议
+fn with_alpn(mut config: ServerConfig) -> Arc<ServerConfig> {
     config.alpn_protocols = vec![
         b"h2".to_vec(),       // HTTP/2
         b"http/1.1".to_vec(), // HTTP/1.1
     ];
-    Ok(Arc::new(config))
+    Arc::new(config)
 }
 
 /// 从证书和私钥文件创建 tokio_rustls TlsAcceptor
@@ -75,7 +109,7 @@ pub fn tls_config(key: &String, cert: &String) -> Result<Arc<ServerConfig>, std:
 /// - `Ok(tokio_rustls::TlsAcceptor)`: TLS 接受器
 /// - `Err(std::io::Error)`: 创建失败
 #[allow(dead_code)]
-pub fn rust_tls_acceptor(key: &String, cert: &String) -> Result<tokio_rustls::TlsAcceptor, std::io::Error> {
+pub fn rust_tls_acceptor(key: &str, cert: &str) -> Result<tokio_rustls::TlsAcceptor, std::io::Error> {
     Ok(tls_config(key, cert)?.into())
 }
 
@@ -84,25 +118,19 @@ use std::future::Future;
 use std::pin::Pin;
 
 use futures_util::ready;
-use tokio::{
-    io::{AsyncRead, AsyncWrite, ReadBuf},
-    net::{TcpListener, TcpStream},
-};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio_rustls::rustls::{ServerConfig, ServerConnection};
 
 /// TLS 连接接受器
 ///
-/// 用于 Hyper 服务器的 TLS 接受器，支持动态配置更新
-///
-/// # 泛型参数
-/// - `L`: 监听器类型，默认为 `TcpListener`
+/// 用于 Hyper 服务器的 TLS 接受器，支持动态配置更新。
+/// 只负责把已接受的连接包装为 [`TlsStream`]，因此可以在 TLS 之前处理 PROXY protocol 等前置数据
 ///
 /// # 字段
 /// - `config`: TLS 服务器配置
-/// - `listener`: TCP 监听器
-pub struct TlsAcceptor<L = TcpListener> {
+#[derive(Clone)]
+pub struct TlsAcceptor {
     config: Arc<ServerConfig>,
-    listener: L,
 }
 
 impl TlsAcceptor {
@@ -110,12 +138,11 @@ impl TlsAcceptor {
     ///
     /// # 参数
     /// - `config`: TLS 服务器配置
-    /// - `listener`: TCP 监听器
     ///
     /// # 返回
     /// 新创建的 TlsAcceptor 实例
-    pub fn new(config: Arc<ServerConfig>, listener: TcpListener) -> Self {
-        Self { config, listener }
+    pub fn new(config: Arc<ServerConfig>) -> Self {
+        Self { config }
     }
 
     /// 替换 TLS 配置
@@ -131,24 +158,26 @@ impl TlsAcceptor {
         self.config = new_config;
     }
 
-    /// 接受新的 TLS 连接
+    /// 将连接包装为 TLS 流
+    ///
+    /// 握手在首次读写时进行，不会阻塞调用方
+    ///
+    /// # 参数
+    /// - `stream`: 已接受的连接
     ///
     /// # 返回
-    /// - `Ok((TlsStream, SocketAddr))`: 成功接受连接，返回 TLS 流和客户端地址
-    /// - `Err(io::Error)`: 接受连接失败
-    pub async fn accept(&mut self) -> Result<(TlsStream, SocketAddr), io::Error> {
-        let (sock, addr) = self.listener.accept().await?;
-        Ok((TlsStream::new(sock, self.config.clone()), addr))
+    /// 使用当前配置的 TLS 流
+    pub fn accept<C: AsyncRead + AsyncWrite + Unpin>(&self, stream: C) -> TlsStream<C> {
+        TlsStream::new(stream, self.config.clone())
     }
 }
 
-impl<C, L> From<(C, L)> for TlsAcceptor
+impl<C> From<C> for TlsAcceptor
 where
     C: Into<Arc<ServerConfig>>,
-    L: Into<TcpListener>,
 {
-    fn from((config, listener): (C, L)) -> Self {
-        Self::new(config.into(), listener.into())
+    fn from(config: C) -> Self {
+        Self::new(config.into())
     }
 }
 
@@ -167,8 +196,8 @@ where
 /// TlsStream 实现 AsyncRead/AsyncWrite，首次操作时自动完成握手。
 ///
 /// # 泛型参数
-/// - `C`: 底层连接类型，默认为 `TcpStream`
-pub struct TlsStream<C = TcpStream> {
+/// - `C`: 底层连接类型，默认为 [`Stream`] (TCP 或 Unix domain socket)
+pub struct TlsStream<C = Stream> {
     state: State<C>,
 }
 
@@ -176,7 +205,7 @@ impl<C: AsyncRead + AsyncWrite + Unpin> TlsStream<C> {
     /// 创建新的 TLS 流
     ///
     /// # 参数
-    /// - `stream`: 底层 TCP 流
+    /// - `stream`: 底层连接
     /// - `config`: TLS 配置
     fn new(stream: C, config: Arc<ServerConfig>) -> Self {
         let accept = tokio_rustls::TlsAcceptor::from(config).accept(stream);
@@ -185,6 +214,21 @@ impl<C: AsyncRead + AsyncWrite + Unpin> TlsStream<C> {
         }
     }
 
+    /// 完成 TLS 握手
+    ///
+    /// 首次读写时会自动握手，提前调用可以为握手单独设置超时
+    ///
+    /// # 返回
+    /// - `Ok(())`: 握手完成
+    /// - `Err(io::Error)`: 握手失败
+    pub async fn handshake(&mut self) -> io::Result<()> {
+        if let State::Handshaking(accept) = &mut self.state {
+            let stream = accept.await?;
+            self.state = State::Streaming(stream);
+        }
+        Ok(())
+    }
+
     /// 获取底层 IO 流的引用
     ///
     /// # 返回
@@ -205,7 +249,7 @@ impl<C: AsyncRead + AsyncWrite + Unpin> TlsStream<C> {
     /// # 返回
     /// - `Some(&ServerConnection)`: 握手完成后的 TLS 连接
     /// - `None`: 握手尚未完成
-    pub fn _connection(&self) -> Option<&ServerConnection> {
+    pub fn connection(&self) -> Option<&ServerConnection> {
         match &self.state {
             State::Handshaking(_) => None,
             State::Streaming(stream) => Some(stream.get_ref().1),
@@ -0,0 +1,462 @@
+//! # 虚拟主机模块
+//!
+//! 一个服务器为多个域名提供服务，按请求的主机名把请求分派到不同的 `Router`
+//!
+//! # 主要组件
+//! - `VirtualHost`: 虚拟主机的路由和可选的独立证书
+//! - `VirtualHosts`: 主机名模式到虚拟主机的映射、默认路由和未知主机的响应状态码
+//!
+//! # 主机名模式
+//! - `example.com`: 精确匹配，不区分大小写
+//! - `*.example.com`: 匹配 `example.com` 的所有子域名 (不包括 `example.com` 本身)
+//!
+//! 精确匹配优先，多个通配符都能匹配时后缀最长的优先
+//!
+//! # 选择规则
+//! 主机名按 [`Host`](crate::util::extractor::Host) extractor 的规则取自 `Host` 头或 HTTP/2、HTTP/3 的 `:authority`
+//! (去掉端口)，请求中没有主机名时使用 TLS 握手的 SNI。
+//! TLS 连接的 SNI 和请求的主机名选中不同的虚拟主机，且其中任一虚拟主机使用独立证书时，
+//! 连接的证书不属于请求的主机，返回 421
+
+use std::{
+    convert::Infallible,
+    io,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    Router,
+    extract::Request,
+    response::{IntoResponse, Response},
+    routing::future::RouteFuture,
+};
+use futures_util::future::Either;
+use hyper::StatusCode;
+use log::debug;
+use tokio_rustls::rustls::{
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use tower::Service;
+
+use crate::{
+    TlsParam,
+    connection::ConnectionInfo,
+    util::{extractor::request_host, tls::certified_key},
+};
+
+/// 虚拟主机
+///
+/// # 字段
+/// - `router`: 该主机的路由
+/// - `tls`: 该主机的独立证书 (可选)，TLS 握手时按 SNI 选择；为 None 时使用服务器的证书。
+///   只使用其中的 `cert` 和 `key`，是否启用 TLS 由服务器的 [`TlsParam`] 决定
+#[derive(Debug, Clone)]
+pub struct VirtualHost {
+    pub router: Router,
+    pub tls: Option<TlsParam>,
+}
+
+impl VirtualHost {
+    /// 创建使用服务器证书的虚拟主机
+    pub fn new(router: Router) -> Self {
+        Self { router, tls: None }
+    }
+
+    /// 设置该主机的独立证书
+    pub fn with_tls_param(mut self, tls: TlsParam) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+}
+
+impl From<Router> for VirtualHost {
+    fn from(router: Router) -> Self {
+        Self::new(router)
+    }
+}
+
+/// 虚拟主机映射
+///
+/// 通过 [`Server::with_virtual_hosts`](crate::Server::with_virtual_hosts) 设置后替换服务器的路由。
+/// 没有匹配的虚拟主机时使用默认路由，没有默认路由时返回未知主机的状态码 (默认 421)
+///
+/// # 示例
+///
+/// ```no_run
+/// use axum::{Router, routing::get};
+/// use axum_bootstrap::{
+///     TlsParam, generate_shutdown_receiver, new_server,
+///     vhost::{VirtualHost, VirtualHosts},
+/// };
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let hosts = VirtualHosts::new()
+///         .with_host("api.example.com", Router::new().route("/", get(|| async { "api" })))
+///         .with_host("*.example.com", Router::new().route("/", get(|| async { "wildcard" })))
+///         .with_host(
+///             "example.org",
+///             VirtualHost::new(Router::new().route("/", get(|| async { "org" }))).with_tls_param(TlsParam {
+///                 tls: true,
+///                 cert: "example.org.pem".to_string(),
+///                 key: "example.org.key".to_string(),
+///             }),
+///         )
+///         .with_default(Router::new().route("/", get(|| async { "default" })));
+///     let server = new_server(443, Router::new(), generate_shutdown_receiver())
+///         .with_tls_param(Some(TlsParam {
+///             tls: true,
+///             cert: "example.com.pem".to_string(),
+///             key: "example.com.key".to_string(),
+///         }))
+///         .with_virtual_hosts(hosts);
+///     server.run().await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct VirtualHosts {
+    hosts: Vec<(String, VirtualHost)>,
+    default: Option<Router>,
+    unknown_host_status: StatusCode,
+}
+
+impl Default for VirtualHosts {
+    fn default() -> Self {
+        Self {
+            hosts: Vec::new(),
+            default: None,
+            unknown_host_status: StatusCode::MISDIRECTED_REQUEST,
+        }
+    }
+}
+
+impl VirtualHosts {
+    /// 创建空的虚拟主机映射
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加虚拟主机
+    ///
+    /// # 参数
+    /// - `pattern`: 主机名模式，格式见模块文档，重复的模式以后添加的为准
+    /// - `host`: 虚拟主机，可以直接传入 `Router`
+    pub fn with_host(mut self, pattern: impl AsRef<str>, host: impl Into<VirtualHost>) -> Self {
+        let pattern = normalize(pattern.as_ref());
+        self.hosts.retain(|(existing, _)| *existing != pattern);
+        self.hosts.push((pattern, host.into()));
+        self
+    }
+
+    /// 设置没有匹配的虚拟主机时使用的路由
+    pub fn with_default(mut self, router: Router) -> Self {
+        self.default = Some(router);
+        self
+    }
+
+    /// 设置没有匹配的虚拟主机且没有默认路由时的响应状态码
+    ///
+    /// # 参数
+    /// - `status`: 响应状态码，默认为 421 Misdirected Request，也可以使用 404 等
+    pub fn with_unknown_host_status(mut self, status: StatusCode) -> Self {
+        self.unknown_host_status = status;
+        self
+    }
+
+    /// 拆分为分派请求的路由和各虚拟主机的独立证书
+    pub(crate) fn into_parts(self) -> (Router, Vec<(String, TlsParam)>) {
+        let certificates = self
+            .hosts
+            .iter()
+            .filter_map(|(pattern, host)| Some((pattern.clone(), host.tls.clone()?)))
+            .collect();
+        let service = VirtualHostService {
+            hosts: self
+                .hosts
+                .into_iter()
+                .map(|(pattern, host)| {
+                    let own_certificate = host.tls.is_some();
+                    (
+                        pattern,
+                        HostRoute {
+                            router: host.router,
+                            own_certificate,
+                        },
+                    )
+                })
+                .collect(),
+            default: self.default,
+            unknown_host_status: self.unknown_host_status,
+        };
+        (Router::new().fallback_service(service), certificates)
+    }
+}
+
+/// 按主机名分派请求的服务
+///
+/// # 字段
+/// - `hosts`: 主机名模式和对应的路由
+/// - `default`: 默认路由
+/// - `unknown_host_status`: 未知主机的响应状态码
+#[derive(Clone)]
+struct VirtualHostService {
+    hosts: Arc<[(String, HostRoute)]>,
+    default: Option<Router>,
+    unknown_host_status: StatusCode,
+}
+
+/// 虚拟主机的路由
+///
+/// # 字段
+/// - `router`: 该主机的路由
+/// - `own_certificate`: 是否使用独立证书
+struct HostRoute {
+    router: Router,
+    own_certificate: bool,
+}
+
+impl VirtualHostService {
+    /// 为请求选择路由
+    ///
+    /// # 返回
+    /// - `Ok(Router)`: 匹配的虚拟主机或默认路由
+    /// - `Err(StatusCode)`: 没有匹配的路由，或 SNI 与主机名选中的虚拟主机冲突
+    fn route(&self, req: &Request) -> Result<Router, StatusCode> {
+        let host = request_host(req.headers(), req.uri()).map(|host| normalize(strip_port(host)));
+        let sni = req
+            .extensions()
+            .get::<ConnectionInfo>()
+            .and_then(|info| info.tls.as_ref())
+            .and_then(|tls| tls.server_name.as_deref())
+            .map(normalize);
+        let selected = match (&host, &sni) {
+            (Some(host), Some(sni)) => {
+                let (by_host, by_sni) = (select(&self.hosts, host), select(&self.hosts, sni));
+                let own_certificate = |index: Option<usize>| index.is_some_and(|index| self.hosts[index].1.own_certificate);
+                if by_host != by_sni && (own_certificate(by_host) || own_certificate(by_sni)) {
+                    debug!("[vhost]: host {host} does not match sni {sni}");
+                    return Err(StatusCode::MISDIRECTED_REQUEST);
+                }
+                by_host
+            }
+            (Some(name), None) | (None, Some(name)) => select(&self.hosts, name),
+            (None, None) => None,
+        };
+        match selected {
+            Some(index) => Ok(self.hosts[index].1.router.clone()),
+            None => self.default.clone().ok_or(self.unknown_host_status),
+        }
+    }
+}
+
+impl Service<Request> for VirtualHostService {
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Either<std::future::Ready<Result<Response, Infallible>>, RouteFuture<Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        match self.route(&req) {
+            Ok(mut router) => Either::Right(router.call(req)),
+            Err(status) => Either::Left(std::future::ready(Ok(status.into_response()))),
+        }
+    }
+}
+
+/// 按 SNI 选择证书的证书选择器
+///
+/// 没有 SNI 或没有匹配的虚拟主机时使用服务器的证书
+#[derive(Debug)]
+pub(crate) struct SniResolver {
+    default: Arc<CertifiedKey>,
+    hosts: Vec<(String, Arc<CertifiedKey>)>,
+}
+
+impl SniResolver {
+    /// 加载服务器证书和各虚拟主机的独立证书
+    ///
+    /// # 参数
+    /// - `default`: 服务器的 TLS 参数
+    /// - `hosts`: 主机名模式和对应的 TLS 参数
+    ///
+    /// # 返回
+    /// - `Ok(SniResolver)`: 证书选择器
+    /// - `Err(io::Error)`: 任一证书或私钥加载失败
+    pub(crate) fn load(default: &TlsParam, hosts: &[(String, TlsParam)]) -> io::Result<Self> {
+        let hosts = hosts
+            .iter()
+            .map(|(pattern, tls)| {
+                certified_key(&tls.key, &tls.cert)
+                    .map(|key| (pattern.clone(), key))
+                    .map_err(|e| io::Error::new(e.kind(), format!("load certificate of {pattern}: {e}")))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            default: certified_key(&default.key, &default.cert)?,
+            hosts,
+        })
+    }
+}
+
+impl ResolvesServerCert for SniResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let selected = client_hello.server_name().and_then(|name| select(&self.hosts, &normalize(name)));
+        Some(selected.map_or(&self.default, |index| &self.hosts[index].1).clone())
+    }
+}
+
+/// 选择与主机名匹配的模式
+///
+/// # 参数
+/// - `entries`: 主机名模式及其关联的值
+/// - `host`: 经过 [`normalize`] 处理的主机名
+///
+/// # 返回
+/// 精确匹配的模式，或后缀最长的通配符模式的下标
+fn select<T>(entries: &[(String, T)], host: &str) -> Option<usize> {
+    entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (pattern, _))| {
+            let specificity = match pattern.strip_prefix("*.") {
+                Some(suffix) => {
+                    host.strip_suffix(suffix).filter(|rest| rest.ends_with('.'))?;
+                    suffix.len()
+                }
+                None if pattern == host => usize::MAX,
+                None => return None,
+            };
+            Some((specificity, index))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, index)| index)
+}
+
+/// 主机名转换为小写并去掉末尾的 `.`
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// 去掉主机名中的端口，IPv6 地址保留方括号
+fn strip_port(host: &str) -> &str {
+    match host.strip_prefix('[') {
+        Some(rest) => rest.find(']').map_or(host, |end| &host[..end + 2]),
+        None => host.rsplit_once(':').map_or(host, |(hostname, _)| hostname),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::{connection::TlsInfo, util::io::PeerAddr};
+
+    #[test]
+    fn test_select() {
+        let entries = [
+            ("*.example.com".to_string(), ()),
+            ("*.api.example.com".to_string(), ()),
+            ("api.example.com".to_string(), ()),
+        ];
+        assert_eq!(select(&entries, "www.example.com"), Some(0));
+        assert_eq!(select(&entries, "a.b.example.com"), Some(0));
+        assert_eq!(select(&entries, "v1.api.example.com"), Some(1));
+        assert_eq!(select(&entries, "api.example.com"), Some(2));
+        assert_eq!(select(&entries, "example.com"), None);
+        assert_eq!(select(&entries, "badexample.com"), None);
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+    }
+
+    #[tokio::test]
+    async fn test_sni_and_host() {
+        let tls = |server_name: &str| TlsParam {
+            tls: true,
+            cert: format!("{server_name}.pem"),
+            key: format!("{server_name}.key"),
+        };
+        let (router, certificates) = VirtualHosts::new()
+            .with_host("a.example.com", Router::new().route("/", get(|| async { "a" })))
+            .with_host("b.example.com", Router::new().route("/", get(|| async { "b" })))
+            .with_host("example.org", VirtualHost::new(Router::new().route("/", get(|| async { "org" }))).with_tls_param(tls("example.org")))
+            .into_parts();
+        assert_eq!(certificates.len(), 1);
+        assert_eq!(certificates[0].0, "example.org");
+
+        let request = |host: Option<&str>, sni: Option<&str>| {
+            let mut builder = Request::builder().uri("/");
+            if let Some(host) = host {
+                builder = builder.header(hyper::header::HOST, host);
+            }
+            let mut request = builder.body(axum::body::Body::empty()).unwrap();
+            let mut info = ConnectionInfo::new(None, PeerAddr::Tcp(([127, 0, 0, 1], 1234).into()));
+            info.tls = Some(Arc::new(TlsInfo {
+                version: None,
+                cipher_suite: None,
+                server_name: sni.map(String::from),
+                alpn_protocol: None,
+                peer_certificates: Vec::new(),
+            }));
+            request.extensions_mut().insert(info);
+            request
+        };
+        let body = |response: Response| async move {
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, String::from_utf8(body.to_vec()).unwrap())
+        };
+        let call = |req: Request| {
+            let router = router.clone();
+            async move { body(tower::ServiceExt::oneshot(router, req).await.unwrap()).await }
+        };
+
+        // 请求中没有主机名时使用 SNI
+        assert_eq!(call(request(None, Some("a.example.com"))).await, (StatusCode::OK, "a".into()));
+        // 共用服务器证书的虚拟主机之间按主机名选择
+        assert_eq!(call(request(Some("B.example.com:443"), Some("a.example.com"))).await, (StatusCode::OK, "b".into()));
+        // 使用独立证书的虚拟主机要求 SNI 与主机名一致
+        assert_eq!(call(request(Some("example.org"), Some("a.example.com"))).await.0, StatusCode::MISDIRECTED_REQUEST);
+        assert_eq!(call(request(Some("a.example.com"), Some("example.org"))).await.0, StatusCode::MISDIRECTED_REQUEST);
+        assert_eq!(call(request(Some("example.org"), Some("example.org."))).await, (StatusCode::OK, "org".into()));
+    }
+
+    #[tokio::test]
+    async fn test_virtual_hosts() {
+        let (_shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
+        let hosts = VirtualHosts::new()
+            .with_host("api.example.com", Router::new().route("/", get(|| async { "api" })))
+            .with_host("*.example.com", Router::new().route("/", get(|| async { "wildcard" })))
+            .with_unknown_host_status(StatusCode::NOT_FOUND);
+        let (handle, addr) = crate::spawn_loopback(crate::new_server(0, Router::new(), shutdown_rx).with_virtual_hosts(hosts)).await;
+
+        for (host, expected) in [
+            ("api.example.com", "200 OK"),
+            ("www.example.com:8080", "200 OK"),
+            ("example.com", "404 Not Found"),
+        ] {
+            let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+            client
+                .write_all(format!("GET / HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            assert!(response.starts_with(&format!("HTTP/1.1 {expected}")), "{host}: {response}");
+            if host.starts_with("api") {
+                assert!(response.ends_with("\r\n\r\napi"));
+            } else if host.starts_with("www") {
+                assert!(response.ends_with("\r\n\r\nwildcard"));
+            }
+        }
+
+        handle.stop().await;
+    }
+}